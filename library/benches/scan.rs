@@ -0,0 +1,97 @@
+use std::path::Path;
+use std::time::Instant;
+
+use library::config::ConfigInner;
+use library::context::Context;
+use library::obsidian::task_file::TaskFilter;
+
+/*
+    `cargo bench -p library --bench scan` times reading the tasks of a
+    generated vault of 10k notes, on one thread and on the default number of
+    threads, without the index and then with it. NOTES and FOLDERS change its
+    size, like `NOTES=50000 cargo bench ...`.
+*/
+fn main() {
+    let notes = variable("NOTES", 10_000);
+    let folders = variable("FOLDERS", 100);
+    let vault = std::env::temp_dir().join(format!("obsidian-jira-bench-{}", notes));
+    generate_vault(&vault, notes, folders);
+    println!(
+        "{} notes in {} folders, in {}",
+        notes,
+        folders,
+        vault.display()
+    );
+
+    for threads in [1, 0] {
+        let label = match threads {
+            1 => String::from("1 thread"),
+            _ => String::from("default threads"),
+        };
+        let _ = std::fs::remove_file(vault.join(".obsidian-jira/index.json"));
+        let context = context_for(&vault);
+        time(&format!("{}, no index", label), || scan(&context, threads));
+        time(&format!("{}, with the index", label), || {
+            scan(&context_for(&vault), threads)
+        });
+    }
+}
+
+fn variable(name: &str, default: usize) -> usize {
+    std::env::var(name)
+        .ok()
+        .and_then(|x| x.parse().ok())
+        .unwrap_or(default)
+}
+
+fn time(label: &str, function: impl FnOnce() -> usize) {
+    let start = Instant::now();
+    let tasks = function();
+    println!("{:<32} {:>8.1?}  ({} tasks)", label, start.elapsed(), tasks);
+}
+
+fn scan(context: &Context, threads: usize) -> usize {
+    let mut filter = TaskFilter::new();
+    filter.set_path("Projects");
+    if threads > 0 {
+        filter.set_max_threads(threads);
+    }
+    filter.get_tasks(context).len()
+}
+
+fn context_for(vault: &Path) -> Context {
+    let config = ConfigInner::parse(&format!(
+        "jira_url: mycompany\nuser_mail: me@mycompany.com\nuser_id: '1234'\njira_token: secret\nvault_path: {}\nproject_path: Projects\n",
+        vault.display()
+    ))
+    .unwrap();
+    Context::new(config)
+}
+
+// Kept between runs, only written when the number of notes changes
+fn generate_vault(vault: &Path, notes: usize, folders: usize) {
+    let marker = vault.join(".generated");
+    if std::fs::read_to_string(&marker).is_ok_and(|x| x == format!("{} {}", notes, folders)) {
+        return;
+    }
+    let _ = std::fs::remove_dir_all(vault);
+    for note in 0..notes {
+        let folder = vault
+            .join("Projects")
+            .join(format!("Area {}", note % 10))
+            .join(format!("Folder {}", note % folders.max(1)));
+        std::fs::create_dir_all(&folder).unwrap();
+        std::fs::write(
+            folder.join(format!("Task {}.md", note)),
+            format!(
+                "---\npriority: {}\nstatus: To Do\nsprints:\n  - Y24W{}\nremaining time: '{}:30'\ntags: [generated]\n---\nSome text about task {}.\n\n- [ ] A step\n- [x] Another step\n",
+                note % 5 + 1,
+                note % 52 + 1,
+                note % 8,
+                note
+            ),
+        )
+        .unwrap();
+    }
+    std::fs::write(marker, format!("{} {}", notes, folders)).unwrap();
+}
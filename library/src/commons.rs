@@ -20,6 +20,40 @@ impl From<&str> for DateTime {
     }
 }
 
+impl DateTime {
+    pub fn new(date_time: time::OffsetDateTime) -> Self {
+        DateTime(date_time)
+    }
+
+    // The inverse of the From<&str>, as Jira wants it
+    pub fn to_jira_string(&self) -> String {
+        let offset = self.0.offset();
+        format!(
+            "{}T{:0>2}:{:0>2}:{:0>2}.{:0>3}{}{:0>2}{:0>2}",
+            self.0.date(),
+            self.0.hour(),
+            self.0.minute(),
+            self.0.second(),
+            self.0.millisecond(),
+            if offset.is_negative() { '-' } else { '+' },
+            offset.whole_hours().abs(),
+            offset.minutes_past_hour().abs()
+        )
+    }
+}
+
+impl std::fmt::Display for DateTime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {:0>2}:{:0>2}",
+            self.0.date(),
+            self.0.hour(),
+            self.0.minute()
+        )
+    }
+}
+
 #[derive(Deserialize, Serialize, Clone, Debug)]
 #[serde(from = "&str")]
 #[serde(into = "String")]
@@ -43,6 +77,10 @@ impl Date {
     pub fn new(date: time::Date) -> Self {
         Date(date)
     }
+
+    pub fn get_date(&self) -> time::Date {
+        self.0
+    }
 }
 
 pub struct DateIterator {
@@ -77,7 +115,8 @@ impl Into<String> for Date {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+// Ordered from the lowest to the highest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Priority {
     VeryLow,
     Low,
@@ -87,40 +126,50 @@ pub enum Priority {
 }
 
 impl Priority {
+    // The number written in the notes, 1 is the highest
+    pub fn from_number(number: u8) -> Option<Self> {
+        use Priority::*;
+        match number {
+            1 => Some(VeryHigh),
+            2 => Some(High),
+            3 => Some(Medium),
+            4 => Some(Low),
+            5 => Some(VeryLow),
+            _ => None,
+        }
+    }
+
+    pub fn to_number(&self) -> u8 {
+        use Priority::*;
+        match self {
+            VeryLow => 5,
+            Low => 4,
+            Medium => 3,
+            High => 2,
+            VeryHigh => 1,
+        }
+    }
+
     pub fn deserialize_from_number<'de, D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: Deserializer<'de>,
     {
-        let input: u8 = Deserialize::deserialize(deserializer).unwrap();
+        let input: u8 = Deserialize::deserialize(deserializer)?;
         /*
         let input = serde_json::value::Number::deserialize(deserializer)
             .unwrap()
             .as_u64()
             .unwrap();
         */
-        use Priority::*;
-        Ok(match input {
-            1 => VeryHigh,
-            2 => High,
-            3 => Medium,
-            4 => Low,
-            5 => VeryLow,
-            _ => panic!(),
+        Self::from_number(input).ok_or_else(|| {
+            serde::de::Error::custom(format!("priority {} is not between 1 and 5", input))
         })
     }
     pub fn serialize_to_number<S: Serializer>(
         input: &Self,
         serializer: S,
     ) -> Result<S::Ok, S::Error> {
-        use Priority::*;
-        let number = match input {
-            VeryLow => 5,
-            Low => 4,
-            Medium => 3,
-            High => 2,
-            VeryHigh => 1,
-        };
-        serializer.serialize_u8(number)
+        serializer.serialize_u8(input.to_number())
     }
 
     pub fn deserialize_from_jira_field<'de, D>(deserializer: D) -> Result<Self, D::Error>
@@ -154,7 +203,7 @@ impl Priority {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Copy, Clone)]
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq, Eq)]
 pub enum Status {
     #[serde(rename = "To Do")]
     ToDo,
@@ -165,6 +214,25 @@ pub enum Status {
 }
 
 impl Status {
+    // As written in the notes, like `In Progress`
+    pub fn get_name(&self) -> &'static str {
+        use Status::*;
+        match self {
+            ToDo => "To Do",
+            InProgress => "In Progress",
+            Blocked => "Blocked",
+            Done => "Done",
+        }
+    }
+
+    // Ignoring the case
+    pub fn from_name(name: &str) -> Option<Self> {
+        use Status::*;
+        [ToDo, InProgress, Blocked, Done]
+            .into_iter()
+            .find(|x| x.get_name().eq_ignore_ascii_case(name.trim()))
+    }
+
     pub fn deserialize_from_jira<'de, D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: Deserializer<'de>,
@@ -185,15 +253,38 @@ impl Status {
     }
 }
 
-#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
 pub enum IssueType {
     Story,
     Task,
+    #[serde(alias = "Sub-task")]
     SubTask,
     Epic,
 }
 
 impl IssueType {
+    pub fn get_name(&self) -> &'static str {
+        use IssueType::*;
+        match self {
+            Story => "Story",
+            Task => "Task",
+            SubTask => "Sub-task",
+            Epic => "Epic",
+        }
+    }
+
+    // Ignoring the case, `SubTask` is also accepted like in the notes
+    pub fn from_name(name: &str) -> Option<Self> {
+        use IssueType::*;
+        let name = name.trim();
+        if name.eq_ignore_ascii_case("subtask") {
+            return Some(SubTask);
+        }
+        [Story, Task, SubTask, Epic]
+            .into_iter()
+            .find(|x| x.get_name().eq_ignore_ascii_case(name))
+    }
+
     pub fn deserialize_from_jira<'de, D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: Deserializer<'de>,
@@ -270,6 +361,10 @@ impl Sprint {
         Self(name)
     }
 
+    pub fn get_name(&self) -> &str {
+        &self.0
+    }
+
     pub fn deserialize_sprint_vec_from_jira<'de, D>(
         deserializer: D,
     ) -> Result<Vec<Sprint>, D::Error>
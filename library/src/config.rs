@@ -1,68 +1,999 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
 use serde::Deserialize;
 use serde_yaml;
 
+use crate::credentials::{LazyToken, Secret, TokenSource};
+use crate::obsidian::planner::PlannerSettings;
+use crate::obsidian::query::Query;
+use crate::obsidian::settings::VaultSettings;
+
 pub static CONFIG: Config = Config {
     lock: std::sync::OnceLock::new(),
 };
 
+pub const CONFIG_ENV_VAR: &str = "OBSIDIAN_JIRA_CONFIG";
+pub const PROFILE_ENV_VAR: &str = "OBSIDIAN_JIRA_PROFILE";
+const CONFIG_FILE_NAME: &str = "config.yaml";
+const VAULT_CONFIG_FOLDER: &str = ".obsidian-jira";
+
 pub struct Config {
     lock: std::sync::OnceLock<ConfigInner>,
 }
 
 impl Config {
-    fn get_inner(&self) -> &ConfigInner {
-        &self.lock.get_or_init(|| ConfigInner::read_config_file())
+    pub fn get_inner(&self) -> &ConfigInner {
+        self.lock
+            .get_or_init(|| match ConfigInner::load(None, None) {
+                Ok(config) => config,
+                Err(error) => panic!("{}", error),
+            })
+    }
+
+    // Loads the config from an explicit path, or from the first location found.
+    // Has to be called before anything reads the config to have any effect.
+    pub fn load(
+        &self,
+        explicit_path: Option<&Path>,
+        profile: Option<&str>,
+    ) -> Result<(), ConfigError> {
+        let config = ConfigInner::load(explicit_path, profile)?;
+        let _ = self.lock.set(config);
+        Ok(())
     }
 
     pub fn get_jira_url(&self) -> &str {
-        &self.get_inner().jira_url
+        self.get_inner().get_jira_url()
     }
 
     pub fn get_week(&self) -> Option<u8> {
-        self.get_inner().week
+        self.get_inner().get_week()
     }
 
     pub fn get_user_id(&self) -> &str {
-        &self.get_inner().user_id
+        self.get_inner().get_user_id()
     }
 
     pub fn get_user_mail(&self) -> &str {
-        &self.get_inner().user_mail
+        self.get_inner().get_user_mail()
     }
 
-    pub fn get_jira_token(&self) -> &str {
-        &self.get_inner().jira_token
+    pub fn get_jira_token(&self) -> Result<&str, String> {
+        self.get_inner().get_jira_token()
     }
 
     pub fn get_vault_path(&self) -> &str {
-        &self.get_inner().vault_path
+        self.get_inner().get_vault_path()
     }
 
     pub fn get_daily_notes_path(&self) -> &str {
-        &self.get_inner().daily_notes_path
+        self.get_inner().get_daily_notes_path()
     }
 
     pub fn get_project_path(&self) -> &str {
-        &self.get_inner().project_path
+        self.get_inner().get_project_path()
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct ConfigInner {
     jira_url: String,
     user_mail: String,
     user_id: String,
-    jira_token: String,
+    // Read from the token source once the file is valid, or later when that
+    // would ask for a passphrase
+    #[serde(skip)]
+    jira_token: LazyToken,
     vault_path: String,
     daily_notes_path: String,
     project_path: String,
     #[serde(default)]
+    templates_path: Option<String>,
+    #[serde(default)]
+    attachments_path: Option<String>,
+    // Moment.js pattern, like in the settings of Obsidian's daily notes
+    #[serde(default)]
+    daily_note_format: Option<String>,
+    // Excluded files from the settings of Obsidian
+    #[serde(skip)]
+    ignore_filters: Vec<String>,
+    #[serde(default)]
     week: Option<u8>,
+    // Saved task queries by name, see `obsidian::query`
+    #[serde(default)]
+    views: BTreeMap<String, String>,
+    // How the daily notes are read, see `obsidian::planner`
+    #[serde(default)]
+    planner: PlannerSettings,
+    // Name of the profile in the file, None for files without profiles
+    #[serde(skip)]
+    profile: Option<String>,
 }
 
 impl ConfigInner {
-    pub fn read_config_file() -> ConfigInner {
-        let file = std::fs::File::open("config.yaml").unwrap();
-        serde_yaml::from_reader(file).unwrap()
+    pub fn get_jira_url(&self) -> &str {
+        &self.jira_url
+    }
+
+    pub fn get_week(&self) -> Option<u8> {
+        self.week
+    }
+
+    pub fn get_user_id(&self) -> &str {
+        &self.user_id
+    }
+
+    pub fn get_user_mail(&self) -> &str {
+        &self.user_mail
+    }
+
+    // Read the first time it is asked for, from the source in the config
+    pub fn get_jira_token(&self) -> Result<&str, String> {
+        self.jira_token
+            .resolve()
+            .map(|x| x.expose())
+            .map_err(|message| format!("Could not get the Jira token: {}", message))
+    }
+
+    // Reads the token now, so a passphrase is asked for before a TUI starts
+    pub fn resolve_jira_token(&self) -> Result<(), String> {
+        self.get_jira_token().map(|_| ())
+    }
+
+    // For the clients, which read the token with their first request
+    pub fn get_lazy_jira_token(&self) -> &LazyToken {
+        &self.jira_token
+    }
+
+    // Whether reading the token will ask for a passphrase on the terminal
+    pub fn jira_token_needs_prompt(&self) -> bool {
+        self.jira_token.needs_prompt()
+    }
+
+    pub fn get_vault_path(&self) -> &str {
+        &self.vault_path
+    }
+
+    pub fn get_profile(&self) -> Option<&str> {
+        self.profile.as_deref()
+    }
+
+    pub fn get_daily_notes_path(&self) -> &str {
+        &self.daily_notes_path
+    }
+
+    pub fn get_project_path(&self) -> &str {
+        &self.project_path
+    }
+
+    pub fn get_templates_path(&self) -> Option<&str> {
+        self.templates_path.as_deref()
+    }
+
+    pub fn get_attachments_path(&self) -> Option<&str> {
+        self.attachments_path.as_deref()
+    }
+
+    pub fn get_ignore_filters(&self) -> &[String] {
+        &self.ignore_filters
+    }
+
+    pub fn get_views(&self) -> &BTreeMap<String, String> {
+        &self.views
+    }
+
+    pub fn get_planner(&self) -> &PlannerSettings {
+        &self.planner
+    }
+
+    pub fn get_daily_note_format(&self) -> &str {
+        self.daily_note_format
+            .as_deref()
+            .unwrap_or(crate::obsidian::date_format::DEFAULT_FORMAT)
+    }
+
+    pub fn load(
+        explicit_path: Option<&Path>,
+        profile: Option<&str>,
+    ) -> Result<ConfigInner, ConfigError> {
+        ConfigFile::find(explicit_path)?.load_profile(profile)
+    }
+
+    // Parses a file with a single profile, or with a default one
+    pub fn parse(text: &str) -> Result<ConfigInner, Vec<ConfigProblem>> {
+        Self::parse_profile(text, None)
+    }
+
+    // Checks the whole profile before giving up, so every problem is reported at once
+    pub fn parse_profile(
+        text: &str,
+        profile: Option<&str>,
+    ) -> Result<ConfigInner, Vec<ConfigProblem>> {
+        let mapping = parse_mapping(text)?;
+        let problem = |location: &[&str], message: String| {
+            vec![ConfigProblem {
+                line: find_line(text, location),
+                message,
+            }]
+        };
+
+        let Some(profiles) = mapping.get("profiles") else {
+            if let Some(profile) = profile {
+                return Err(problem(
+                    &[],
+                    format!(
+                        "there is no profile `{}`, the file has no `profiles`",
+                        profile
+                    ),
+                ));
+            }
+            return Self::parse_mapping(text, &mapping, &[]);
+        };
+
+        for key in mapping.keys() {
+            if !key.as_str().is_some_and(|x| PROFILES_KEYS.contains(&x)) {
+                let key = key.as_str().unwrap_or_default();
+                return Err(problem(
+                    &[key],
+                    format!(
+                        "with profiles, only `{}` go at the top of the file, found `{}`",
+                        PROFILES_KEYS.join("`, `"),
+                        key
+                    ),
+                ));
+            }
+        }
+        let Some(profiles) = profiles.as_mapping() else {
+            return Err(problem(
+                &["profiles"],
+                String::from("`profiles` must map names to profiles"),
+            ));
+        };
+        let names: Vec<&str> = profiles.keys().filter_map(|x| x.as_str()).collect();
+
+        let name = match profile.or_else(|| mapping.get("default_profile").and_then(|x| x.as_str()))
+        {
+            Some(name) => name,
+            None if names.len() == 1 => names[0],
+            None => {
+                return Err(problem(
+                    &["profiles"],
+                    format!(
+                        "there are several profiles, pick one of `{}`",
+                        names.join("`, `")
+                    ),
+                ))
+            }
+        };
+        match profiles.get(name).map(|x| x.as_mapping()) {
+            Some(Some(profile)) => {
+                let mut config = Self::parse_mapping(text, profile, &["profiles", name])?;
+                config.profile = Some(name.to_owned());
+                Ok(config)
+            }
+            Some(None) => Err(problem(
+                &["profiles", name],
+                format!("the profile `{}` must be a mapping", name),
+            )),
+            None => Err(problem(
+                &["profiles"],
+                format!(
+                    "there is no profile `{}`, pick one of `{}`",
+                    name,
+                    names.join("`, `")
+                ),
+            )),
+        }
+    }
+
+    // `location` is the path to the profile in the file, to find the lines of the problems
+    fn parse_mapping(
+        text: &str,
+        mapping: &serde_yaml::Mapping,
+        location: &[&str],
+    ) -> Result<ConfigInner, Vec<ConfigProblem>> {
+        let find_key_line = |key: &str| find_line(text, &[location, &[key]].concat());
+        let mut problems = Vec::new();
+        let mut problem = |key: &str, message: String| {
+            problems.push(ConfigProblem {
+                line: find_key_line(key),
+                message,
+            })
+        };
+
+        for key in mapping.keys() {
+            match key.as_str() {
+                Some(key) if KNOWN_KEYS.contains(&key) => {}
+                Some(key) => problem(key, format!("unknown key `{}`", key)),
+                None => problem("", format!("keys must be text, found {:?}", key)),
+            }
+        }
+
+        let get_text = |key: &str| mapping.get(key).and_then(|x| x.as_str());
+        for key in REQUIRED_KEYS {
+            match mapping.get(key) {
+                None => problem(key, format!("missing `{}`", key)),
+                Some(value) if value.as_str().is_none() => {
+                    problem(key, format!("`{}` must be text", key))
+                }
+                Some(_) => {}
+            }
+        }
+
+        if let Some(jira_url) = get_text("jira_url") {
+            if jira_url.contains('/') || jira_url.contains('.') {
+                problem(
+                    "jira_url",
+                    format!(
+                        "`jira_url` is only the site name, like `mycompany` for mycompany.atlassian.net, found `{}`",
+                        jira_url
+                    ),
+                );
+            }
+        }
+
+        if let Some(user_mail) = get_text("user_mail") {
+            if !user_mail.contains('@') {
+                problem(
+                    "user_mail",
+                    format!("`user_mail` is not an email address: `{}`", user_mail),
+                );
+            }
+        }
+
+        for key in OPTIONAL_TEXT_KEYS {
+            if mapping.get(key).is_some_and(|x| x.as_str().is_none()) {
+                problem(key, format!("`{}` must be text", key))
+            }
+        }
+
+        // What is not in the config comes from the settings of Obsidian
+        let mut values = mapping.clone();
+        let vault_path = get_text("vault_path")
+            .map(PathBuf::from)
+            .or_else(|| crate::setup::find_vault(&std::env::current_dir().ok()?));
+        let mut settings = VaultSettings::default();
+        match &vault_path {
+            None => problem(
+                "vault_path",
+                String::from(
+                    "missing `vault_path`, and the current folder is not in an Obsidian vault",
+                ),
+            ),
+            Some(vault_path) if !vault_path.is_dir() => problem(
+                "vault_path",
+                format!("`vault_path` is not a folder: `{}`", vault_path.display()),
+            ),
+            Some(vault_path) => {
+                settings = VaultSettings::read(vault_path);
+                let defaults = [
+                    ("vault_path", vault_path.to_str()),
+                    ("daily_notes_path", settings.get_daily_notes_folder()),
+                    (
+                        "project_path",
+                        settings.get_new_note_folder().filter(|x| !x.is_empty()),
+                    ),
+                    ("templates_path", settings.get_templates_folder()),
+                    ("attachments_path", settings.get_attachment_folder()),
+                    ("daily_note_format", settings.get_daily_note_format()),
+                ];
+                for (key, default) in defaults {
+                    if let (false, Some(default)) = (values.contains_key(key), default) {
+                        values.insert(key.into(), default.into());
+                    }
+                }
+
+                // Without them every note of the vault would be read as a task
+                for (key, setting) in [
+                    ("daily_notes_path", "folder for the daily notes"),
+                    ("project_path", "folder for new notes"),
+                ] {
+                    if !values.contains_key(key) {
+                        problem(
+                            key,
+                            format!("missing `{}`, and Obsidian has no {} set", key, setting),
+                        );
+                    }
+                }
+
+                for key in FOLDER_KEYS {
+                    let Some(folder) = values.get(key).and_then(|x| x.as_str()) else {
+                        continue;
+                    };
+                    if !vault_path.join(folder).is_dir() {
+                        problem(
+                            key,
+                            format!("`{}` is not a folder of the vault: `{}`", key, folder),
+                        );
+                    }
+                }
+            }
+        }
+
+        let token_keys: Vec<_> = TOKEN_KEYS
+            .into_iter()
+            .filter(|x| mapping.contains_key(x))
+            .collect();
+        if token_keys.len() > 1 {
+            problem(
+                token_keys[1],
+                format!(
+                    "only one of `{}` can be given, found `{}`",
+                    TOKEN_KEYS.join("`, `"),
+                    token_keys.join("`, `")
+                ),
+            );
+        }
+        for key in &token_keys {
+            if get_text(key).is_none() {
+                problem(key, format!("`{}` must be text", key));
+            }
+        }
+
+        if let Some(format) = get_text("daily_note_format") {
+            if format.trim().is_empty() {
+                problem(
+                    "daily_note_format",
+                    String::from("`daily_note_format` must be a date pattern, like `YYYY-MM-DD`"),
+                );
+            }
+        }
+
+        // Views are nested, their lines are found here
+        let mut view_problems = Vec::new();
+        if let Some(views) = mapping.get("views") {
+            match views.as_mapping() {
+                None => problem("views", String::from("`views` must map names to queries")),
+                Some(views) => {
+                    for (name, query) in views {
+                        let name = name.as_str().unwrap_or_default();
+                        let line = find_line(text, &[location, &["views", name]].concat());
+                        let message = match query.as_str().map(Query::parse) {
+                            None => format!("the view `{}` must be a query in text", name),
+                            Some(Err(error)) => format!("the view `{}` {}", name, error),
+                            Some(Ok(_)) => continue,
+                        };
+                        view_problems.push(ConfigProblem { line, message });
+                    }
+                }
+            }
+        }
+
+        if let Some(planner) = mapping.get("planner") {
+            if let Err(error) = serde_yaml::from_value::<PlannerSettings>(planner.clone()) {
+                problem("planner", format!("`planner` is not valid: {}", error));
+            }
+        }
+
+        if let Some(week) = mapping.get("week") {
+            if !week.is_null() && !week.as_u64().is_some_and(|x| (1..=53).contains(&x)) {
+                problem("week", String::from("`week` must be a number from 1 to 53"));
+            }
+        }
+
+        problems.extend(view_problems);
+        if !problems.is_empty() {
+            problems.sort_by_key(|x| x.line);
+            return Err(problems);
+        }
+
+        let token_source = match token_keys.first().copied() {
+            Some("jira_token") => {
+                TokenSource::Plain(Secret::new(get_text("jira_token").unwrap().to_owned()))
+            }
+            Some("token_env") => TokenSource::Env(get_text("token_env").unwrap().to_owned()),
+            Some("token_command") => {
+                TokenSource::Command(get_text("token_command").unwrap().to_owned())
+            }
+            Some(key) => TokenSource::File(PathBuf::from(get_text(key).unwrap())),
+            None => TokenSource::default(),
+        };
+        // A passphrase is only asked for once the token is needed
+        let jira_token = LazyToken::new(token_source);
+        if !jira_token.needs_prompt() {
+            jira_token.resolve().map_err(|message| {
+                vec![ConfigProblem {
+                    line: token_keys.first().and_then(|x| find_key_line(x)),
+                    message: format!("could not get the Jira token: {}", message),
+                }]
+            })?;
+        }
+
+        let mut config: ConfigInner = serde_yaml::from_value(serde_yaml::Value::Mapping(values))
+            .map_err(|error| {
+                vec![ConfigProblem {
+                    line: None,
+                    message: error.to_string(),
+                }]
+            })?;
+        config.jira_token = jira_token;
+        config.ignore_filters = settings.get_ignore_filters().to_vec();
+        Ok(config)
+    }
+}
+
+const REQUIRED_KEYS: [&str; 3] = ["jira_url", "user_mail", "user_id"];
+
+// Default to the vault around the current folder and to the settings of Obsidian
+const OPTIONAL_TEXT_KEYS: [&str; 6] = [
+    "vault_path",
+    "daily_notes_path",
+    "project_path",
+    "templates_path",
+    "attachments_path",
+    "daily_note_format",
+];
+
+// Relative to the vault
+const FOLDER_KEYS: [&str; 4] = [
+    "daily_notes_path",
+    "project_path",
+    "templates_path",
+    "attachments_path",
+];
+
+const KNOWN_KEYS: [&str; 16] = [
+    "jira_url",
+    "user_mail",
+    "user_id",
+    "jira_token",
+    "token_env",
+    "token_command",
+    "token_file",
+    "vault_path",
+    "daily_notes_path",
+    "project_path",
+    "templates_path",
+    "attachments_path",
+    "daily_note_format",
+    "week",
+    "views",
+    "planner",
+];
+
+// The ways to give the Jira token, see `credentials::TokenSource`
+const TOKEN_KEYS: [&str; 4] = ["jira_token", "token_env", "token_command", "token_file"];
+
+// Keys allowed at the top of a file with profiles
+const PROFILES_KEYS: [&str; 2] = ["profiles", "default_profile"];
+
+fn parse_mapping(text: &str) -> Result<serde_yaml::Mapping, Vec<ConfigProblem>> {
+    serde_yaml::from_str(text).map_err(|error| {
+        vec![ConfigProblem {
+            line: error.location().map(|x| x.line()),
+            message: error.to_string(),
+        }]
+    })
+}
+
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+// Line (starting at 1) where a key is defined, `path` is the keys leading to it
+fn find_line(text: &str, path: &[&str]) -> Option<usize> {
+    let lines: Vec<&str> = text.lines().collect();
+    let (mut start, mut end) = (0, lines.len());
+    let mut parent_indentation = None;
+    let mut found = None;
+
+    for key in path {
+        if key.is_empty() {
+            return None;
+        }
+        let position = (start..end).find(|&i| {
+            let line = lines[i];
+            parent_indentation.map_or(indentation(line) == 0, |x| indentation(line) > x)
+                && line
+                    .trim_start()
+                    .strip_prefix(key)
+                    .is_some_and(|rest| rest.trim_start().starts_with(':'))
+        })?;
+
+        // The children of the key are the lines indented further
+        let key_indentation = indentation(lines[position]);
+        end = (position + 1..end)
+            .find(|&i| !lines[i].trim().is_empty() && indentation(lines[i]) <= key_indentation)
+            .unwrap_or(end);
+        start = position + 1;
+        parent_indentation = Some(key_indentation);
+        found = Some(position + 1);
+    }
+    found
+}
+
+/*
+    A config file holds either a single profile, with its keys at the top, or
+    named profiles, for example one per Jira site and vault:
+
+        default_profile: work
+        profiles:
+          work:
+            jira_url: mycompany
+            ...
+          side:
+            jira_url: myproject
+            ...
+
+    The profile used is the one asked for (like with --profile), otherwise the
+    one in OBSIDIAN_JIRA_PROFILE, then `default_profile`, then the only one.
+*/
+#[derive(Debug, Clone)]
+pub struct ConfigFile {
+    path: PathBuf,
+    text: String,
+}
+
+impl ConfigFile {
+    pub fn find(explicit_path: Option<&Path>) -> Result<Self, ConfigError> {
+        Self::read(&find_config_file(explicit_path)?)
+    }
+
+    pub fn read(path: &Path) -> Result<Self, ConfigError> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|error| ConfigError::Io(path.to_owned(), error))?;
+        Ok(Self {
+            path: path.to_owned(),
+            text,
+        })
+    }
+
+    pub fn get_path(&self) -> &Path {
+        &self.path
+    }
+
+    // In the order of the file, empty when the file has a single profile
+    pub fn get_profile_names(&self) -> Vec<String> {
+        let Ok(mapping) = parse_mapping(&self.text) else {
+            return Vec::new();
+        };
+        mapping
+            .get("profiles")
+            .and_then(|x| x.as_mapping())
+            .map(|x| {
+                x.keys()
+                    .filter_map(|x| x.as_str())
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn load_profile(&self, profile: Option<&str>) -> Result<ConfigInner, ConfigError> {
+        let from_env = std::env::var(PROFILE_ENV_VAR).ok();
+        ConfigInner::parse_profile(&self.text, profile.or(from_env.as_deref()))
+            .map_err(|problems| ConfigError::Invalid(self.path.clone(), problems))
+    }
+}
+
+/*
+    The config file is looked for, in order, in:
+    ~ The path given explicitly, for example with --config
+    ~ The path in the OBSIDIAN_JIRA_CONFIG environment variable
+    ~ $XDG_CONFIG_HOME/obsidian-jira/config.yaml (~/.config if unset)
+    ~ .obsidian-jira/config.yaml in the vault, looking up from the current folder
+    ~ config.yaml in the current folder, as it used to be
+*/
+pub fn config_candidates(explicit_path: Option<&Path>) -> Vec<PathBuf> {
+    candidates_from(
+        explicit_path,
+        std::env::var_os(CONFIG_ENV_VAR).map(PathBuf::from),
+        xdg_config_home(),
+        &std::env::current_dir().unwrap_or_default(),
+    )
+}
+
+fn xdg_config_home() -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|x| PathBuf::from(x).join(".config")))
+}
+
+// The config of the user, where `init` writes by default
+pub fn user_config_path() -> Option<PathBuf> {
+    xdg_config_home().map(|x| x.join("obsidian-jira").join(CONFIG_FILE_NAME))
+}
+
+fn candidates_from(
+    explicit_path: Option<&Path>,
+    env_path: Option<PathBuf>,
+    xdg_config_home: Option<PathBuf>,
+    current_dir: &Path,
+) -> Vec<PathBuf> {
+    // An explicit path is never second-guessed
+    if let Some(path) = explicit_path {
+        return vec![path.to_owned()];
+    }
+
+    let mut candidates = Vec::new();
+    candidates.extend(env_path);
+    candidates.extend(xdg_config_home.map(|x| x.join("obsidian-jira").join(CONFIG_FILE_NAME)));
+    candidates.extend(
+        current_dir
+            .ancestors()
+            .map(|x| x.join(VAULT_CONFIG_FOLDER).join(CONFIG_FILE_NAME)),
+    );
+    candidates.push(current_dir.join(CONFIG_FILE_NAME));
+    candidates
+}
+
+pub fn find_config_file(explicit_path: Option<&Path>) -> Result<PathBuf, ConfigError> {
+    let candidates = config_candidates(explicit_path);
+    match candidates.iter().find(|x| x.is_file()) {
+        Some(path) => Ok(path.clone()),
+        None => Err(ConfigError::NotFound(candidates)),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigProblem {
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    NotFound(Vec<PathBuf>),
+    Io(PathBuf, std::io::Error),
+    Invalid(PathBuf, Vec<ConfigProblem>),
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::NotFound(candidates) => {
+                writeln!(f, "No config file found, looked in:")?;
+                for candidate in candidates {
+                    writeln!(f, "  {}", candidate.display())?;
+                }
+                Ok(())
+            }
+            ConfigError::Io(path, error) => {
+                write!(f, "Could not read {}: {}", path.display(), error)
+            }
+            ConfigError::Invalid(path, problems) => {
+                writeln!(f, "Invalid config file {}:", path.display())?;
+                for problem in problems {
+                    match problem.line {
+                        Some(line) => writeln!(f, "  line {}: {}", line, problem.message)?,
+                        None => writeln!(f, "  {}", problem.message)?,
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn lookup_order() {
+        let current_dir = Path::new("/vault/Projects");
+        let candidates = candidates_from(
+            None,
+            Some(PathBuf::from("/env/config.yaml")),
+            Some(PathBuf::from("/home/me/.config")),
+            current_dir,
+        );
+        assert_eq!(
+            candidates,
+            [
+                "/env/config.yaml",
+                "/home/me/.config/obsidian-jira/config.yaml",
+                "/vault/Projects/.obsidian-jira/config.yaml",
+                "/vault/.obsidian-jira/config.yaml",
+                "/.obsidian-jira/config.yaml",
+                "/vault/Projects/config.yaml",
+            ]
+            .map(PathBuf::from)
+        );
+
+        let explicit = Path::new("/somewhere/else.yaml");
+        assert_eq!(
+            candidates_from(Some(explicit), None, None, current_dir),
+            vec![explicit.to_owned()]
+        );
+    }
+
+    #[test]
+    fn reports_every_problem() {
+        let text = "jira_url: https://mycompany.atlassian.net
+user_mail: me
+user_id: 1234
+vault_path: /does/not/exist
+daily_notes_path: Daily
+project_path: Projects
+week: 60
+colour: blue
+jira_token: secret
+token_command: pass show jira
+";
+        let problems = ConfigInner::parse(text).unwrap_err();
+        let lines: Vec<_> = problems.iter().map(|x| x.line).collect();
+        assert_eq!(
+            lines,
+            [
+                Some(1),
+                Some(2),
+                Some(3),
+                Some(4),
+                Some(7),
+                Some(8),
+                Some(10)
+            ]
+        );
+        assert_eq!(problems[5].message, "unknown key `colour`");
+        assert!(problems[6].message.starts_with("only one of"));
+    }
+
+    #[test]
+    fn valid_config() {
+        use crate::test_vault::{config_text, temporary_vault};
+        let vault = temporary_vault("config");
+
+        let config = ConfigInner::parse(&config_text(&vault)).unwrap();
+        assert_eq!(config.jira_url, "mycompany");
+        assert_eq!(config.week, None);
+        assert_eq!(config.get_jira_token(), Ok("secret"));
+        assert!(!format!("{:?}", config).contains("secret"));
+    }
+
+    #[test]
+    fn picks_profiles() {
+        use crate::test_vault::temporary_vault;
+        let vault = temporary_vault("profiles");
+
+        let text = format!(
+            "default_profile: work
+profiles:
+  work:
+    jira_url: mycompany
+    user_mail: me@mycompany.com
+    user_id: '1234'
+    jira_token: secret
+    vault_path: {0}
+    daily_notes_path: Daily
+    project_path: Projects
+  side:
+    jira_url: myproject
+    user_mail: me
+    user_id: '5678'
+    jira_token: other
+    vault_path: {0}
+    daily_notes_path: Daily
+    project_path: Projects
+",
+            vault.display()
+        );
+        let config = ConfigInner::parse(&text).unwrap();
+        assert_eq!(config.jira_url, "mycompany");
+        assert_eq!(config.get_profile(), Some("work"));
+
+        let problems = ConfigInner::parse_profile(&text, Some("side")).unwrap_err();
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].line, Some(13));
+
+        let problems = ConfigInner::parse_profile(&text, Some("home")).unwrap_err();
+        assert_eq!(problems[0].line, Some(2));
+        assert_eq!(
+            problems[0].message,
+            "there is no profile `home`, pick one of `work`, `side`"
+        );
+    }
+
+    #[test]
+    fn defaults_to_the_obsidian_settings() {
+        use crate::test_vault::{config_text, temporary_vault};
+        let vault = temporary_vault("defaults");
+        std::fs::create_dir_all(vault.join(".obsidian")).unwrap();
+        std::fs::create_dir_all(vault.join("Journal")).unwrap();
+        std::fs::create_dir_all(vault.join("Inbox")).unwrap();
+        std::fs::write(
+            vault.join(".obsidian/daily-notes.json"),
+            r#"{"folder": "Journal", "format": "YYYY-MM-DD ddd"}"#,
+        )
+        .unwrap();
+        std::fs::write(
+            vault.join(".obsidian/app.json"),
+            r#"{"newFileLocation": "folder", "newFileFolderPath": "Inbox/", "attachmentFolderPath": "Files"}"#,
+        )
+        .unwrap();
+
+        // Without the folders, taken from the settings
+        let text =
+            config_text(&vault).replace("daily_notes_path: Daily\nproject_path: Projects\n", "");
+        let problems = ConfigInner::parse(&text).unwrap_err();
+        assert_eq!(
+            problems[0].message,
+            "`attachments_path` is not a folder of the vault: `Files`"
+        );
+
+        std::fs::create_dir_all(vault.join("Files")).unwrap();
+        let config = ConfigInner::parse(&text).unwrap();
+        assert_eq!(config.get_daily_notes_path(), "Journal");
+        assert_eq!(config.get_daily_note_format(), "YYYY-MM-DD ddd");
+        assert_eq!(config.get_project_path(), "Inbox");
+        assert_eq!(config.get_attachments_path(), Some("Files"));
+        assert_eq!(config.get_templates_path(), None);
+
+        // New notes next to the current one give no project folder
+        std::fs::write(
+            vault.join(".obsidian/app.json"),
+            r#"{"newFileLocation": "current"}"#,
+        )
+        .unwrap();
+        let problems = ConfigInner::parse(&text).unwrap_err();
+        assert_eq!(
+            problems[0].message,
+            "missing `project_path`, and Obsidian has no folder for new notes set"
+        );
+    }
+
+    #[test]
+    fn checks_saved_views() {
+        use crate::test_vault::{config_text, temporary_vault};
+        let vault = temporary_vault("views");
+
+        let text = config_text(&vault)
+            + "views:
+  open: FROM \"Projects\" WHERE status != \"Done\" SORT priority
+  broken: WHERE stat = 1
+";
+        let problems = ConfigInner::parse(&text).unwrap_err();
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].line, Some(10));
+        assert_eq!(
+            problems[0].message,
+            "the view `broken` at character 7: unknown field `stat`"
+        );
+
+        let config = ConfigInner::parse(&text.replace("stat =", "priority =")).unwrap();
+        assert_eq!(
+            config.get_views().keys().collect::<Vec<_>>(),
+            ["broken", "open"]
+        );
+    }
+
+    #[test]
+    fn checks_the_planner_settings() {
+        use crate::test_vault::{config_text, temporary_vault};
+        let vault = temporary_vault("planner");
+
+        let text = config_text(&vault)
+            + "planner:
+  format: day_planner
+  heading: Day planner
+  working_hours: 9am - 5:30pm
+  daily_max: 7h30m
+";
+        let config = ConfigInner::parse(&text).unwrap();
+        assert_eq!(
+            config.get_planner().get_format(),
+            crate::obsidian::planner::PlanFormat::DayPlanner
+        );
+        assert_eq!(config.get_planner().get_sections(), ["Day planner"]);
+        assert_eq!(
+            config.get_planner().get_working_hours().unwrap().get_end(),
+            time::macros::time!(17:30)
+        );
+        assert_eq!(
+            config.get_planner().get_daily_max(),
+            Some(time::Duration::minutes(450))
+        );
+
+        let problems = ConfigInner::parse(&text.replace("day_planner", "dayplanner")).unwrap_err();
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].line, Some(8));
+        assert!(problems[0].message.starts_with("`planner` is not valid: "));
+
+        let problems = ConfigInner::parse(&text.replace("5:30pm", "later")).unwrap_err();
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].message.contains("found 'later'"));
     }
 }
@@ -0,0 +1,213 @@
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+
+use crate::config::{ConfigError, ConfigInner, CONFIG};
+#[cfg(feature = "async")]
+use crate::jira::nonblocking::AsyncJiraClient;
+use crate::jira::{Connection, JiraCache, JiraClient, PendingWrites};
+use crate::obsidian::index::VaultIndex;
+use crate::obsidian::task_file::{TaskFilter, TaskNames};
+use crate::obsidian::wikilink::NoteResolver;
+
+/*
+    Everything the library needs to talk to the vault and to Jira: the config,
+    the HTTP client, the local Jira state and the index of the notes. It is
+    passed explicitly to every function of the library, so several vaults or
+    configs can be used side by side, and tests can point it at a temporary
+    folder.
+
+    `Context::global()` builds one from the global `CONFIG` for code that does
+    not want to carry it around.
+*/
+#[derive(Debug)]
+pub struct Context {
+    config: ConfigInner,
+    client: JiraClient,
+    #[cfg(feature = "async")]
+    async_client: AsyncJiraClient,
+    cache: Mutex<JiraCache>,
+    pending_writes: Mutex<PendingWrites>,
+    // Held for a whole replay, so the queued writes are sent one replay at a time
+    replaying: Mutex<()>,
+    // Whether Jira could not be reached the last time it was asked
+    offline: AtomicBool,
+    index: VaultIndex,
+    // Every note of the vault for the links, walked when first needed
+    notes: Mutex<Option<Arc<NoteResolver>>>,
+    // The names and aliases of the tasks of the project, the same way
+    task_names: Mutex<Option<Arc<TaskNames>>>,
+}
+
+impl Context {
+    pub fn new(config: ConfigInner) -> Self {
+        let state_path = Path::new(config.get_vault_path()).join(".obsidian-jira");
+        let pending_writes = PendingWrites::load(state_path.join("pending_writes.json"));
+        Self {
+            client: JiraClient::new(Connection::from_config(&config)),
+            #[cfg(feature = "async")]
+            async_client: AsyncJiraClient::new(Connection::from_config(&config)),
+            cache: Mutex::new(JiraCache::load(state_path.join("cache.json"))),
+            // Writes queued by an earlier run are sent once Jira answers
+            offline: AtomicBool::new(!pending_writes.is_empty()),
+            pending_writes: Mutex::new(pending_writes),
+            replaying: Mutex::new(()),
+            index: VaultIndex::load(state_path.join("index.json")),
+            notes: Mutex::new(None),
+            task_names: Mutex::new(None),
+            config,
+        }
+    }
+
+    // Loads the config from an explicit path, or from the first location found
+    pub fn load(explicit_path: Option<&Path>, profile: Option<&str>) -> Result<Self, ConfigError> {
+        Ok(Self::new(ConfigInner::load(explicit_path, profile)?))
+    }
+
+    pub fn global() -> &'static Context {
+        static GLOBAL: OnceLock<Context> = OnceLock::new();
+        GLOBAL.get_or_init(|| Context::new(CONFIG.get_inner().clone()))
+    }
+
+    pub fn get_config(&self) -> &ConfigInner {
+        &self.config
+    }
+
+    pub fn get_client(&self) -> &JiraClient {
+        &self.client
+    }
+
+    // To change the retry policy or the request budget
+    pub fn get_client_mut(&mut self) -> &mut JiraClient {
+        &mut self.client
+    }
+
+    #[cfg(feature = "async")]
+    pub fn get_async_client(&self) -> &AsyncJiraClient {
+        &self.async_client
+    }
+
+    pub fn get_cache(&self) -> &Mutex<JiraCache> {
+        &self.cache
+    }
+
+    pub fn get_pending_writes(&self) -> &Mutex<PendingWrites> {
+        &self.pending_writes
+    }
+
+    // Until dropped, no other replay of the pending writes can start
+    pub(crate) fn lock_replay(&self) -> MutexGuard<'_, ()> {
+        self.replaying.lock().unwrap()
+    }
+
+    // Returns whether it was offline before
+    pub(crate) fn set_offline(&self, offline: bool) -> bool {
+        self.offline.swap(offline, Ordering::Relaxed)
+    }
+
+    // What was parsed from the notes, see `obsidian::index`
+    pub fn get_index(&self) -> &VaultIndex {
+        &self.index
+    }
+
+    // Finds the notes the links point to, see `reload_notes`
+    pub fn get_note_resolver(&self) -> Arc<NoteResolver> {
+        self.notes
+            .lock()
+            .unwrap()
+            .get_or_insert_with(|| Arc::new(NoteResolver::from_vault(self)))
+            .clone()
+    }
+
+    // For the text naming a task, see `reload_notes`
+    pub fn get_task_names(&self) -> Arc<TaskNames> {
+        self.task_names
+            .lock()
+            .unwrap()
+            .get_or_insert_with(|| {
+                let tasks = TaskFilter::new()
+                    .set_path(self.config.get_project_path())
+                    .set_recursive(true)
+                    .get_tasks(self);
+                Arc::new(TaskNames::new(&tasks))
+            })
+            .clone()
+    }
+
+    // The vault is walked again the next time the links are resolved, for notes
+    // added, moved or removed since. What was indexed so far is saved.
+    pub fn reload_notes(&self) {
+        let _ = self.index.save();
+        *self.notes.lock().unwrap() = None;
+        *self.task_names.lock().unwrap() = None;
+    }
+
+    pub fn get_vault_path(&self) -> &Path {
+        Path::new(self.config.get_vault_path())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::obsidian::wikilink::Wikilink;
+    use std::path::Path;
+
+    #[test]
+    fn works_on_a_temporary_vault() {
+        use crate::test_vault::{context_for, temporary_vault};
+        let vault = temporary_vault("context");
+        std::fs::write(
+            vault.join("Projects/Write the report.md"),
+            "---\npriority: 2\nstatus: In Progress\njira key: MB-7\nsprints:\n  - Y24W10\nremaining time: '3:00'\n---\nSome notes\n",
+        )
+        .unwrap();
+        std::fs::write(
+            vault.join("Daily/2024-03-05.md"),
+            "- [x] 9:00 - 10:30 [[Write the report]]\n- [ ] 11:00 - 12:00 Meeting\n",
+        )
+        .unwrap();
+
+        let context = context_for(&vault);
+
+        assert_eq!(
+            crate::obsidian::task_file::find_jira_key(
+                &context,
+                &Wikilink::parse("Write the report"),
+                Path::new("Daily")
+            ),
+            Some(crate::jira::JiraKey::new("MB-7"))
+        );
+
+        let balance = crate::obsidian::SprintTimeBalance::new(&context, 2024, 10);
+        let report = &balance.tasks[0];
+        assert_eq!(report.name, "Write the report");
+        assert!(report.in_sprint);
+        assert_eq!(report.remaining_time, time::Duration::hours(3));
+        assert_eq!(report.completed_time, time::Duration::minutes(90));
+        assert_eq!(balance.tasks[1].name, "Meeting");
+        assert_eq!(balance.tasks[1].uncompleted_time, time::Duration::hours(1));
+    }
+
+    #[test]
+    fn walks_the_vault_once_for_the_links() {
+        use crate::test_vault::{context_for, temporary_vault};
+        let vault = temporary_vault("resolver");
+        let context = context_for(&vault);
+        let link = Wikilink::parse("Later");
+        let resolver = context.get_note_resolver();
+        assert!(resolver.resolve(&link, Path::new("Daily")).is_none());
+
+        std::fs::write(vault.join("Projects/Later.md"), "Added after\n").unwrap();
+        assert!(std::sync::Arc::ptr_eq(
+            &resolver,
+            &context.get_note_resolver()
+        ));
+        context.reload_notes();
+        assert_eq!(
+            context
+                .get_note_resolver()
+                .resolve(&link, Path::new("Daily")),
+            Some(Path::new("Projects/Later.md"))
+        );
+    }
+}
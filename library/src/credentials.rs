@@ -0,0 +1,237 @@
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::{Arc, OnceLock};
+
+use age::secrecy::SecretString;
+
+pub const TOKEN_ENV_VAR: &str = "OBSIDIAN_JIRA_TOKEN";
+pub const PASSPHRASE_ENV_VAR: &str = "OBSIDIAN_JIRA_PASSPHRASE";
+
+// A value that must never end up in logs or panic messages
+#[derive(Clone, Default, PartialEq)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: String) -> Self {
+        Self(value)
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Debug for Secret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Secret(<redacted>)")
+    }
+}
+
+/*
+    Where the Jira token comes from, one of:
+    ~ jira_token: the token itself, in plain text
+    ~ token_env: the name of an environment variable holding it
+    ~ token_command: a command printing it, like `pass show jira`
+    ~ token_file: a file encrypted with a passphrase by age (`age -p`), the
+      passphrase is read from OBSIDIAN_JIRA_PASSPHRASE or asked for
+    When none is given, the OBSIDIAN_JIRA_TOKEN environment variable is used.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenSource {
+    Plain(Secret),
+    Env(String),
+    Command(String),
+    File(PathBuf),
+}
+
+impl Default for TokenSource {
+    fn default() -> Self {
+        TokenSource::Env(String::from(TOKEN_ENV_VAR))
+    }
+}
+
+impl TokenSource {
+    pub fn resolve(&self) -> Result<Secret, String> {
+        let token = match self {
+            TokenSource::Plain(token) => return Ok(token.clone()),
+            TokenSource::Env(variable) => std::env::var(variable)
+                .map_err(|_| format!("the environment variable `{}` is not set", variable))?,
+            TokenSource::Command(command) => run_command(command)?,
+            TokenSource::File(path) => {
+                let passphrase = match std::env::var(PASSPHRASE_ENV_VAR) {
+                    Ok(passphrase) => passphrase,
+                    Err(_) => prompt_secret(&format!("Passphrase for {}: ", path.display()))?
+                        .expose()
+                        .to_owned(),
+                };
+                let encrypted = std::fs::read(path)
+                    .map_err(|error| format!("could not read `{}`: {}", path.display(), error))?;
+                decrypt(&encrypted, passphrase)?
+            }
+        };
+
+        let token = token.trim();
+        if token.is_empty() {
+            return Err(String::from("the token is empty"));
+        }
+        Ok(Secret::new(token.to_owned()))
+    }
+}
+
+/*
+    A token read from its source the first time it is needed. A token file
+    without OBSIDIAN_JIRA_PASSPHRASE asks for the passphrase on the terminal,
+    which has to happen before a TUI takes over the terminal, see `needs_prompt`.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct LazyToken {
+    source: TokenSource,
+    // Shared by the clones, so a passphrase is asked for once
+    token: Arc<OnceLock<Secret>>,
+}
+
+impl LazyToken {
+    pub fn new(source: TokenSource) -> Self {
+        Self {
+            source,
+            token: Arc::new(OnceLock::new()),
+        }
+    }
+
+    pub fn get_source(&self) -> &TokenSource {
+        &self.source
+    }
+
+    // Whether resolving it would ask for something on the terminal
+    pub fn needs_prompt(&self) -> bool {
+        self.token.get().is_none()
+            && matches!(self.source, TokenSource::File(_))
+            && std::env::var_os(PASSPHRASE_ENV_VAR).is_none()
+    }
+
+    // Reads the token once, later calls return the same one
+    pub fn resolve(&self) -> Result<&Secret, String> {
+        if let Some(token) = self.token.get() {
+            return Ok(token);
+        }
+        let token = self.source.resolve()?;
+        Ok(self.token.get_or_init(|| token))
+    }
+}
+
+// Asks on the terminal without echoing what is typed
+pub fn prompt_secret(prompt: &str) -> Result<Secret, String> {
+    rpassword::prompt_password(prompt)
+        .map(Secret::new)
+        .map_err(|error| format!("could not read from the terminal: {}", error))
+}
+
+fn run_command(command: &str) -> Result<String, String> {
+    let output = if cfg!(windows) {
+        Command::new("cmd").args(["/C", command]).output()
+    } else {
+        Command::new("sh").args(["-c", command]).output()
+    }
+    .map_err(|error| format!("could not run `{}`: {}", command, error))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "`{}` failed ({}): {}",
+            command,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    String::from_utf8(output.stdout).map_err(|_| format!("`{}` did not print text", command))
+}
+
+// Armored, so the file can be opened and checked by hand
+pub fn encrypt(token: &str, passphrase: String) -> Result<String, String> {
+    let encryptor = age::Encryptor::with_user_passphrase(SecretString::from(passphrase));
+    let mut encrypted = Vec::new();
+    let mut writer = encryptor
+        .wrap_output(
+            age::armor::ArmoredWriter::wrap_output(&mut encrypted, age::armor::Format::AsciiArmor)
+                .map_err(|error| error.to_string())?,
+        )
+        .map_err(|error| error.to_string())?;
+    writer
+        .write_all(token.as_bytes())
+        .map_err(|error| error.to_string())?;
+    writer
+        .finish()
+        .and_then(|x| x.finish())
+        .map_err(|error| error.to_string())?;
+    Ok(String::from_utf8(encrypted).unwrap())
+}
+
+// Accepts both binary and armored age files
+pub fn decrypt(encrypted: &[u8], passphrase: String) -> Result<String, String> {
+    let identity = age::scrypt::Identity::new(SecretString::from(passphrase));
+    let decryptor = age::Decryptor::new_buffered(age::armor::ArmoredReader::new(encrypted))
+        .map_err(|error| format!("not an age encrypted file: {}", error))?;
+
+    let mut token = String::new();
+    decryptor
+        .decrypt(std::iter::once(&identity as &dyn age::Identity))
+        .map_err(|error| format!("could not decrypt the token: {}", error))?
+        .read_to_string(&mut token)
+        .map_err(|error| format!("could not decrypt the token: {}", error))?;
+    Ok(token)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn never_prints_the_secret() {
+        let source = TokenSource::Plain(Secret::new(String::from("hunter2")));
+        assert!(!format!("{:?}", source).contains("hunter2"));
+        assert_eq!(source.resolve().unwrap().expose(), "hunter2");
+    }
+
+    #[test]
+    fn resolves_commands() {
+        let source = TokenSource::Command(String::from("echo from-command"));
+        assert_eq!(source.resolve().unwrap().expose(), "from-command");
+        assert!(TokenSource::Command(String::from("exit 3"))
+            .resolve()
+            .is_err());
+    }
+
+    #[test]
+    fn resolves_lazy_tokens_once() {
+        // Each shell prints its own process id
+        let token = LazyToken::new(TokenSource::Command(String::from("echo $$")));
+        assert!(!token.needs_prompt());
+        let first = token.resolve().unwrap().clone();
+        assert_eq!(token.resolve().unwrap(), &first);
+        assert_eq!(token.clone().resolve().unwrap(), &first);
+    }
+
+    #[test]
+    fn decrypts_age_files() {
+        let mut recipient = age::scrypt::Recipient::new(SecretString::from("correct horse"));
+        recipient.set_work_factor(2);
+        let encryptor = age::Encryptor::with_recipients(std::iter::once(&recipient as _)).unwrap();
+        let mut encrypted = Vec::new();
+        let mut writer = encryptor.wrap_output(&mut encrypted).unwrap();
+        writer.write_all(b"from-file\n").unwrap();
+        writer.finish().unwrap();
+
+        assert_eq!(
+            decrypt(&encrypted, String::from("correct horse")).unwrap(),
+            "from-file\n"
+        );
+        assert!(decrypt(&encrypted, String::from("wrong")).is_err());
+
+        let armored = encrypt("from-init", String::from("correct horse")).unwrap();
+        assert!(armored.starts_with("-----BEGIN AGE ENCRYPTED FILE-----"));
+        assert_eq!(
+            decrypt(armored.as_bytes(), String::from("correct horse")).unwrap(),
+            "from-init"
+        );
+    }
+}
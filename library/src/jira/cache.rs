@@ -0,0 +1,367 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::request::JiraRequest;
+
+/*
+    Everything fetched from Jira is kept on disk, so the tool keeps working
+    without a connection. Issues are stored once, keyed by their key and
+    `updated` date, and the searches only keep the keys of the issues they
+    returned. Any other answer (sprints, users, comments...) is stored as is,
+    keyed by the request that produced it.
+
+    The files carry a version: when the format changes, old caches are ignored.
+*/
+pub const CACHE_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedIssue {
+    updated: String,
+    issue: Value,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheData {
+    version: u32,
+    #[serde(default)]
+    issues: BTreeMap<String, CachedIssue>,
+    #[serde(default)]
+    users: BTreeMap<String, Value>,
+    #[serde(default)]
+    responses: BTreeMap<String, Value>,
+}
+
+impl Default for CacheData {
+    fn default() -> Self {
+        Self {
+            version: CACHE_VERSION,
+            issues: BTreeMap::new(),
+            users: BTreeMap::new(),
+            responses: BTreeMap::new(),
+        }
+    }
+}
+
+// Saved by `save`, or when dropped with answers not saved yet
+#[derive(Debug)]
+pub struct JiraCache {
+    path: PathBuf,
+    data: CacheData,
+    changed: bool,
+}
+
+fn read_versioned<T: Default + for<'de> Deserialize<'de>>(
+    path: &Path,
+    get_version: fn(&T) -> u32,
+) -> T {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|text| serde_json::from_str::<T>(&text).ok())
+        .filter(|data| get_version(data) == CACHE_VERSION)
+        .unwrap_or_default()
+}
+
+pub(crate) fn write_atomically<T: Serialize>(path: &Path, data: &T) -> std::io::Result<()> {
+    write_file_atomically(path, &serde_json::to_vec(data)?)
+}
+
+// Written next to the file and renamed over it, so a crash or a full disk
+// leaves either the old file or the new one, never half of it. The temporary
+// file is hidden, the vault scans skip it.
+pub(crate) fn write_file_atomically(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temporary = path.with_file_name(format!(".{}.tmp", name));
+    std::fs::write(&temporary, contents)?;
+    std::fs::rename(temporary, path)
+}
+
+impl JiraCache {
+    pub fn load<P: Into<PathBuf>>(path: P) -> Self {
+        let path = path.into();
+        let data = read_versioned(&path, |x: &CacheData| x.version);
+        Self {
+            path,
+            data,
+            changed: false,
+        }
+    }
+
+    pub fn save(&mut self) -> std::io::Result<()> {
+        write_atomically(&self.path, &self.data)?;
+        self.changed = false;
+        Ok(())
+    }
+
+    // Whether there are answers that are not saved yet
+    pub fn is_changed(&self) -> bool {
+        self.changed
+    }
+
+    pub fn store(&mut self, request: &JiraRequest, response: &Value) {
+        self.changed = true;
+        let mut response = response.clone();
+        if let Some(issues) = response.get_mut("issues").and_then(|x| x.as_array_mut()) {
+            for issue in issues.iter_mut() {
+                let key = self.store_issue(issue);
+                *issue = Value::from(key);
+            }
+        }
+        if let Some(account_id) = response.get("accountId").and_then(|x| x.as_str()) {
+            self.data
+                .users
+                .insert(account_id.to_owned(), response.clone());
+        }
+        self.data.responses.insert(request.get_key(), response);
+    }
+
+    pub fn get(&self, request: &JiraRequest) -> Option<Value> {
+        let mut response = self.data.responses.get(&request.get_key())?.clone();
+        if let Some(keys) = response.get_mut("issues").and_then(|x| x.as_array_mut()) {
+            for key in keys.iter_mut() {
+                *key = self.get_issue(key.as_str()?)?.clone();
+            }
+        }
+        Some(response)
+    }
+
+    // Returns the key of the issue
+    pub fn store_issue(&mut self, issue: &Value) -> String {
+        self.changed = true;
+        let key = issue["key"].as_str().unwrap_or_default().to_owned();
+        let updated = issue
+            .pointer("/fields/updated")
+            .and_then(|x| x.as_str())
+            .unwrap_or_default()
+            .to_owned();
+
+        for role in ["creator", "reporter", "assignee"] {
+            let user = &issue["fields"][role];
+            if let Some(account_id) = user.get("accountId").and_then(|x| x.as_str()) {
+                self.data.users.insert(account_id.to_owned(), user.clone());
+            }
+        }
+
+        let unchanged = self
+            .data
+            .issues
+            .get(&key)
+            .is_some_and(|x| x.updated == updated);
+        if !unchanged {
+            self.data.issues.insert(
+                key.clone(),
+                CachedIssue {
+                    updated,
+                    issue: issue.clone(),
+                },
+            );
+        }
+        key
+    }
+
+    pub fn get_issue(&self, key: &str) -> Option<&Value> {
+        self.data.issues.get(key).map(|x| &x.issue)
+    }
+
+    pub fn get_user(&self, account_id: &str) -> Option<&Value> {
+        self.data.users.get(account_id)
+    }
+}
+
+impl Drop for JiraCache {
+    fn drop(&mut self) {
+        if self.changed {
+            let _ = self.save();
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PendingData {
+    version: u32,
+    #[serde(default)]
+    requests: VecDeque<JiraRequest>,
+    // Writes Jira refused when they were replayed, with the reason
+    #[serde(default)]
+    rejected: Vec<(JiraRequest, String)>,
+}
+
+impl Default for PendingData {
+    fn default() -> Self {
+        Self {
+            version: CACHE_VERSION,
+            requests: VecDeque::new(),
+            rejected: Vec::new(),
+        }
+    }
+}
+
+// Writes made while offline, in the order they have to be sent
+#[derive(Debug)]
+pub struct PendingWrites {
+    path: PathBuf,
+    data: PendingData,
+}
+
+impl PendingWrites {
+    pub fn load<P: Into<PathBuf>>(path: P) -> Self {
+        let path = path.into();
+        let data = read_versioned(&path, |x: &PendingData| x.version);
+        Self { path, data }
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        write_atomically(&self.path, &self.data)
+    }
+
+    pub fn push(&mut self, request: JiraRequest) {
+        self.data.requests.push_back(request);
+    }
+
+    // Out of the queue while it is sent, so it is not sent twice
+    pub fn take_next(&mut self) -> Option<JiraRequest> {
+        self.data.requests.pop_front()
+    }
+
+    // Back in front of the queue, when it could not be sent
+    pub fn put_back(&mut self, request: JiraRequest) {
+        self.data.requests.push_front(request);
+    }
+
+    pub fn reject(&mut self, request: JiraRequest, reason: String) {
+        self.data.rejected.push((request, reason));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.requests.is_empty()
+    }
+
+    pub fn get_requests(&self) -> impl Iterator<Item = &JiraRequest> {
+        self.data.requests.iter()
+    }
+
+    pub fn get_rejected(&self) -> &[(JiraRequest, String)] {
+        &self.data.rejected
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::jira::{request, JiraKey};
+    use serde_json::json;
+
+    // In the state folder of a vault of its own, named after the file
+    fn temporary_path(name: &str) -> PathBuf {
+        use crate::test_vault::temporary_vault;
+        let vault = temporary_vault(name.trim_end_matches(".json"));
+        vault.join(".obsidian-jira").join(name)
+    }
+
+    fn issue(key: &str, updated: &str, summary: &str) -> Value {
+        json!({
+            "key": key,
+            "fields": {
+                "summary": summary,
+                "updated": updated,
+                "assignee": { "accountId": "abc", "displayName": "Jane Doe" },
+            },
+        })
+    }
+
+    #[test]
+    fn serves_cached_searches() {
+        let path = temporary_path("cache.json");
+        let search = request::search_issues("abc", 10);
+        let response = json!({
+            "total": 2,
+            "issues": [
+                issue("MB-1", "2024-03-01T10:00:00.000+0100", "First"),
+                issue("MB-2", "2024-03-01T10:00:00.000+0100", "Second"),
+            ],
+        });
+
+        let mut cache = JiraCache::load(&path);
+        cache.store(&search, &response);
+        cache.store_issue(&issue("MB-2", "2024-03-02T10:00:00.000+0100", "Renamed"));
+        cache.save().unwrap();
+
+        let cache = JiraCache::load(&path);
+        let cached = cache.get(&search).unwrap();
+        assert_eq!(cached["total"], 2);
+        assert_eq!(cached["issues"][0], response["issues"][0]);
+        assert_eq!(cached["issues"][1]["fields"]["summary"], "Renamed");
+        assert_eq!(cache.get_user("abc").unwrap()["displayName"], "Jane Doe");
+        assert!(cache.get(&request::search_issues("abc", 20)).is_none());
+    }
+
+    #[test]
+    fn saves_when_dropped() {
+        let path = temporary_path("dropped_cache.json");
+        let mut cache = JiraCache::load(&path);
+        cache.store_issue(&issue("MB-3", "2024-03-01T10:00:00.000+0100", "Third"));
+        assert!(cache.is_changed());
+        assert!(!path.exists());
+        drop(cache);
+
+        let cache = JiraCache::load(&path);
+        assert!(!cache.is_changed());
+        assert_eq!(
+            cache.get_issue("MB-3").unwrap()["fields"]["summary"],
+            "Third"
+        );
+    }
+
+    #[test]
+    fn ignores_other_versions() {
+        let path = temporary_path("old_cache.json");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(
+            &path,
+            r#"{"version": 0, "issues": {"MB-1": {"updated": "", "issue": {}}}}"#,
+        )
+        .unwrap();
+        let cache = JiraCache::load(&path);
+        assert!(cache.get_issue("MB-1").is_none());
+    }
+
+    #[test]
+    fn keeps_pending_writes_in_order() {
+        let path = temporary_path("pending.json");
+        let key = JiraKey::new("MB-1");
+
+        let mut pending = PendingWrites::load(&path);
+        pending.push(request::transition_issue(&key, "31"));
+        pending.push(request::add_comment(&key, "Done"));
+        pending.save().unwrap();
+
+        let mut pending = PendingWrites::load(&path);
+        let first = pending.take_next().unwrap();
+        assert_eq!(first, request::transition_issue(&key, "31"));
+        pending.reject(first, String::from("No such transition"));
+        assert_eq!(
+            pending.take_next(),
+            Some(request::add_comment(&key, "Done"))
+        );
+        assert!(pending.is_empty());
+        assert_eq!(pending.get_rejected().len(), 1);
+
+        pending.push(request::add_comment(&key, "Again"));
+        let request = pending.take_next().unwrap();
+        assert!(pending.is_empty());
+        pending.push(request::add_comment(&key, "Later"));
+        pending.put_back(request);
+        assert_eq!(
+            pending.get_requests().collect::<Vec<_>>(),
+            [
+                &request::add_comment(&key, "Again"),
+                &request::add_comment(&key, "Later")
+            ]
+        );
+    }
+}
@@ -0,0 +1,444 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::de::DeserializeOwned;
+
+use super::error::JiraError;
+use super::request::JiraRequest;
+use crate::config::ConfigInner;
+use crate::credentials::{LazyToken, Secret, TokenSource};
+
+pub const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 5,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(60),
+        }
+    }
+}
+
+impl RetryPolicy {
+    // Exponential backoff, unless Jira told us how long to wait. Both are
+    // capped by `max_delay`.
+    pub fn get_delay(&self, attempt: u32, retry_after: Option<Duration>) -> Duration {
+        if let Some(retry_after) = retry_after {
+            return retry_after.min(self.max_delay);
+        }
+        self.base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay)
+    }
+}
+
+impl RetryPolicy {
+    // How long to wait before sending the request again, None to give up.
+    // The blocking and the async clients both ask here.
+    pub fn get_retry_delay(
+        &self,
+        error: &JiraError,
+        method: &reqwest::Method,
+        attempt: u32,
+        retry_after: Option<Duration>,
+    ) -> Option<Duration> {
+        if !error.is_transient(method) || attempt >= self.max_retries {
+            return None;
+        }
+        Some(self.get_delay(attempt, retry_after))
+    }
+}
+
+/* ~ Number of requests that can still be sent, retries included.
+ * The budget is a bucket of `capacity` requests that fills up again over
+ * `window`, so a runaway loop is stopped without failing a long session
+ * for good. ~ */
+#[derive(Debug)]
+pub struct RequestBudget {
+    capacity: u32,
+    window: Duration,
+    // Requests left, and when they were counted
+    state: Mutex<(f64, Instant)>,
+}
+
+impl Default for RequestBudget {
+    fn default() -> Self {
+        Self::new(1000, Duration::from_secs(600))
+    }
+}
+
+impl RequestBudget {
+    pub fn new(capacity: u32, window: Duration) -> Self {
+        Self {
+            capacity,
+            window,
+            state: Mutex::new((f64::from(capacity), Instant::now())),
+        }
+    }
+
+    pub fn take(&self) -> Result<(), JiraError> {
+        let mut state = self.state.lock().unwrap();
+        self.refill(&mut state);
+        if state.0 < 1.0 {
+            return Err(JiraError::BudgetExhausted);
+        }
+        state.0 -= 1.0;
+        Ok(())
+    }
+
+    pub fn get_remaining(&self) -> u32 {
+        let mut state = self.state.lock().unwrap();
+        self.refill(&mut state);
+        state.0 as u32
+    }
+
+    fn refill(&self, (remaining, counted): &mut (f64, Instant)) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(*counted).as_secs_f64();
+        let rate = f64::from(self.capacity) / self.window.as_secs_f64().max(f64::EPSILON);
+        *remaining = (*remaining + elapsed * rate).min(f64::from(self.capacity));
+        *counted = now;
+    }
+}
+
+/* ~ What the blocking and the async clients share: where and as whom the
+ * requests are sent, how they are retried and how many can be sent. The
+ * clients only bring the transport, and ask here before and after every
+ * attempt. ~ */
+#[derive(Debug)]
+pub struct Connection {
+    base_url: String,
+    user_mail: String,
+    // Read with the first request, a token that cannot be read fails them all
+    jira_token: LazyToken,
+    retry_policy: RetryPolicy,
+    budget: RequestBudget,
+}
+
+impl Connection {
+    pub fn new(base_url: &str, user_mail: &str, jira_token: &str) -> Self {
+        let jira_token = LazyToken::new(TokenSource::Plain(Secret::new(jira_token.to_owned())));
+        Self::with_token(base_url, user_mail, jira_token)
+    }
+
+    pub fn from_config(config: &ConfigInner) -> Self {
+        Self::with_token(
+            &base_url_from_config(config),
+            config.get_user_mail(),
+            config.get_lazy_jira_token().clone(),
+        )
+    }
+
+    fn with_token(base_url: &str, user_mail: &str, jira_token: LazyToken) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_owned(),
+            user_mail: user_mail.to_owned(),
+            jira_token,
+            retry_policy: RetryPolicy::default(),
+            budget: RequestBudget::default(),
+        }
+    }
+
+    pub fn set_retry_policy(&mut self, retry_policy: RetryPolicy) -> &mut Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    pub fn set_request_budget(&mut self, capacity: u32, window: Duration) -> &mut Self {
+        self.budget = RequestBudget::new(capacity, window);
+        self
+    }
+
+    pub fn get_remaining_budget(&self) -> u32 {
+        self.budget.get_remaining()
+    }
+
+    pub fn get_url(&self, request: &JiraRequest) -> String {
+        request.get_url(&self.base_url)
+    }
+
+    // The user and the password of the basic authentication
+    pub fn get_credentials(&self) -> Result<(&str, &str), JiraError> {
+        let token = self.jira_token.resolve().map_err(JiraError::Token)?;
+        Ok((&self.user_mail, token.expose()))
+    }
+
+    // Before every attempt, retries included
+    pub fn start_attempt(&self) -> Result<(), JiraError> {
+        self.budget.take()
+    }
+
+    // How long to wait before the next attempt, the error when giving up
+    pub fn get_retry_delay(
+        &self,
+        error: JiraError,
+        request: &JiraRequest,
+        attempt: u32,
+        retry_after: Option<Duration>,
+    ) -> Result<Duration, JiraError> {
+        self.retry_policy
+            .get_retry_delay(&error, request.get_method(), attempt, retry_after)
+            .ok_or(error)
+    }
+}
+
+#[derive(Debug)]
+pub struct JiraClient {
+    http: reqwest::blocking::Client,
+    connection: Connection,
+}
+
+impl JiraClient {
+    pub fn new(connection: Connection) -> Self {
+        let http = reqwest::blocking::Client::builder()
+            .connect_timeout(CONNECT_TIMEOUT)
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .unwrap();
+        Self { http, connection }
+    }
+
+    pub fn get_connection(&self) -> &Connection {
+        &self.connection
+    }
+
+    // To change the retry policy or the request budget
+    pub fn get_connection_mut(&mut self) -> &mut Connection {
+        &mut self.connection
+    }
+
+    pub fn send<T: DeserializeOwned>(&self, request: &JiraRequest) -> Result<T, JiraError> {
+        let text = self.execute(request)?;
+        Ok(serde_json::from_str(&text)?)
+    }
+
+    // Sends the request, retrying transient failures, and returns the body of the answer
+    pub fn execute(&self, request: &JiraRequest) -> Result<String, JiraError> {
+        let mut attempt = 0;
+        loop {
+            self.connection.start_attempt()?;
+
+            let (error, retry_after) = match self.build(request)?.send() {
+                Ok(response) if response.status().is_success() => return Ok(response.text()?),
+                Ok(response) => {
+                    let retry_after = parse_retry_after(response.headers());
+                    let status = response.status().as_u16();
+                    let body = response.text().unwrap_or_default();
+                    (JiraError::from_response(status, &body), retry_after)
+                }
+                Err(error) => (JiraError::Network(error), None),
+            };
+
+            let delay = self
+                .connection
+                .get_retry_delay(error, request, attempt, retry_after)?;
+            std::thread::sleep(delay);
+            attempt += 1;
+        }
+    }
+
+    fn build(&self, request: &JiraRequest) -> Result<reqwest::blocking::RequestBuilder, JiraError> {
+        let (user, password) = self.connection.get_credentials()?;
+        let builder = self
+            .http
+            .request(
+                request.get_method().clone(),
+                self.connection.get_url(request),
+            )
+            .basic_auth(user, Some(password))
+            .query(request.get_query());
+
+        Ok(match request.get_body() {
+            Some(body) => builder.json(body),
+            None => builder,
+        })
+    }
+}
+
+pub fn base_url_from_config(config: &ConfigInner) -> String {
+    base_url(config.get_jira_url())
+}
+
+// `jira_url` is only the site name, like `mycompany` for mycompany.atlassian.net
+pub fn base_url(jira_url: &str) -> String {
+    format!("https://{}.atlassian.net", jira_url)
+}
+
+// Jira sends the number of seconds to wait
+pub fn parse_retry_after(headers: &reqwest::header::HeaderMap) -> Option<Duration> {
+    let seconds: u64 = headers
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()?;
+    Some(Duration::from_secs(seconds))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::{Read, Write};
+
+    // Answers each connection with the next of the given status lines and bodies
+    fn serve(responses: Vec<(&'static str, &'static str)>) -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            for (status, body) in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = Vec::new();
+                let mut buffer = [0; 1024];
+                while !request.windows(4).any(|x| x == b"\r\n\r\n") {
+                    let read = stream.read(&mut buffer).unwrap();
+                    if read == 0 {
+                        break;
+                    }
+                    request.extend_from_slice(&buffer[..read]);
+                }
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+        format!("http://{}", address)
+    }
+
+    fn fast_client(base_url: &str) -> JiraClient {
+        let mut client = JiraClient::new(Connection::new(base_url, "me@example.com", "token"));
+        client.get_connection_mut().set_retry_policy(RetryPolicy {
+            max_retries: 2,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(10),
+        });
+        client
+    }
+
+    const UNAVAILABLE: (&str, &str) = ("503 Service Unavailable\r\nRetry-After: 0", "");
+    const NOT_FOUND: (&str, &str) = (
+        "404 Not Found",
+        r#"{"errorMessages":["Issue does not exist"],"errors":{}}"#,
+    );
+    const OK: (&str, &str) = ("200 OK", r#"{"total":3}"#);
+
+    #[test]
+    fn backoff() {
+        let policy = RetryPolicy {
+            max_retries: 10,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(1),
+        };
+        assert_eq!(policy.get_delay(0, None), Duration::from_millis(100));
+        assert_eq!(policy.get_delay(2, None), Duration::from_millis(400));
+        assert_eq!(policy.get_delay(8, None), Duration::from_secs(1));
+        assert_eq!(
+            policy.get_delay(0, Some(Duration::from_millis(700))),
+            Duration::from_millis(700)
+        );
+        assert_eq!(
+            policy.get_delay(0, Some(Duration::from_secs(3600))),
+            Duration::from_secs(1)
+        );
+
+        let post = reqwest::Method::POST;
+        assert_eq!(
+            policy.get_retry_delay(&JiraError::RateLimited, &post, 1, None),
+            Some(Duration::from_millis(200))
+        );
+        assert_eq!(
+            policy.get_retry_delay(&JiraError::RateLimited, &post, 10, None),
+            None
+        );
+        assert_eq!(
+            policy.get_retry_delay(&JiraError::NotFound(String::new()), &post, 0, None),
+            None
+        );
+    }
+
+    #[test]
+    fn retries_transient_errors() {
+        let client = fast_client(&serve(vec![UNAVAILABLE, UNAVAILABLE, OK]));
+        let request = JiraRequest::new(reqwest::Method::GET, String::from("/rest/api/2/search"));
+
+        let answer: serde_json::Value = client.send(&request).unwrap();
+        assert_eq!(answer["total"], 3);
+        assert_eq!(client.get_connection().get_remaining_budget(), 997);
+    }
+
+    #[test]
+    fn gives_up() {
+        let client = fast_client(&serve(vec![UNAVAILABLE, UNAVAILABLE, UNAVAILABLE]));
+        let request = JiraRequest::new(reqwest::Method::GET, String::from("/"));
+        assert!(matches!(
+            client.execute(&request),
+            Err(JiraError::Server(503, _))
+        ));
+
+        let client = fast_client(&serve(vec![NOT_FOUND]));
+        match client.execute(&request) {
+            Err(JiraError::NotFound(message)) => assert_eq!(message, "Issue does not exist"),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn request_budget() {
+        let mut client = fast_client(&serve(vec![UNAVAILABLE, OK]));
+        client
+            .get_connection_mut()
+            .set_request_budget(1, Duration::from_secs(3600));
+        let request = JiraRequest::new(reqwest::Method::GET, String::from("/"));
+        assert!(matches!(
+            client.execute(&request),
+            Err(JiraError::BudgetExhausted)
+        ));
+    }
+
+    #[test]
+    fn reads_the_token_with_the_first_request() {
+        let variable = "OBSIDIAN_JIRA_CLIENT_TEST_TOKEN";
+        let token = LazyToken::new(TokenSource::Env(variable.to_owned()));
+        let client = JiraClient::new(Connection::with_token(
+            &serve(vec![OK]),
+            "me@example.com",
+            token,
+        ));
+        let request = JiraRequest::new(reqwest::Method::GET, String::from("/"));
+        match client.execute(&request) {
+            Err(JiraError::Token(message)) => assert!(message.contains(variable)),
+            other => panic!("{:?}", other),
+        }
+
+        std::env::set_var(variable, "token");
+        assert!(client.execute(&request).is_ok());
+    }
+
+    #[test]
+    fn refills_the_request_budget() {
+        let budget = RequestBudget::new(2, Duration::from_millis(100));
+        budget.take().unwrap();
+        budget.take().unwrap();
+        assert!(matches!(budget.take(), Err(JiraError::BudgetExhausted)));
+
+        std::thread::sleep(Duration::from_millis(60));
+        assert_eq!(budget.get_remaining(), 1);
+        budget.take().unwrap();
+
+        std::thread::sleep(Duration::from_millis(300));
+        assert_eq!(budget.get_remaining(), 2);
+    }
+}
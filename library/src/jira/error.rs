@@ -0,0 +1,203 @@
+use serde::Deserialize;
+
+#[derive(Debug)]
+pub enum JiraError {
+    // 401, the mail or the token are wrong
+    Unauthorized(String),
+    // 403, the user cannot see or do this
+    Forbidden(String),
+    // 404, also what Jira answers for issues the user cannot see
+    NotFound(String),
+    // 429, still limited after all the retries
+    RateLimited,
+    // 5xx, still failing after all the retries
+    Server(u16, String),
+    // Any other unsuccessful status
+    Status(u16, String),
+    BudgetExhausted,
+    // The token of the config could not be read, see `credentials`
+    Token(String),
+    Network(reqwest::Error),
+    Decode(serde_json::Error),
+    // Reading or writing the local cache or the notes
+    Io(std::io::Error),
+}
+
+impl JiraError {
+    pub fn from_response(status: u16, body: &str) -> Self {
+        let message = error_message(body);
+        match status {
+            401 => JiraError::Unauthorized(message),
+            403 => JiraError::Forbidden(message),
+            404 => JiraError::NotFound(message),
+            429 => JiraError::RateLimited,
+            500..=599 => JiraError::Server(status, message),
+            _ => JiraError::Status(status, message),
+        }
+    }
+
+    // Whether trying again later could give a different answer. A request
+    // that timed out or got a server error may have been applied by Jira, so
+    // it is only sent again when applying it twice does no harm, unlike a POST
+    // adding a comment.
+    pub fn is_transient(&self, method: &reqwest::Method) -> bool {
+        match self {
+            JiraError::RateLimited => true,
+            JiraError::Server(status, _) => {
+                matches!(status, 500 | 502..=504) && is_idempotent(method)
+            }
+            JiraError::Network(error) => {
+                error.is_connect() || (error.is_timeout() && is_idempotent(method))
+            }
+            _ => false,
+        }
+    }
+
+    // Jira cannot be reached at all, as opposed to Jira saying no
+    pub fn is_offline(&self) -> bool {
+        matches!(
+            self,
+            JiraError::Network(_) | JiraError::Server(502..=504, _)
+        )
+    }
+
+    // Whether the request can be queued and sent once Jira is back: it is
+    // offline, and the request either never reached Jira or does no harm twice
+    pub fn can_send_later(&self, method: &reqwest::Method) -> bool {
+        match self {
+            JiraError::Network(error) if error.is_connect() => true,
+            _ => self.is_offline() && is_idempotent(method),
+        }
+    }
+}
+
+// Sending them twice leaves Jira as sending them once
+fn is_idempotent(method: &reqwest::Method) -> bool {
+    use reqwest::Method;
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::PUT | Method::DELETE
+    )
+}
+
+// Jira errors look like {"errorMessages": [...], "errors": {"field": "message"}}
+fn error_message(body: &str) -> String {
+    #[derive(Deserialize, Default)]
+    #[serde(rename_all = "camelCase")]
+    struct Intermediate {
+        #[serde(default)]
+        error_messages: Vec<String>,
+        #[serde(default)]
+        errors: std::collections::BTreeMap<String, String>,
+    }
+
+    let Ok(parsed) = serde_json::from_str::<Intermediate>(body) else {
+        return body.trim().to_owned();
+    };
+
+    parsed
+        .error_messages
+        .into_iter()
+        .chain(
+            parsed
+                .errors
+                .into_iter()
+                .map(|(field, message)| format!("{}: {}", field, message)),
+        )
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+impl std::fmt::Display for JiraError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JiraError::Unauthorized(message) => {
+                write!(f, "Jira rejected the credentials: {}", message)
+            }
+            JiraError::Forbidden(message) => write!(f, "Not allowed by Jira: {}", message),
+            JiraError::NotFound(message) => write!(f, "Not found in Jira: {}", message),
+            JiraError::RateLimited => write!(f, "Rate limited by Jira"),
+            JiraError::Server(status, message) => {
+                write!(f, "Jira server error {}: {}", status, message)
+            }
+            JiraError::Status(status, message) => {
+                write!(f, "Jira answered {}: {}", status, message)
+            }
+            JiraError::BudgetExhausted => {
+                write!(f, "The Jira request budget is exhausted, try again later")
+            }
+            JiraError::Token(message) => write!(f, "Could not get the Jira token: {}", message),
+            JiraError::Network(error) => write!(f, "Could not reach Jira: {}", error),
+            JiraError::Decode(error) => write!(f, "Unexpected answer from Jira: {}", error),
+            JiraError::Io(error) => write!(f, "Could not read or write a local file: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for JiraError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            JiraError::Network(error) => Some(error),
+            JiraError::Decode(error) => Some(error),
+            JiraError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for JiraError {
+    fn from(error: reqwest::Error) -> Self {
+        JiraError::Network(error)
+    }
+}
+
+impl From<std::io::Error> for JiraError {
+    fn from(error: std::io::Error) -> Self {
+        JiraError::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for JiraError {
+    fn from(error: serde_json::Error) -> Self {
+        JiraError::Decode(error)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::JiraError;
+
+    #[test]
+    fn status_to_error() {
+        let body = r#"{"errorMessages":["Issue does not exist"],"errors":{}}"#;
+        match JiraError::from_response(404, body) {
+            JiraError::NotFound(message) => assert_eq!(message, "Issue does not exist"),
+            other => panic!("{:?}", other),
+        }
+
+        let body = r#"{"errorMessages":[],"errors":{"summary":"Too long"}}"#;
+        match JiraError::from_response(400, body) {
+            JiraError::Status(400, message) => assert_eq!(message, "summary: Too long"),
+            other => panic!("{:?}", other),
+        }
+
+        assert!(matches!(
+            JiraError::from_response(401, "Unauthorized"),
+            JiraError::Unauthorized(_)
+        ));
+        let get = reqwest::Method::GET;
+        assert!(JiraError::from_response(503, "").is_transient(&get));
+        assert!(JiraError::from_response(429, "").is_transient(&get));
+        assert!(!JiraError::from_response(403, "").is_transient(&get));
+        assert!(!JiraError::from_response(501, "").is_transient(&get));
+        // A comment may have been added before the gateway failed
+        let post = reqwest::Method::POST;
+        assert!(!JiraError::from_response(502, "").is_transient(&post));
+        assert!(!JiraError::from_response(504, "").can_send_later(&post));
+        assert!(JiraError::from_response(504, "").can_send_later(&reqwest::Method::PUT));
+
+        // Jira answered, it is not offline
+        assert!(JiraError::from_response(503, "").is_offline());
+        assert!(!JiraError::from_response(500, "").is_offline());
+    }
+}
@@ -0,0 +1,1003 @@
+use serde_json::{json, Value};
+
+use super::JiraKey;
+use crate::obsidian::wikilink::{find_wikilinks, Wikilink};
+
+/*
+    Conversion between the Markdown of the task notes and the two formats Jira
+    speaks: wiki markup (API v2) and the Atlassian Document Format (API v3).
+
+    Every format is first parsed into a `Document`, and rendered from it, so any
+    pair of formats can be converted and the conversions can be checked against
+    each other. Only what we actually write in the notes is supported: headings,
+    paragraphs, (nested) lists, checkboxes, code fences, inline code, links and
+    wikilinks. Anything else is kept as plain text.
+*/
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Document {
+    blocks: Vec<Block>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Block {
+    Heading(u8, Vec<Inline>),
+    Paragraph(Vec<Inline>),
+    List(List),
+    Code {
+        language: Option<String>,
+        text: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct List {
+    ordered: bool,
+    items: Vec<ListItem>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListItem {
+    checked: Option<bool>,
+    content: Vec<Inline>,
+    children: Vec<List>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Inline {
+    Text(String),
+    Code(String),
+    Link { text: String, url: String },
+    WikiLink(Wikilink),
+    Break,
+}
+
+type KeyLookup<'a> = Box<dyn Fn(&Wikilink) -> Option<JiraKey> + 'a>;
+
+// Wikilinks pointing to a note with a Jira key become links to the issue, the
+// rest are left as `[[...]]` text so they survive the trip back to Obsidian.
+pub struct LinkResolver<'a> {
+    jira_url: &'a str,
+    lookup: KeyLookup<'a>,
+}
+
+impl<'a> LinkResolver<'a> {
+    pub fn new<F: Fn(&Wikilink) -> Option<JiraKey> + 'a>(jira_url: &'a str, lookup: F) -> Self {
+        Self {
+            jira_url,
+            lookup: Box::new(lookup),
+        }
+    }
+
+    pub fn unresolved() -> Self {
+        Self::new("", |_| None)
+    }
+
+    fn resolve(&self, link: &Wikilink) -> Option<String> {
+        (self.lookup)(link).map(|key| key.get_browse_url(self.jira_url))
+    }
+}
+
+pub fn markdown_to_wiki(input: &str, links: &LinkResolver) -> String {
+    Document::from_markdown(input).to_wiki(links)
+}
+
+pub fn markdown_to_adf(input: &str, links: &LinkResolver) -> Value {
+    Document::from_markdown(input).to_adf(links)
+}
+
+pub fn wiki_to_markdown(input: &str) -> String {
+    Document::from_wiki(input).to_markdown()
+}
+
+pub fn adf_to_markdown(input: &Value) -> String {
+    Document::from_adf(input).to_markdown()
+}
+
+impl Document {
+    pub fn get_blocks(&self) -> &[Block] {
+        &self.blocks
+    }
+
+    pub fn from_markdown(input: &str) -> Self {
+        let lines: Vec<&str> = input.lines().collect();
+        let mut blocks = Vec::new();
+        let mut paragraph: Vec<&str> = Vec::new();
+        let mut index = 0;
+
+        while index < lines.len() {
+            let line = lines[index];
+
+            if let Some(language) = line.trim_start().strip_prefix("```") {
+                flush_paragraph(&mut paragraph, &mut blocks, parse_markdown_inlines);
+                let mut code_lines = Vec::new();
+                index += 1;
+                while index < lines.len() && !lines[index].trim_start().starts_with("```") {
+                    code_lines.push(lines[index]);
+                    index += 1;
+                }
+                blocks.push(Block::Code {
+                    language: non_empty(language.trim()),
+                    text: code_lines.join("\n"),
+                });
+                index += 1;
+                continue;
+            }
+
+            if line.trim().is_empty() {
+                flush_paragraph(&mut paragraph, &mut blocks, parse_markdown_inlines);
+                index += 1;
+                continue;
+            }
+
+            if let Some((level, text)) = parse_markdown_heading(line) {
+                flush_paragraph(&mut paragraph, &mut blocks, parse_markdown_inlines);
+                blocks.push(Block::Heading(level, parse_markdown_inlines(text)));
+                index += 1;
+                continue;
+            }
+
+            if parse_markdown_list_line(line).is_some() {
+                flush_paragraph(&mut paragraph, &mut blocks, parse_markdown_inlines);
+                blocks.push(Block::List(parse_markdown_list(&lines, &mut index)));
+                continue;
+            }
+
+            paragraph.push(line.trim());
+            index += 1;
+        }
+        flush_paragraph(&mut paragraph, &mut blocks, parse_markdown_inlines);
+
+        Self { blocks }
+    }
+
+    pub fn from_wiki(input: &str) -> Self {
+        let lines: Vec<&str> = input.lines().collect();
+        let mut blocks = Vec::new();
+        let mut paragraph: Vec<&str> = Vec::new();
+        let mut index = 0;
+
+        while index < lines.len() {
+            let line = lines[index];
+            let trimmed = line.trim();
+
+            if let Some((language, closing)) = parse_wiki_code_start(trimmed) {
+                flush_paragraph(&mut paragraph, &mut blocks, parse_wiki_inlines);
+                let mut code_lines = Vec::new();
+                index += 1;
+                while index < lines.len() && lines[index].trim() != closing {
+                    code_lines.push(lines[index]);
+                    index += 1;
+                }
+                blocks.push(Block::Code {
+                    language,
+                    text: code_lines.join("\n"),
+                });
+                index += 1;
+                continue;
+            }
+
+            if trimmed.is_empty() {
+                flush_paragraph(&mut paragraph, &mut blocks, parse_wiki_inlines);
+                index += 1;
+                continue;
+            }
+
+            if let Some((level, text)) = parse_wiki_heading(trimmed) {
+                flush_paragraph(&mut paragraph, &mut blocks, parse_wiki_inlines);
+                blocks.push(Block::Heading(level, parse_wiki_inlines(text)));
+                index += 1;
+                continue;
+            }
+
+            if let Some((markers, _)) = parse_wiki_list_line(trimmed) {
+                flush_paragraph(&mut paragraph, &mut blocks, parse_wiki_inlines);
+                blocks.push(Block::List(parse_wiki_list(
+                    &lines,
+                    &mut index,
+                    markers.len(),
+                )));
+                continue;
+            }
+
+            paragraph.push(trimmed);
+            index += 1;
+        }
+        flush_paragraph(&mut paragraph, &mut blocks, parse_wiki_inlines);
+
+        Self { blocks }
+    }
+
+    pub fn from_adf(input: &Value) -> Self {
+        let mut blocks = Vec::new();
+        for node in adf_children(input) {
+            parse_adf_block(node, &mut blocks);
+        }
+        Self { blocks }
+    }
+
+    pub fn to_markdown(&self) -> String {
+        self.blocks
+            .iter()
+            .map(|block| match block {
+                Block::Heading(level, content) => format!(
+                    "{} {}",
+                    "#".repeat(*level as usize),
+                    render_markdown_inlines(content)
+                ),
+                Block::Paragraph(content) => render_markdown_inlines(content),
+                Block::List(list) => {
+                    let mut output = Vec::new();
+                    render_markdown_list(list, 0, &mut output);
+                    output.join("\n")
+                }
+                Block::Code { language, text } => format!(
+                    "```{}\n{}\n```",
+                    language.as_deref().unwrap_or_default(),
+                    text
+                ),
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+
+    pub fn to_wiki(&self, links: &LinkResolver) -> String {
+        self.blocks
+            .iter()
+            .map(|block| match block {
+                Block::Heading(level, content) => {
+                    format!("h{}. {}", level, render_wiki_inlines(content, links))
+                }
+                Block::Paragraph(content) => render_wiki_inlines(content, links),
+                Block::List(list) => {
+                    let mut output = Vec::new();
+                    render_wiki_list(list, "", links, &mut output);
+                    output.join("\n")
+                }
+                Block::Code { language, text } => match language {
+                    Some(language) => format!("{{code:{}}}\n{}\n{{code}}", language, text),
+                    None => format!("{{code}}\n{}\n{{code}}", text),
+                },
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+
+    pub fn to_adf(&self, links: &LinkResolver) -> Value {
+        let mut task_ids = 0;
+        let content: Vec<Value> = self
+            .blocks
+            .iter()
+            .map(|block| match block {
+                Block::Heading(level, content) => json!({
+                    "type": "heading",
+                    "attrs": { "level": level },
+                    "content": render_adf_inlines(content, links),
+                }),
+                Block::Paragraph(content) => json!({
+                    "type": "paragraph",
+                    "content": render_adf_inlines(content, links),
+                }),
+                Block::List(list) => render_adf_list(list, links, &mut task_ids),
+                Block::Code { language, text } => {
+                    let mut node = json!({
+                        "type": "codeBlock",
+                        "content": [{ "type": "text", "text": text }],
+                    });
+                    if let Some(language) = language {
+                        node["attrs"] = json!({ "language": language });
+                    }
+                    node
+                }
+            })
+            .collect();
+
+        json!({
+            "version": 1,
+            "type": "doc",
+            "content": content,
+        })
+    }
+}
+
+fn non_empty(input: &str) -> Option<String> {
+    if input.is_empty() {
+        None
+    } else {
+        Some(input.to_owned())
+    }
+}
+
+fn flush_paragraph(
+    lines: &mut Vec<&str>,
+    blocks: &mut Vec<Block>,
+    parse_inlines: fn(&str) -> Vec<Inline>,
+) {
+    if lines.is_empty() {
+        return;
+    }
+    let mut content = Vec::new();
+    for (index, line) in lines.drain(..).enumerate() {
+        if index != 0 {
+            content.push(Inline::Break);
+        }
+        content.extend(parse_inlines(line));
+    }
+    blocks.push(Block::Paragraph(content));
+}
+
+fn push_text(output: &mut Vec<Inline>, text: &str) {
+    if text.is_empty() {
+        return;
+    }
+    if let Some(Inline::Text(previous)) = output.last_mut() {
+        previous.push_str(text);
+    } else {
+        output.push(Inline::Text(text.to_owned()));
+    }
+}
+
+// Splits the wikilinks, embeds included, out of a piece of plain text
+fn push_text_with_wikilinks(output: &mut Vec<Inline>, text: &str) {
+    let mut offset = 0;
+    for (range, link) in find_wikilinks(text) {
+        push_text(output, &text[offset..range.start]);
+        output.push(Inline::WikiLink(link));
+        offset = range.end;
+    }
+    push_text(output, &text[offset..]);
+}
+
+fn parse_markdown_heading(line: &str) -> Option<(u8, &str)> {
+    let level = line.chars().take_while(|x| *x == '#').count();
+    if level == 0 || level > 6 {
+        return None;
+    }
+    let text = line[level..].strip_prefix(' ')?;
+    Some((level as u8, text.trim()))
+}
+
+struct ListLine<'a> {
+    indent: usize,
+    ordered: bool,
+    checked: Option<bool>,
+    text: &'a str,
+}
+
+fn parse_markdown_list_line(line: &str) -> Option<ListLine<'_>> {
+    static LIST_LINE: std::sync::OnceLock<regex::Regex> = std::sync::OnceLock::new();
+    let re = LIST_LINE.get_or_init(|| {
+        regex::Regex::new(r"^(\s*)([-*+]|\d+[.)])(?:\s+\[([ xX])\])?(?:\s+(.*))?$").unwrap()
+    });
+    let capture = re.captures(line)?;
+    let indent = capture
+        .get(1)
+        .unwrap()
+        .as_str()
+        .chars()
+        .map(|x| if x == '\t' { 4 } else { 1 })
+        .sum();
+    let ordered = capture.get(2).unwrap().as_str().ends_with(['.', ')']);
+    let checked = capture.get(3).map(|x| x.as_str() != " ");
+    let text = capture
+        .get(4)
+        .map(|x| x.as_str().trim())
+        .unwrap_or_default();
+
+    Some(ListLine {
+        indent,
+        ordered,
+        checked,
+        text,
+    })
+}
+
+fn parse_markdown_list(lines: &[&str], index: &mut usize) -> List {
+    let first = parse_markdown_list_line(lines[*index]).unwrap();
+    let mut list = List {
+        ordered: first.ordered,
+        items: Vec::new(),
+    };
+
+    while *index < lines.len() {
+        let Some(line) = parse_markdown_list_line(lines[*index]) else {
+            break;
+        };
+        if line.indent < first.indent {
+            break;
+        }
+        if line.indent > first.indent {
+            let child = parse_markdown_list(lines, index);
+            match list.items.last_mut() {
+                Some(item) => item.children.push(child),
+                None => break,
+            }
+            continue;
+        }
+        if line.ordered != list.ordered {
+            break;
+        }
+        list.items.push(ListItem {
+            checked: line.checked,
+            content: parse_markdown_inlines(line.text),
+            children: Vec::new(),
+        });
+        *index += 1;
+    }
+
+    list
+}
+
+fn parse_markdown_inlines(input: &str) -> Vec<Inline> {
+    let mut output = Vec::new();
+    let mut rest = input;
+
+    while !rest.is_empty() {
+        let Some(start) = rest.find(['`', '[']) else {
+            break;
+        };
+        let (before, candidate) = rest.split_at(start);
+
+        if let Some(code) = candidate.strip_prefix('`') {
+            if let Some(end) = code.find('`') {
+                push_text_with_wikilinks(&mut output, before);
+                output.push(Inline::Code(code[..end].to_owned()));
+                rest = &code[end + 1..];
+                continue;
+            }
+        } else if candidate.starts_with("[[") {
+            if let Some(end) = candidate.find("]]") {
+                push_text_with_wikilinks(&mut output, &rest[..start + end + 2]);
+                rest = &candidate[end + 2..];
+                continue;
+            }
+        } else if let Some((text, after)) = candidate[1..].split_once("](") {
+            if let Some(end) = after.find(')') {
+                if !text.contains(']') {
+                    push_text_with_wikilinks(&mut output, before);
+                    output.push(Inline::Link {
+                        text: text.to_owned(),
+                        url: after[..end].to_owned(),
+                    });
+                    rest = &after[end + 1..];
+                    continue;
+                }
+            }
+        }
+
+        push_text_with_wikilinks(&mut output, &rest[..start + 1]);
+        rest = &candidate[1..];
+    }
+    push_text_with_wikilinks(&mut output, rest);
+
+    output
+}
+
+fn parse_wiki_heading(line: &str) -> Option<(u8, &str)> {
+    let rest = line.strip_prefix('h')?;
+    let level = rest.chars().next()?.to_digit(10)?;
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+    let text = rest[1..].strip_prefix(". ")?;
+    Some((level as u8, text.trim()))
+}
+
+fn parse_wiki_code_start(line: &str) -> Option<(Option<String>, &'static str)> {
+    if line == "{noformat}" {
+        return Some((None, "{noformat}"));
+    }
+    let inner = line.strip_prefix("{code")?.strip_suffix('}')?;
+    if inner.is_empty() {
+        return Some((None, "{code}"));
+    }
+    let language = inner
+        .strip_prefix(':')?
+        .split('|')
+        .find(|x| !x.contains('='))
+        .and_then(non_empty);
+    Some((language, "{code}"))
+}
+
+fn parse_wiki_list_line(line: &str) -> Option<(&str, &str)> {
+    let markers_length = line.chars().take_while(|x| *x == '*' || *x == '#').count();
+    if markers_length == 0 {
+        return None;
+    }
+    let text = line[markers_length..].strip_prefix(' ')?;
+    Some((&line[..markers_length], text.trim()))
+}
+
+fn parse_wiki_list(lines: &[&str], index: &mut usize, depth: usize) -> List {
+    let (first_markers, _) = parse_wiki_list_line(lines[*index].trim()).unwrap();
+    let mut list = List {
+        ordered: first_markers.ends_with('#'),
+        items: Vec::new(),
+    };
+
+    while *index < lines.len() {
+        let Some((markers, text)) = parse_wiki_list_line(lines[*index].trim()) else {
+            break;
+        };
+        if markers.len() < depth {
+            break;
+        }
+        // A deeper item, even several levels deeper like `*` then `***`,
+        // belongs to the last item
+        if markers.len() > depth {
+            let child = parse_wiki_list(lines, index, markers.len());
+            let Some(item) = list.items.last_mut() else {
+                break;
+            };
+            // Like `** two` after `*** deep`, in the same list as written
+            match item.children.last_mut() {
+                Some(last) if last.ordered == child.ordered => last.items.extend(child.items),
+                _ => item.children.push(child),
+            }
+            continue;
+        }
+        if markers.ends_with('#') != list.ordered {
+            break;
+        }
+        // Wiki markup has no checkboxes, we write them as the (/) and (x) icons
+        let (checked, text) = if let Some(text) = text.strip_prefix("(/)") {
+            (Some(true), text.trim_start())
+        } else if let Some(text) = text.strip_prefix("(x)") {
+            (Some(false), text.trim_start())
+        } else {
+            (None, text)
+        };
+        list.items.push(ListItem {
+            checked,
+            content: parse_wiki_inlines(text),
+            children: Vec::new(),
+        });
+        *index += 1;
+    }
+
+    list
+}
+
+fn parse_wiki_inlines(input: &str) -> Vec<Inline> {
+    let mut output = Vec::new();
+    let mut text = String::new();
+    let mut rest = input;
+
+    while let Some(character) = rest.chars().next() {
+        if character == '\\' {
+            if let Some(escaped) = rest[1..].chars().next() {
+                text.push(escaped);
+                rest = &rest[1 + escaped.len_utf8()..];
+                continue;
+            }
+        } else if let Some(code) = rest.strip_prefix("{{") {
+            if let Some(end) = code.find("}}") {
+                push_text_with_wikilinks(&mut output, &text);
+                text.clear();
+                output.push(Inline::Code(code[..end].to_owned()));
+                rest = &code[end + 2..];
+                continue;
+            }
+        } else if character == '[' {
+            if let Some(end) = rest.find(']') {
+                let inner = &rest[1..end];
+                let (link_text, url) = match inner.split_once('|') {
+                    Some((link_text, url)) => (link_text, url),
+                    None => (inner, inner),
+                };
+                if !inner.is_empty() {
+                    push_text_with_wikilinks(&mut output, &text);
+                    text.clear();
+                    output.push(Inline::Link {
+                        text: link_text.to_owned(),
+                        url: url.to_owned(),
+                    });
+                    rest = &rest[end + 1..];
+                    continue;
+                }
+            }
+        }
+        text.push(character);
+        rest = &rest[character.len_utf8()..];
+    }
+    push_text_with_wikilinks(&mut output, &text);
+
+    output
+}
+
+fn adf_children(node: &Value) -> &[Value] {
+    node.get("content")
+        .and_then(|x| x.as_array())
+        .map(|x| x.as_slice())
+        .unwrap_or_default()
+}
+
+fn adf_str<'a>(node: &'a Value, pointer: &str) -> Option<&'a str> {
+    node.pointer(pointer).and_then(|x| x.as_str())
+}
+
+fn parse_adf_block(node: &Value, blocks: &mut Vec<Block>) {
+    match adf_str(node, "/type").unwrap_or_default() {
+        "heading" => {
+            let level = node
+                .pointer("/attrs/level")
+                .and_then(|x| x.as_u64())
+                .unwrap_or(1);
+            blocks.push(Block::Heading(level as u8, parse_adf_inlines(node)));
+        }
+        "paragraph" => blocks.push(Block::Paragraph(parse_adf_inlines(node))),
+        "bulletList" | "orderedList" | "taskList" => blocks.push(Block::List(parse_adf_list(node))),
+        "codeBlock" => blocks.push(Block::Code {
+            language: adf_str(node, "/attrs/language").and_then(non_empty),
+            text: adf_children(node)
+                .iter()
+                .filter_map(|x| adf_str(x, "/text"))
+                .collect(),
+        }),
+        // Quotes, panels, tables... we only keep what is inside
+        _ => {
+            for child in adf_children(node) {
+                parse_adf_block(child, blocks);
+            }
+        }
+    }
+}
+
+fn parse_adf_list(node: &Value) -> List {
+    let mut list = List {
+        ordered: adf_str(node, "/type") == Some("orderedList"),
+        items: Vec::new(),
+    };
+
+    for child in adf_children(node) {
+        match adf_str(child, "/type").unwrap_or_default() {
+            "taskItem" => list.items.push(ListItem {
+                checked: Some(adf_str(child, "/attrs/state") == Some("DONE")),
+                content: parse_adf_inlines(child),
+                children: Vec::new(),
+            }),
+            // In ADF, sub-tasks are siblings of the task they belong to
+            "taskList" => {
+                let sublist = parse_adf_list(child);
+                match list.items.last_mut() {
+                    Some(item) => item.children.push(sublist),
+                    None => list.items.extend(sublist.items),
+                }
+            }
+            "listItem" => {
+                let mut item = ListItem {
+                    checked: None,
+                    content: Vec::new(),
+                    children: Vec::new(),
+                };
+                for grandchild in adf_children(child) {
+                    match adf_str(grandchild, "/type").unwrap_or_default() {
+                        "bulletList" | "orderedList" | "taskList" => {
+                            item.children.push(parse_adf_list(grandchild))
+                        }
+                        _ => {
+                            if !item.content.is_empty() {
+                                item.content.push(Inline::Break);
+                            }
+                            item.content.extend(parse_adf_inlines(grandchild));
+                        }
+                    }
+                }
+                list.items.push(item);
+            }
+            _ => {}
+        }
+    }
+
+    list
+}
+
+fn parse_adf_inlines(node: &Value) -> Vec<Inline> {
+    let mut output = Vec::new();
+
+    for child in adf_children(node) {
+        match adf_str(child, "/type").unwrap_or_default() {
+            "text" => {
+                let text = adf_str(child, "/text").unwrap_or_default();
+                let marks = child
+                    .get("marks")
+                    .and_then(|x| x.as_array())
+                    .map(|x| x.as_slice())
+                    .unwrap_or_default();
+                let link = marks
+                    .iter()
+                    .find(|x| adf_str(x, "/type") == Some("link"))
+                    .and_then(|x| adf_str(x, "/attrs/href"));
+
+                if let Some(url) = link {
+                    output.push(Inline::Link {
+                        text: text.to_owned(),
+                        url: url.to_owned(),
+                    });
+                } else if marks.iter().any(|x| adf_str(x, "/type") == Some("code")) {
+                    output.push(Inline::Code(text.to_owned()));
+                } else {
+                    push_text_with_wikilinks(&mut output, text);
+                }
+            }
+            "hardBreak" => output.push(Inline::Break),
+            "inlineCard" => {
+                if let Some(url) = adf_str(child, "/attrs/url") {
+                    output.push(Inline::Link {
+                        text: url.to_owned(),
+                        url: url.to_owned(),
+                    });
+                }
+            }
+            "mention" | "emoji" => push_text(
+                &mut output,
+                adf_str(child, "/attrs/text").unwrap_or_default(),
+            ),
+            _ => output.extend(parse_adf_inlines(child)),
+        }
+    }
+
+    output
+}
+
+fn render_markdown_inlines(content: &[Inline]) -> String {
+    content
+        .iter()
+        .map(|inline| match inline {
+            Inline::Text(text) => text.clone(),
+            Inline::Code(code) => format!("`{}`", code),
+            Inline::Link { text, url } => format!("[{}]({})", text, url),
+            Inline::WikiLink(link) => link.to_text(),
+            Inline::Break => String::from("\n"),
+        })
+        .collect()
+}
+
+fn render_markdown_list(list: &List, depth: usize, output: &mut Vec<String>) {
+    for (index, item) in list.items.iter().enumerate() {
+        let marker = if list.ordered {
+            format!("{}.", index + 1)
+        } else {
+            String::from("-")
+        };
+        let checkbox = match item.checked {
+            Some(true) => " [x]",
+            Some(false) => " [ ]",
+            None => "",
+        };
+        output.push(format!(
+            "{}{}{} {}",
+            "\t".repeat(depth),
+            marker,
+            checkbox,
+            render_markdown_inlines(&item.content)
+        ));
+        for child in item.children.iter() {
+            render_markdown_list(child, depth + 1, output);
+        }
+    }
+}
+
+fn escape_wiki(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    for character in text.chars() {
+        if matches!(character, '[' | ']' | '{' | '}' | '\\') {
+            output.push('\\');
+        }
+        output.push(character);
+    }
+    output
+}
+
+fn render_wiki_inlines(content: &[Inline], links: &LinkResolver) -> String {
+    content
+        .iter()
+        .map(|inline| match inline {
+            Inline::Text(text) => escape_wiki(text),
+            Inline::Code(code) => format!("{{{{{}}}}}", code),
+            Inline::Link { text, url } => format!("[{}|{}]", text, url),
+            Inline::WikiLink(link) => match links.resolve(link) {
+                Some(url) => format!("[{}|{}]", link.get_display_text(), url),
+                None => escape_wiki(&link.to_text()),
+            },
+            Inline::Break => String::from("\n"),
+        })
+        .collect()
+}
+
+fn render_wiki_list(list: &List, markers: &str, links: &LinkResolver, output: &mut Vec<String>) {
+    let markers = format!("{}{}", markers, if list.ordered { '#' } else { '*' });
+    for item in list.items.iter() {
+        let checkbox = match item.checked {
+            Some(true) => "(/) ",
+            Some(false) => "(x) ",
+            None => "",
+        };
+        output.push(format!(
+            "{} {}{}",
+            markers,
+            checkbox,
+            render_wiki_inlines(&item.content, links)
+        ));
+        for child in item.children.iter() {
+            render_wiki_list(child, &markers, links, output);
+        }
+    }
+}
+
+fn render_adf_inlines(content: &[Inline], links: &LinkResolver) -> Vec<Value> {
+    let link = |text: &str, url: &str| {
+        json!({
+            "type": "text",
+            "text": text,
+            "marks": [{ "type": "link", "attrs": { "href": url } }],
+        })
+    };
+
+    content
+        .iter()
+        .filter_map(|inline| match inline {
+            // ADF does not allow empty text nodes
+            Inline::Text(text) if text.is_empty() => None,
+            Inline::Text(text) => Some(json!({ "type": "text", "text": text })),
+            Inline::Code(code) => Some(json!({
+                "type": "text",
+                "text": code,
+                "marks": [{ "type": "code" }],
+            })),
+            Inline::Link { text, url } => Some(link(text, url)),
+            Inline::WikiLink(wikilink) => Some(match links.resolve(wikilink) {
+                Some(url) => link(&wikilink.get_display_text(), &url),
+                None => json!({ "type": "text", "text": wikilink.to_text() }),
+            }),
+            Inline::Break => Some(json!({ "type": "hardBreak" })),
+        })
+        .collect()
+}
+
+fn render_adf_list(list: &List, links: &LinkResolver, task_ids: &mut u32) -> Value {
+    let is_task_list = list.items.iter().any(|x| x.checked.is_some());
+
+    if is_task_list {
+        let mut content = Vec::new();
+        for item in list.items.iter() {
+            *task_ids += 1;
+            content.push(json!({
+                "type": "taskItem",
+                "attrs": {
+                    "localId": task_ids.to_string(),
+                    "state": if item.checked == Some(true) { "DONE" } else { "TODO" },
+                },
+                "content": render_adf_inlines(&item.content, links),
+            }));
+            for child in item.children.iter() {
+                content.push(render_adf_list(child, links, task_ids));
+            }
+        }
+        *task_ids += 1;
+        return json!({
+            "type": "taskList",
+            "attrs": { "localId": task_ids.to_string() },
+            "content": content,
+        });
+    }
+
+    let content: Vec<Value> = list
+        .items
+        .iter()
+        .map(|item| {
+            let mut item_content = vec![json!({
+                "type": "paragraph",
+                "content": render_adf_inlines(&item.content, links),
+            })];
+            for child in item.children.iter() {
+                item_content.push(render_adf_list(child, links, task_ids));
+            }
+            json!({ "type": "listItem", "content": item_content })
+        })
+        .collect();
+
+    json!({
+        "type": if list.ordered { "orderedList" } else { "bulletList" },
+        "content": content,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const NOTE: &str = "# Public API redesign
+
+Some context about the task,
+spread over two lines with `inline code`.
+
+## Steps
+
+- [x] Write the [RFC](https://example.com/rfc)
+- [ ] Talk with [[Backend team|the backend]]
+\t- [ ] Check [[Meetings#Monday]]
+- [ ] Ship it
+
+1. First
+2. Second
+\t- Nested bullet
+
+```rust
+fn main() {
+    println!(\"[[not a link]]\");
+}
+```";
+
+    #[test]
+    fn markdown_round_trip() {
+        let document = Document::from_markdown(NOTE);
+        assert_eq!(document.to_markdown(), NOTE);
+    }
+
+    #[test]
+    fn wiki_round_trip() {
+        let document = Document::from_markdown(NOTE);
+        let wiki = document.to_wiki(&LinkResolver::unresolved());
+        assert_eq!(Document::from_wiki(&wiki), document);
+        assert_eq!(wiki_to_markdown(&wiki), NOTE);
+    }
+
+    #[test]
+    fn adf_round_trip() {
+        let document = Document::from_markdown(NOTE);
+        let adf = document.to_adf(&LinkResolver::unresolved());
+        assert_eq!(Document::from_adf(&adf), document);
+        assert_eq!(adf_to_markdown(&adf), NOTE);
+    }
+
+    #[test]
+    fn wikilinks_to_jira_issues() {
+        let links = LinkResolver::new("mycompany", |link| {
+            (link.get_note_name() == "Public API redesign").then(|| JiraKey::new("MB-12"))
+        });
+        let markdown = "See [[Public API redesign|the API task]] and [[Other note]]";
+
+        assert_eq!(
+            markdown_to_wiki(markdown, &links),
+            "See [the API task|https://mycompany.atlassian.net/browse/MB-12] and \\[\\[Other note\\]\\]"
+        );
+
+        let adf = markdown_to_adf(markdown, &links);
+        assert_eq!(
+            adf.pointer("/content/0/content/1/marks/0/attrs/href"),
+            Some(&json!("https://mycompany.atlassian.net/browse/MB-12"))
+        );
+        assert_eq!(
+            adf.pointer("/content/0/content/3/text"),
+            Some(&json!("[[Other note]]"))
+        );
+
+        // Parsed like the notes, folders and headings included
+        assert_eq!(
+            markdown_to_wiki("[[Projects/Public API redesign#Endpoints]] ![[Diagram.png]]", &links),
+            "[Public API redesign > Endpoints|https://mycompany.atlassian.net/browse/MB-12] !\\[\\[Diagram.png\\]\\]"
+        );
+        assert_eq!(
+            wiki_to_markdown("See \\[\\[Task#^a1b2|the task\\]\\]"),
+            "See [[Task#^a1b2|the task]]"
+        );
+    }
+
+    #[test]
+    fn wiki_list_skipping_levels() {
+        let document = Document::from_wiki("* one\n*** deep\n** two\n* three");
+        let wiki = document.to_wiki(&LinkResolver::unresolved());
+        assert_eq!(wiki, "* one\n** deep\n** two\n* three");
+        assert_eq!(Document::from_wiki(&wiki), document);
+    }
+
+    #[test]
+    fn wiki_from_jira() {
+        let wiki = "h3. Title\n\n* one\n** {{two}}\n* [three|https://example.com]\n\n{noformat}\nraw\n{noformat}";
+        assert_eq!(
+            wiki_to_markdown(wiki),
+            "### Title\n\n- one\n\t- `two`\n- [three](https://example.com)\n\n```\nraw\n```"
+        );
+    }
+}
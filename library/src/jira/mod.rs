@@ -0,0 +1,482 @@
+pub mod cache;
+pub mod client;
+pub mod error;
+pub mod markup;
+#[cfg(feature = "async")]
+pub mod nonblocking;
+pub mod request;
+
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+
+pub use cache::{JiraCache, PendingWrites};
+pub use client::{Connection, JiraClient, RetryPolicy};
+pub use error::JiraError;
+
+use crate::commons::{Date, DateTime, IssueType, Priority, Sprint, Status, TimeEstimate};
+use crate::context::Context;
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct JiraResponse {
+    expand: String,
+    issues: Vec<JiraIssue>,
+    max_results: u32,
+    start_at: u32,
+    total: u32,
+}
+
+impl JiraResponse {
+    pub fn get_issues(&self) -> &[JiraIssue] {
+        &self.issues
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct JiraIssue {
+    expand: String,
+    fields: JiraIssueFields,
+    id: String,
+    key: JiraKey,
+    #[serde(rename = "self")]
+    url: String,
+}
+
+impl JiraIssue {
+    pub fn get_fields(&self) -> &JiraIssueFields {
+        &self.fields
+    }
+    pub fn get_key(&self) -> &JiraKey {
+        &self.key
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct JiraIssueFields {
+    summary: String,
+    #[serde(deserialize_with = "deserialize_description")]
+    description: String,
+    // #[serde(rename = "customfield_10035")]
+    // story_points: Option<f32>,
+    #[serde(rename = "issuetype")]
+    #[serde(deserialize_with = "IssueType::deserialize_from_jira")]
+    issue_type: IssueType,
+    creator: User,
+    reporter: Option<User>,
+    assignee: Option<User>,
+    created: DateTime,
+    updated: DateTime,
+    #[serde(rename = "duedate")]
+    due_date: Option<Date>,
+    #[serde(deserialize_with = "Priority::deserialize_from_jira_field")]
+    priority: Priority,
+    #[serde(flatten)]
+    time_tracking: TimeTrackingJira,
+    #[serde(deserialize_with = "Status::deserialize_from_jira")]
+    status: Status,
+    #[serde(rename = "customfield_10020")]
+    #[serde(deserialize_with = "Sprint::deserialize_sprint_vec_from_jira")]
+    sprints: Vec<Sprint>,
+    #[serde(default)]
+    parent: Option<IssueIdentifier>,
+    #[serde(rename = "subtasks")]
+    children: Vec<IssueIdentifier>,
+}
+
+impl JiraIssueFields {
+    pub fn get_summary(&self) -> &str {
+        &self.summary
+    }
+
+    pub fn get_creation_date(&self) -> &DateTime {
+        &self.created
+    }
+
+    pub fn get_reporter(&self) -> Option<&User> {
+        self.reporter.as_ref()
+    }
+
+    pub fn get_priority(&self) -> &Priority {
+        &self.priority
+    }
+
+    pub fn get_status(&self) -> &Status {
+        &self.status
+    }
+
+    pub fn get_issue_type(&self) -> &IssueType {
+        &self.issue_type
+    }
+
+    pub fn get_due_date(&self) -> Option<&Date> {
+        self.due_date.as_ref()
+    }
+
+    pub fn get_time_tracking(&self) -> &TimeTrackingJira {
+        &self.time_tracking
+    }
+
+    pub fn get_sprints(&self) -> &[Sprint] {
+        &self.sprints
+    }
+
+    pub fn get_parent(&self) -> Option<&IssueIdentifier> {
+        self.parent.as_ref()
+    }
+    pub fn get_children(&self) -> &[IssueIdentifier] {
+        &self.children
+    }
+}
+
+fn deserialize_description<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Deserialize::deserialize(deserializer).or_else(|_| Ok(String::new()))
+}
+
+#[derive(Debug)]
+pub struct IssueIdentifier {
+    key: JiraKey,
+    name: String,
+}
+
+impl IssueIdentifier {
+    pub fn get_key(&self) -> &JiraKey {
+        &self.key
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl<'de> Deserialize<'de> for IssueIdentifier {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        struct Mid {
+            key: String,
+            fields: MidFields,
+        }
+        #[derive(Deserialize)]
+        struct MidFields {
+            summary: String,
+        }
+
+        let mid: Mid = Deserialize::deserialize(deserializer)?;
+
+        Ok(IssueIdentifier {
+            key: JiraKey(mid.key),
+            name: mid.fields.summary,
+        })
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct User {
+    account_id: String,
+    display_name: String,
+    #[serde(rename = "self")]
+    url: String,
+}
+
+impl User {
+    pub fn get_display_name(&self) -> &str {
+        &self.display_name
+    }
+
+    pub fn get_account_id(&self) -> &str {
+        &self.account_id
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Comment {
+    id: String,
+    author: User,
+    #[serde(deserialize_with = "deserialize_description")]
+    body: String,
+    created: DateTime,
+    updated: DateTime,
+}
+
+impl Comment {
+    pub fn get_author(&self) -> &User {
+        &self.author
+    }
+
+    // The body comes in wiki markup
+    pub fn get_body(&self) -> &str {
+        &self.body
+    }
+
+    pub fn get_creation_date(&self) -> &DateTime {
+        &self.created
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct CommentsResponse {
+    comments: Vec<Comment>,
+}
+
+// state is either closed, future
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct TimeTrackingJira {
+    #[serde(rename = "timeestimate")]
+    #[serde(deserialize_with = "TimeEstimate::deserialize_from_secs")]
+    left: Option<TimeEstimate>,
+    #[serde(rename = "timeoriginalestimate")]
+    #[serde(deserialize_with = "TimeEstimate::deserialize_from_secs")]
+    original: Option<TimeEstimate>,
+    #[serde(rename = "timespent")]
+    #[serde(deserialize_with = "TimeEstimate::deserialize_from_secs")]
+    spent: Option<TimeEstimate>,
+}
+
+impl TimeTrackingJira {
+    pub fn get_time_left(&self) -> Option<&TimeEstimate> {
+        self.left.as_ref()
+    }
+    pub fn get_time_original(&self) -> Option<&TimeEstimate> {
+        self.original.as_ref()
+    }
+    pub fn get_time_spent(&self) -> Option<&TimeEstimate> {
+        self.spent.as_ref()
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct JiraKey(String);
+
+impl JiraKey {
+    pub fn new(key: &str) -> Self {
+        Self(key.to_owned())
+    }
+
+    pub fn get_browse_url(&self, jira_url: &str) -> String {
+        format!("https://{}.atlassian.net/browse/{}", jira_url, self.0)
+    }
+}
+
+impl std::fmt::Display for JiraKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BoardSprint {
+    id: u64,
+    name: String,
+    state: String,
+}
+
+impl BoardSprint {
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    // future, active or closed
+    pub fn get_state(&self) -> &str {
+        &self.state
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct BoardSprintsResponse {
+    values: Vec<BoardSprint>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WriteOutcome {
+    Sent,
+    // Jira could not be reached, it will be sent with the next write or replay
+    Queued,
+}
+
+/*
+    Answers from Jira when it can be reached, from the cache otherwise. The
+    cache is saved when the context is dropped, or with `save_cache`, not
+    after every answer. When Jira is reached again after being offline, the
+    writes queued meanwhile are sent.
+*/
+fn read(context: &Context, request: &request::JiraRequest) -> Result<String, JiraError> {
+    match context.get_client().execute(request) {
+        Ok(text) => {
+            let response = serde_json::from_str::<Value>(&text)?;
+            context
+                .get_cache()
+                .lock()
+                .unwrap()
+                .store(request, &response);
+
+            // Whatever was refused stays in the rejected list
+            if context.set_offline(false) {
+                let _ = replay_pending_writes(context);
+            }
+            Ok(text)
+        }
+        Err(error) if error.is_offline() => {
+            context.set_offline(true);
+            let cache = context.get_cache().lock().unwrap();
+            cache.get(request).map(|x| x.to_string()).ok_or(error)
+        }
+        Err(error) => Err(error),
+    }
+}
+
+// The requests are sent without holding the lock of the pending writes
+fn write(context: &Context, request: request::JiraRequest) -> Result<WriteOutcome, JiraError> {
+    // Older writes have to reach Jira first, this one waits behind them otherwise
+    let result = match replay_pending_writes(context) {
+        Ok(_) => context.get_client().execute(&request),
+        Err(error) if error.is_offline() => return queue_write(context, request),
+        Err(error) => return Err(error),
+    };
+
+    match result {
+        Ok(_) => {
+            context.set_offline(false);
+            Ok(WriteOutcome::Sent)
+        }
+        // Not when Jira may have applied it, like a comment posted before a 504
+        Err(error) if error.can_send_later(request.get_method()) => queue_write(context, request),
+        Err(error) => {
+            if error.is_offline() {
+                context.set_offline(true);
+            }
+            Err(error)
+        }
+    }
+}
+
+fn queue_write(
+    context: &Context,
+    request: request::JiraRequest,
+) -> Result<WriteOutcome, JiraError> {
+    context.set_offline(true);
+    let mut pending = context.get_pending_writes().lock().unwrap();
+    pending.push(request);
+    pending.save()?;
+    Ok(WriteOutcome::Queued)
+}
+
+// Sends the writes queued while offline, returns how many were processed.
+// A second caller waits for the replay in progress, so the writes reach Jira
+// in the order they were made.
+pub fn replay_pending_writes(context: &Context) -> Result<usize, JiraError> {
+    let _replaying = context.lock_replay();
+    let pending = context.get_pending_writes();
+    let mut sent = 0;
+    loop {
+        let Some(request) = pending.lock().unwrap().take_next() else {
+            return Ok(sent);
+        };
+        let result = context.get_client().execute(&request);
+
+        let mut pending = pending.lock().unwrap();
+        match result {
+            Ok(_) => (),
+            Err(error) if error.can_send_later(request.get_method()) => {
+                pending.put_back(request);
+                return Err(error);
+            }
+            Err(error) => pending.reject(request, error.to_string()),
+        }
+        pending.save()?;
+        sent += 1;
+    }
+}
+
+// Writes the answers cached since the last save
+pub fn save_cache(context: &Context) -> Result<(), JiraError> {
+    let mut cache = context.get_cache().lock().unwrap();
+    if cache.is_changed() {
+        cache.save()?;
+    }
+    Ok(())
+}
+
+pub fn get_issues(context: &Context, max_results: u32) -> Result<JiraResponse, JiraError> {
+    let request = request::search_issues(context.get_config().get_user_id(), max_results);
+    Ok(serde_json::from_str(&read(context, &request)?)?)
+}
+
+pub fn get_myself(context: &Context) -> Result<User, JiraError> {
+    Ok(serde_json::from_str(&read(context, &request::myself())?)?)
+}
+
+pub fn get_user(context: &Context, account_id: &str) -> Result<User, JiraError> {
+    Ok(serde_json::from_str(&read(
+        context,
+        &request::get_user(account_id),
+    )?)?)
+}
+
+pub fn get_board_sprints(context: &Context, board_id: u32) -> Result<Vec<BoardSprint>, JiraError> {
+    let response: BoardSprintsResponse =
+        serde_json::from_str(&read(context, &request::board_sprints(board_id, 50))?)?;
+    Ok(response.values)
+}
+
+// Links are resolved from the project folder, where the notes sent to Jira are
+fn vault_link_resolver(context: &Context) -> markup::LinkResolver<'_> {
+    let folder = std::path::Path::new(context.get_config().get_project_path());
+    markup::LinkResolver::new(context.get_config().get_jira_url(), move |link| {
+        crate::obsidian::task_file::find_jira_key(context, link, folder)
+    })
+}
+
+// Pushes the body of a note as the description of the issue
+pub fn update_issue_description(
+    context: &Context,
+    key: &JiraKey,
+    markdown: &str,
+) -> Result<WriteOutcome, JiraError> {
+    let wiki = markup::markdown_to_wiki(markdown, &vault_link_resolver(context));
+    write(context, request::update_description(key, &wiki))
+}
+
+pub fn update_issue_fields(
+    context: &Context,
+    key: &JiraKey,
+    fields: Value,
+) -> Result<WriteOutcome, JiraError> {
+    write(context, request::update_fields(key, fields))
+}
+
+pub fn transition_issue(
+    context: &Context,
+    key: &JiraKey,
+    transition_id: &str,
+) -> Result<WriteOutcome, JiraError> {
+    write(context, request::transition_issue(key, transition_id))
+}
+
+pub fn add_worklog(
+    context: &Context,
+    key: &JiraKey,
+    time_spent: &TimeEstimate,
+    started: &DateTime,
+) -> Result<WriteOutcome, JiraError> {
+    write(context, request::add_worklog(key, time_spent, started))
+}
+
+pub fn get_comments(context: &Context, key: &JiraKey) -> Result<Vec<Comment>, JiraError> {
+    let response: CommentsResponse =
+        serde_json::from_str(&read(context, &request::get_comments(key))?)?;
+    Ok(response.comments)
+}
+
+pub fn add_comment(
+    context: &Context,
+    key: &JiraKey,
+    markdown: &str,
+) -> Result<WriteOutcome, JiraError> {
+    let wiki = markup::markdown_to_wiki(markdown, &vault_link_resolver(context));
+    write(context, request::add_comment(key, &wiki))
+}
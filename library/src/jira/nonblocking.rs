@@ -0,0 +1,254 @@
+use std::future::Future;
+
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use super::client::{parse_retry_after, Connection, CONNECT_TIMEOUT, REQUEST_TIMEOUT};
+use super::error::JiraError;
+use super::request::{self, JiraRequest};
+use super::{
+    markup, BoardSprint, BoardSprintsResponse, Comment, CommentsResponse, JiraKey, JiraResponse,
+    User,
+};
+use crate::commons::{DateTime, TimeEstimate};
+use crate::context::Context;
+
+/*
+    Same API as the blocking functions in `jira`, for async code. The requests,
+    models, errors and the `Connection` are shared with the blocking client, only
+    the transport changes. Needs to run inside a tokio runtime.
+
+    Unlike the blocking API, answers are not cached and writes are not queued
+    while offline, so there is nothing to replay.
+*/
+
+#[derive(Debug)]
+pub struct AsyncJiraClient {
+    http: reqwest::Client,
+    connection: Connection,
+}
+
+impl AsyncJiraClient {
+    pub fn new(connection: Connection) -> Self {
+        let http = reqwest::Client::builder()
+            .connect_timeout(CONNECT_TIMEOUT)
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .unwrap();
+        Self { http, connection }
+    }
+
+    pub fn get_connection(&self) -> &Connection {
+        &self.connection
+    }
+
+    // To change the retry policy or the request budget
+    pub fn get_connection_mut(&mut self) -> &mut Connection {
+        &mut self.connection
+    }
+
+    pub async fn send<T: DeserializeOwned>(&self, request: &JiraRequest) -> Result<T, JiraError> {
+        let text = self.execute(request).await?;
+        Ok(serde_json::from_str(&text)?)
+    }
+
+    pub async fn execute(&self, request: &JiraRequest) -> Result<String, JiraError> {
+        let mut attempt = 0;
+        loop {
+            self.connection.start_attempt()?;
+
+            let (error, retry_after) = match self.build(request)?.send().await {
+                Ok(response) if response.status().is_success() => {
+                    return Ok(response.text().await?)
+                }
+                Ok(response) => {
+                    let retry_after = parse_retry_after(response.headers());
+                    let status = response.status().as_u16();
+                    let body = response.text().await.unwrap_or_default();
+                    (JiraError::from_response(status, &body), retry_after)
+                }
+                Err(error) => (JiraError::Network(error), None),
+            };
+
+            let delay = self
+                .connection
+                .get_retry_delay(error, request, attempt, retry_after)?;
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    fn build(&self, request: &JiraRequest) -> Result<reqwest::RequestBuilder, JiraError> {
+        let (user, password) = self.connection.get_credentials()?;
+        let builder = self
+            .http
+            .request(
+                request.get_method().clone(),
+                self.connection.get_url(request),
+            )
+            .basic_auth(user, Some(password))
+            .query(request.get_query());
+
+        Ok(match request.get_body() {
+            Some(body) => builder.json(body),
+            None => builder,
+        })
+    }
+}
+
+pub async fn get_issues(context: &Context, max_results: u32) -> Result<JiraResponse, JiraError> {
+    let request = request::search_issues(context.get_config().get_user_id(), max_results);
+    context.get_async_client().send(&request).await
+}
+
+pub async fn get_myself(context: &Context) -> Result<User, JiraError> {
+    context.get_async_client().send(&request::myself()).await
+}
+
+pub async fn get_user(context: &Context, account_id: &str) -> Result<User, JiraError> {
+    context
+        .get_async_client()
+        .send(&request::get_user(account_id))
+        .await
+}
+
+pub async fn get_board_sprints(
+    context: &Context,
+    board_id: u32,
+) -> Result<Vec<BoardSprint>, JiraError> {
+    let response: BoardSprintsResponse = context
+        .get_async_client()
+        .send(&request::board_sprints(board_id, 50))
+        .await?;
+    Ok(response.values)
+}
+
+// The links are resolved when called, not in the future: that may walk the
+// vault, which blocks. The future only talks to Jira.
+pub fn update_issue_description<'a>(
+    context: &'a Context,
+    key: &JiraKey,
+    markdown: &str,
+) -> impl Future<Output = Result<(), JiraError>> + 'a {
+    let wiki = markup::markdown_to_wiki(markdown, &super::vault_link_resolver(context));
+    let request = request::update_description(key, &wiki);
+    async move {
+        context.get_async_client().execute(&request).await?;
+        Ok(())
+    }
+}
+
+pub async fn update_issue_fields(
+    context: &Context,
+    key: &JiraKey,
+    fields: Value,
+) -> Result<(), JiraError> {
+    context
+        .get_async_client()
+        .execute(&request::update_fields(key, fields))
+        .await?;
+    Ok(())
+}
+
+pub async fn transition_issue(
+    context: &Context,
+    key: &JiraKey,
+    transition_id: &str,
+) -> Result<(), JiraError> {
+    context
+        .get_async_client()
+        .execute(&request::transition_issue(key, transition_id))
+        .await?;
+    Ok(())
+}
+
+pub async fn add_worklog(
+    context: &Context,
+    key: &JiraKey,
+    time_spent: &TimeEstimate,
+    started: &DateTime,
+) -> Result<(), JiraError> {
+    context
+        .get_async_client()
+        .execute(&request::add_worklog(key, time_spent, started))
+        .await?;
+    Ok(())
+}
+
+pub async fn get_comments(context: &Context, key: &JiraKey) -> Result<Vec<Comment>, JiraError> {
+    let response: CommentsResponse = context
+        .get_async_client()
+        .send(&request::get_comments(key))
+        .await?;
+    Ok(response.comments)
+}
+
+// Like `update_issue_description`, the links are resolved when called
+pub fn add_comment<'a>(
+    context: &'a Context,
+    key: &JiraKey,
+    markdown: &str,
+) -> impl Future<Output = Result<(), JiraError>> + 'a {
+    let wiki = markup::markdown_to_wiki(markdown, &super::vault_link_resolver(context));
+    let request = request::add_comment(key, &wiki);
+    async move {
+        context.get_async_client().execute(&request).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::jira::RetryPolicy;
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    async fn serve(responses: Vec<(&'static str, &'static str)>) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            for (status, body) in responses {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buffer = [0; 1024];
+                while !request.windows(4).any(|x| x == b"\r\n\r\n") {
+                    let read = stream.read(&mut buffer).await.unwrap();
+                    if read == 0 {
+                        break;
+                    }
+                    request.extend_from_slice(&buffer[..read]);
+                }
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        format!("http://{}", address)
+    }
+
+    #[tokio::test]
+    async fn retries_transient_errors() {
+        let base_url = serve(vec![
+            ("429 Too Many Requests\r\nRetry-After: 0", ""),
+            ("200 OK", r#"{"total":3}"#),
+        ])
+        .await;
+        let mut client =
+            AsyncJiraClient::new(Connection::new(&base_url, "me@example.com", "token"));
+        client.get_connection_mut().set_retry_policy(RetryPolicy {
+            max_retries: 2,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(10),
+        });
+
+        let request = JiraRequest::new(reqwest::Method::GET, String::from("/rest/api/2/search"));
+        let answer: serde_json::Value = client.send(&request).await.unwrap();
+        assert_eq!(answer["total"], 3);
+        assert_eq!(client.get_connection().get_remaining_budget(), 998);
+    }
+}
@@ -0,0 +1,140 @@
+use reqwest::Method;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{json, Value};
+
+use super::JiraKey;
+use crate::commons::{DateTime, TimeEstimate};
+
+// A call to the Jira API, independent of the client that ends up sending it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JiraRequest {
+    #[serde(serialize_with = "serialize_method")]
+    #[serde(deserialize_with = "deserialize_method")]
+    method: Method,
+    path: String,
+    query: Vec<(String, String)>,
+    body: Option<Value>,
+}
+
+fn serialize_method<S: Serializer>(method: &Method, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(method.as_str())
+}
+
+fn deserialize_method<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Method, D::Error> {
+    let method: String = Deserialize::deserialize(deserializer)?;
+    Method::from_bytes(method.as_bytes()).map_err(serde::de::Error::custom)
+}
+
+impl JiraRequest {
+    pub fn new(method: Method, path: String) -> Self {
+        Self {
+            method,
+            path,
+            query: Vec::new(),
+            body: None,
+        }
+    }
+
+    pub fn with_query(mut self, key: &str, value: String) -> Self {
+        self.query.push((key.to_owned(), value));
+        self
+    }
+
+    pub fn with_body(mut self, body: Value) -> Self {
+        self.body = Some(body);
+        self
+    }
+
+    pub fn get_method(&self) -> &Method {
+        &self.method
+    }
+
+    pub fn get_path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn get_url(&self, base_url: &str) -> String {
+        format!("{}{}", base_url, self.path)
+    }
+
+    pub fn get_query(&self) -> &[(String, String)] {
+        &self.query
+    }
+
+    // Identifies the request, to store its answer
+    pub fn get_key(&self) -> String {
+        let query: Vec<String> = self
+            .query
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect();
+        format!("{} {}?{}", self.method, self.path, query.join("&"))
+    }
+
+    pub fn is_read(&self) -> bool {
+        self.method == Method::GET
+    }
+
+    pub fn get_body(&self) -> Option<&Value> {
+        self.body.as_ref()
+    }
+}
+
+pub fn search_issues(user_id: &str, max_results: u32) -> JiraRequest {
+    JiraRequest::new(Method::GET, String::from("/rest/api/2/search"))
+        .with_query("maxResults", max_results.to_string())
+        .with_query("jql", format!("assignee={}", user_id))
+}
+
+pub fn board_sprints(board_id: u32, max_results: u32) -> JiraRequest {
+    JiraRequest::new(
+        Method::GET,
+        format!("/rest/agile/1.0/board/{}/sprint", board_id),
+    )
+    .with_query("maxResults", max_results.to_string())
+}
+
+pub fn update_description(key: &JiraKey, wiki: &str) -> JiraRequest {
+    JiraRequest::new(Method::PUT, format!("/rest/api/2/issue/{}", key))
+        .with_body(json!({ "fields": { "description": wiki } }))
+}
+
+pub fn get_comments(key: &JiraKey) -> JiraRequest {
+    JiraRequest::new(Method::GET, format!("/rest/api/2/issue/{}/comment", key))
+        .with_query("maxResults", 5000.to_string())
+        .with_query("orderBy", String::from("created"))
+}
+
+pub fn add_comment(key: &JiraKey, wiki: &str) -> JiraRequest {
+    JiraRequest::new(Method::POST, format!("/rest/api/2/issue/{}/comment", key))
+        .with_body(json!({ "body": wiki }))
+}
+
+pub fn myself() -> JiraRequest {
+    JiraRequest::new(Method::GET, String::from("/rest/api/2/myself"))
+}
+
+pub fn get_user(account_id: &str) -> JiraRequest {
+    JiraRequest::new(Method::GET, String::from("/rest/api/2/user"))
+        .with_query("accountId", account_id.to_owned())
+}
+
+pub fn update_fields(key: &JiraKey, fields: Value) -> JiraRequest {
+    JiraRequest::new(Method::PUT, format!("/rest/api/2/issue/{}", key))
+        .with_body(json!({ "fields": fields }))
+}
+
+pub fn transition_issue(key: &JiraKey, transition_id: &str) -> JiraRequest {
+    JiraRequest::new(
+        Method::POST,
+        format!("/rest/api/2/issue/{}/transitions", key),
+    )
+    .with_body(json!({ "transition": { "id": transition_id } }))
+}
+
+pub fn add_worklog(key: &JiraKey, time_spent: &TimeEstimate, started: &DateTime) -> JiraRequest {
+    JiraRequest::new(Method::POST, format!("/rest/api/2/issue/{}/worklog", key)).with_body(json!({
+        "timeSpentSeconds": time_spent.to_secs(),
+        "started": started.to_jira_string(),
+    }))
+}
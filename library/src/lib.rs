@@ -2,42 +2,20 @@
 
 pub mod commons;
 pub mod config;
+pub mod context;
+pub mod credentials;
 pub mod jira;
 pub mod obsidian;
+pub mod setup;
+#[cfg(test)]
+mod test_vault;
 
 use std::io::Write;
 
 fn test_sprint(max_results: u32) -> () {
-    let url = format!(
-        "https://{}.atlassian.net/rest/agile/1.0/board/5/sprint",
-        crate::config::CONFIG.get_jira_url()
-    );
-
-    let client = reqwest::blocking::Client::new();
-
-    let query = [
-        ("maxResults", max_results.to_string()),
-        /*
-        (
-            "jql",
-            format!(
-                "assignee={}",
-                crate::config::CONFIG.get().unwrap().get_user_id(),
-            ),
-        ),
-        */
-    ];
-
-    let auth_mail = crate::config::CONFIG.get_user_mail();
-    let auth_token = crate::config::CONFIG.get_jira_token();
-
-    let output = client
-        .get(url)
-        .basic_auth(auth_mail, Some(auth_token))
-        .query(&query)
-        .send()
-        .unwrap()
-        .json::<serde_json::Value>()
+    let output: serde_json::Value = context::Context::global()
+        .get_client()
+        .send(&jira::request::board_sprints(5, max_results))
         .unwrap();
 
     println!("{:#?}", output);
@@ -45,36 +23,13 @@ fn test_sprint(max_results: u32) -> () {
 
 fn get_raw() {
     let max_results = 200;
-    let url = format!(
-        "https://{}.atlassian.net/rest/api/2/search",
-        crate::config::CONFIG.get_jira_url()
-    );
-
-    let client = reqwest::blocking::Client::new();
-
-    let query = [
-        ("maxResults", max_results.to_string()),
-        /*
-        (
-            "jql",
-            format!(
-                "assignee={}",
-                crate::config::CONFIG.get().unwrap().get_user_id(),
-            ),
-        ),
-        */
-    ];
-
-    let auth_mail = crate::config::CONFIG.get_user_mail();
-    let auth_token = crate::config::CONFIG.get_jira_token();
+    let request =
+        jira::request::JiraRequest::new(reqwest::Method::GET, String::from("/rest/api/2/search"))
+            .with_query("maxResults", max_results.to_string());
 
-    let output = client
-        .get(url)
-        .basic_auth(auth_mail, Some(auth_token))
-        .query(&query)
-        .send()
-        .unwrap()
-        .json::<serde_json::Value>()
+    let output: serde_json::Value = context::Context::global()
+        .get_client()
+        .send(&request)
         .unwrap();
 
     let mut file = std::fs::File::create("docs/example_issue_response_v2.json").unwrap();
@@ -82,7 +37,7 @@ fn get_raw() {
 }
 
 fn test_jira() {
-    let response = jira::get_issues(30);
+    let response = jira::get_issues(context::Context::global(), 30).unwrap();
     // let all_issues = response.get("issues").unwrap().as_array().unwrap();
     println!("{:#?}", response);
 
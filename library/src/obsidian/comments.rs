@@ -0,0 +1,218 @@
+use crate::jira::{markup, Comment};
+
+/*
+    The comments of the issue live in their own section of the task note, which
+    is rewritten on every sync. Everything outside of it belongs to the user:
+
+    ## Jira comments
+
+    > [!quote] Jane Doe, 2024-03-01 10:15
+    > The body of the comment, converted to Markdown
+
+    ### New comment
+    Anything written under this heading is posted on the next sync, up to
+    the next heading of level 3 or higher, and removed once it is posted
+
+    The section ends at the next heading of level 1 or 2.
+*/
+const SECTION_HEADING: &str = "## Jira comments";
+const NEW_COMMENT_HEADING: &str = "### New comment";
+
+fn heading_level(line: &str) -> Option<usize> {
+    let level = line.chars().take_while(|x| *x == '#').count();
+    (level > 0 && line[level..].starts_with(' ')).then_some(level)
+}
+
+// Lines of the note with their byte offset, skipping the ones inside code fences
+fn lines_outside_code(content: &str) -> impl Iterator<Item = (usize, &str)> {
+    let mut in_code = false;
+    let mut offset = 0;
+    content.split_inclusive('\n').filter_map(move |line| {
+        let line_offset = offset;
+        offset += line.len();
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+            return None;
+        }
+        (!in_code).then_some((line_offset, line.trim_end()))
+    })
+}
+
+fn find_section(content: &str) -> Option<std::ops::Range<usize>> {
+    let mut start = None;
+    for (offset, line) in lines_outside_code(content) {
+        match start {
+            None if line == SECTION_HEADING => start = Some(offset),
+            Some(start) if heading_level(line).is_some_and(|x| x <= 2) => {
+                return Some(start..offset)
+            }
+            _ => {}
+        }
+    }
+    start.map(|start| start..content.len())
+}
+
+pub fn take_new_comments(content: &str) -> Vec<String> {
+    find_new_comments(content)
+        .into_iter()
+        .map(|(_, text)| text)
+        .collect()
+}
+
+// Once it is posted or queued, with its heading, so it is not posted again
+pub fn remove_new_comment(content: &str, comment: &str) -> String {
+    match find_new_comments(content)
+        .into_iter()
+        .find(|(_, text)| text == comment)
+    {
+        Some((range, _)) => format!("{}{}", &content[..range.start], &content[range.end..]),
+        None => content.to_owned(),
+    }
+}
+
+// The new comments with where they are written, from their heading. A comment
+// goes on over the headings of a lower level, like `#### Details`.
+fn find_new_comments(content: &str) -> Vec<(std::ops::Range<usize>, String)> {
+    let Some(section) = find_section(content) else {
+        return Vec::new();
+    };
+    let level = heading_level(NEW_COMMENT_HEADING).unwrap();
+
+    let mut comments = Vec::new();
+    let mut current: Option<usize> = None;
+    let headings = lines_outside_code(&content[section.clone()])
+        .map(|(offset, line)| (section.start + offset, line))
+        .filter(|(_, line)| heading_level(line).is_some_and(|x| x <= level))
+        .chain(std::iter::once((section.end, "")));
+
+    for (offset, line) in headings {
+        if let Some(start) = current.take() {
+            let text_start = content[start..offset]
+                .find('\n')
+                .map_or(offset, |x| start + x + 1);
+            let text = content[text_start..offset].trim();
+            if !text.is_empty() {
+                comments.push((start..offset, text.to_owned()));
+            }
+        }
+        if line == NEW_COMMENT_HEADING {
+            current = Some(offset);
+        }
+    }
+
+    comments
+}
+
+pub fn render_section(comments: &[Comment]) -> String {
+    let mut output = format!("{}\n\n", SECTION_HEADING);
+    for comment in comments {
+        output.push_str(&format!(
+            "> [!quote] {}, {}\n",
+            comment.get_author().get_display_name(),
+            comment.get_creation_date()
+        ));
+        for line in markup::wiki_to_markdown(comment.get_body()).lines() {
+            output.push_str(format!("> {}", line).trim_end());
+            output.push('\n');
+        }
+        output.push('\n');
+    }
+    output.push_str(NEW_COMMENT_HEADING);
+    output.push_str("\n\n");
+    output
+}
+
+pub fn replace_section(content: &str, section: &str) -> String {
+    match find_section(content) {
+        Some(range) => format!(
+            "{}{}{}",
+            &content[..range.start],
+            section,
+            &content[range.end..]
+        ),
+        None if content.trim().is_empty() => section.to_owned(),
+        None => format!("{}\n\n{}", content.trim_end(), section),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const NOTE: &str = "Some notes of mine
+
+## Jira comments
+
+> [!quote] Old, 2024-01-01 00:00
+> Outdated
+
+### New comment
+First line
+
+```
+### Not a heading
+```
+
+### New comment
+
+## Other section
+Untouched
+";
+
+    fn comment(author: &str, body: &str) -> Comment {
+        let json = serde_json::json!({
+            "id": "10001",
+            "author": { "accountId": "1", "displayName": author, "self": "" },
+            "body": body,
+            "created": "2024-03-01T10:15:00.000+0100",
+            "updated": "2024-03-01T10:15:00.000+0100",
+        });
+        serde_json::from_str(&json.to_string()).unwrap()
+    }
+
+    #[test]
+    fn new_comments() {
+        assert_eq!(
+            take_new_comments(NOTE),
+            vec![String::from("First line\n\n```\n### Not a heading\n```")]
+        );
+        assert!(take_new_comments("### New comment\nOutside the section").is_empty());
+    }
+
+    #[test]
+    fn new_comment_with_sub_headings() {
+        let note = "## Jira comments\n\n### New comment\nIntro\n\n#### Details\nMore\n\n### New comment\nSecond\n\n## Mine\nKept\n";
+        assert_eq!(
+            take_new_comments(note),
+            ["Intro\n\n#### Details\nMore", "Second"]
+        );
+        assert_eq!(
+            remove_new_comment(note, "Intro\n\n#### Details\nMore"),
+            "## Jira comments\n\n### New comment\nSecond\n\n## Mine\nKept\n"
+        );
+    }
+
+    #[test]
+    fn replace_comments() {
+        let section = render_section(&[comment("Jane Doe", "h1. Hi\n\n* a list")]);
+        let expected = "Some notes of mine
+
+## Jira comments
+
+> [!quote] Jane Doe, 2024-03-01 10:15
+> # Hi
+>
+> - a list
+
+### New comment
+
+## Other section
+Untouched
+";
+        assert_eq!(replace_section(NOTE, &section), expected);
+        assert_eq!(
+            replace_section("Just text\n", &section),
+            format!("Just text\n\n{}", section)
+        );
+    }
+}
@@ -0,0 +1,131 @@
+use time::{Date, Duration, Month};
+
+/*
+    Dates formatted like Obsidian does, with Moment.js patterns such as
+    `YYYY/MM/YYYY-MM-DD ddd`. Text between brackets is copied as is, and
+    anything that is not a token too. Names are in English.
+
+    ~ YYYY YY         year
+    ~ Q               quarter
+    ~ MMMM MMM MM M   month: March, Mar, 03, 3
+    ~ DDDD DDD        day of the year: 064, 64
+    ~ DD Do D         day of the month: 05, 5th, 5
+    ~ dddd ddd dd d   day of the week: Tuesday, Tue, Tu, 2 (Sunday is 0)
+    ~ E e             day of the week, ISO (Monday is 1) and from Sunday (0)
+    ~ WW W GGGG GG    ISO week and its year
+    ~ ww w gggg gg    week starting on Sunday, the first one contains January 1st, and its year
+*/
+pub const DEFAULT_FORMAT: &str = "YYYY-MM-DD";
+
+const TOKENS: [&str; 26] = [
+    "YYYY", "YY", "Q", "MMMM", "MMM", "MM", "M", "DDDD", "DDD", "DD", "Do", "D", "dddd", "ddd",
+    "dd", "d", "E", "e", "WW", "W", "GGGG", "GG", "ww", "w", "gggg", "gg",
+];
+
+pub fn format_date(date: &Date, pattern: &str) -> String {
+    let mut output = String::new();
+    let mut rest = pattern;
+
+    while let Some(character) = rest.chars().next() {
+        if character == '[' {
+            let end = rest.find(']').unwrap_or(rest.len());
+            output.push_str(&rest[1..end]);
+            rest = rest.get(end + 1..).unwrap_or_default();
+            continue;
+        }
+        match TOKENS.iter().find(|x| rest.starts_with(*x)) {
+            Some(token) => {
+                output.push_str(&format_token(date, token));
+                rest = &rest[token.len()..];
+            }
+            None => {
+                output.push(character);
+                rest = &rest[character.len_utf8()..];
+            }
+        }
+    }
+    output
+}
+
+fn format_token(date: &Date, token: &str) -> String {
+    let (sunday_year, sunday_week) = sunday_week(date);
+    match token {
+        "YYYY" => format!("{:04}", date.year()),
+        "YY" => format!("{:02}", date.year().rem_euclid(100)),
+        "Q" => ((date.month() as u8 - 1) / 3 + 1).to_string(),
+        "MMMM" => date.month().to_string(),
+        "MMM" => date.month().to_string()[..3].to_owned(),
+        "MM" => format!("{:02}", date.month() as u8),
+        "M" => (date.month() as u8).to_string(),
+        "DDDD" => format!("{:03}", date.ordinal()),
+        "DDD" => date.ordinal().to_string(),
+        "DD" => format!("{:02}", date.day()),
+        "Do" => ordinal(date.day()),
+        "D" => date.day().to_string(),
+        "dddd" => date.weekday().to_string(),
+        "ddd" => date.weekday().to_string()[..3].to_owned(),
+        "dd" => date.weekday().to_string()[..2].to_owned(),
+        "d" | "e" => date.weekday().number_days_from_sunday().to_string(),
+        "E" => date.weekday().number_from_monday().to_string(),
+        "WW" => format!("{:02}", date.iso_week()),
+        "W" => date.iso_week().to_string(),
+        "GGGG" => format!("{:04}", date.to_iso_week_date().0),
+        "GG" => format!("{:02}", date.to_iso_week_date().0.rem_euclid(100)),
+        "ww" => format!("{:02}", sunday_week),
+        "w" => sunday_week.to_string(),
+        "gggg" => format!("{:04}", sunday_year),
+        "gg" => format!("{:02}", sunday_year.rem_euclid(100)),
+        _ => token.to_owned(),
+    }
+}
+
+fn ordinal(day: u8) -> String {
+    let suffix = match (day % 10, day % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{}{}", day, suffix)
+}
+
+// Weeks start on Sunday and belong to the year of their Saturday
+fn sunday_week(date: &Date) -> (i32, u8) {
+    let start = *date - Duration::days(date.weekday().number_days_from_sunday() as i64);
+    let year = (start + Duration::days(6)).year();
+    let january_first = Date::from_calendar_date(year, Month::January, 1).unwrap();
+    let first_start =
+        january_first - Duration::days(january_first.weekday().number_days_from_sunday() as i64);
+    (year, ((start - first_start).whole_weeks() + 1) as u8)
+}
+
+#[cfg(test)]
+mod test {
+    use super::format_date;
+    use time::macros::date;
+
+    #[test]
+    fn formats_like_moment() {
+        let day = date!(2024 - 03 - 05);
+        assert_eq!(format_date(&day, super::DEFAULT_FORMAT), "2024-03-05");
+        assert_eq!(
+            format_date(&day, "YYYY/MM/YYYY-MM-DD ddd"),
+            "2024/03/2024-03-05 Tue"
+        );
+        assert_eq!(
+            format_date(&day, "dddd, MMMM Do YYYY [week] W"),
+            "Tuesday, March 5th 2024 week 10"
+        );
+        assert_eq!(format_date(&day, "DDDD Q E d YY"), "065 1 2 2 24");
+    }
+
+    #[test]
+    fn weeks_around_new_year() {
+        // A Sunday: still the last ISO week of 2023, already the first US week of 2024
+        let day = date!(2023 - 12 - 31);
+        assert_eq!(format_date(&day, "GGGG-[W]WW"), "2023-W52");
+        assert_eq!(format_date(&day, "gggg-[w]ww"), "2024-w01");
+        assert_eq!(format_date(&date!(2021 - 01 - 03), "GGGG-WW"), "2020-53");
+    }
+}
@@ -0,0 +1,99 @@
+use regex::Regex;
+
+/*
+    Glob patterns to pick notes, matched against paths relative to the vault
+    with `/` between folders:
+    ~ *        anything but a `/`
+    ~ **       any number of folders, like `Projects/**/*.md`
+    ~ ?        one character but a `/`
+    ~ {a,b}    either alternative
+    A pattern without a `/` is matched against the name only, so `Archive`
+    matches every file or folder named Archive. A trailing `/` is ignored.
+*/
+#[derive(Debug, Clone)]
+pub struct Glob {
+    pattern: String,
+    regex: Regex,
+    whole_path: bool,
+}
+
+impl Glob {
+    pub fn new(pattern: &str) -> Result<Self, regex::Error> {
+        let trimmed = pattern.trim().trim_matches('/');
+        let chars: Vec<char> = trimmed.chars().collect();
+        let mut regex = String::from("^");
+        let mut in_braces = false;
+        let mut i = 0;
+
+        while i < chars.len() {
+            match chars[i] {
+                '*' if chars.get(i + 1) == Some(&'*') => {
+                    if chars.get(i + 2) == Some(&'/') {
+                        regex.push_str("(?:.*/)?");
+                        i += 1;
+                    } else {
+                        regex.push_str(".*");
+                    }
+                    i += 1;
+                }
+                '*' => regex.push_str("[^/]*"),
+                '?' => regex.push_str("[^/]"),
+                '{' => {
+                    regex.push_str("(?:");
+                    in_braces = true;
+                }
+                ',' if in_braces => regex.push('|'),
+                '}' if in_braces => {
+                    regex.push(')');
+                    in_braces = false;
+                }
+                character => regex.push_str(&regex::escape(&character.to_string())),
+            }
+            i += 1;
+        }
+        regex.push('$');
+
+        Ok(Self {
+            pattern: pattern.to_owned(),
+            regex: Regex::new(&regex)?,
+            whole_path: trimmed.contains('/'),
+        })
+    }
+
+    pub fn get_pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    pub fn is_match(&self, relative_path: &str) -> bool {
+        if self.whole_path {
+            self.regex.is_match(relative_path)
+        } else {
+            let name = relative_path.rsplit('/').next().unwrap_or_default();
+            self.regex.is_match(name)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Glob;
+
+    #[test]
+    fn matches_paths() {
+        let glob = Glob::new("Projects/**/*.md").unwrap();
+        assert!(glob.is_match("Projects/Task.md"));
+        assert!(glob.is_match("Projects/Area/Sub/Task.md"));
+        assert!(!glob.is_match("Projects/Task.png"));
+        assert!(!glob.is_match("Other/Projects/Task.md"));
+
+        let glob = Glob::new("Archive/").unwrap();
+        assert!(glob.is_match("Projects/Archive"));
+        assert!(!glob.is_match("Projects/Archived"));
+
+        let glob = Glob::new("*.{png,jpg}").unwrap();
+        assert!(glob.is_match("Files/photo.jpg"));
+        assert!(!glob.is_match("photo.jpg.md"));
+
+        assert!(Glob::new("Y2?W1*").unwrap().is_match("Sprints/Y24W10"));
+    }
+}
@@ -0,0 +1,417 @@
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::commons::Date;
+
+use super::planner::{self, DayPlan, PlannerSettings};
+use super::task_file::{TaskFile, TaskProperties};
+
+/*
+    What was parsed from the notes, kept on disk so reloading only parses the
+    notes that changed. Every note is keyed by its path in the vault and
+    remembers the modification time and size of the file and a hash of its
+    text:
+    ~ same time and size      the file is not even read
+    ~ same hash               the file was touched but not changed, nothing is parsed
+    ~ otherwise               the note is parsed again
+
+    A file read less than `SETTLE_TIME` after it was modified can still change
+    without its time changing, on file systems that keep coarse times (FAT,
+    some network drives), like a checkbox ticked in the same second. Until it
+    is read once it is older than that, its hash is checked every time.
+
+    Task notes keep their properties only, so the index stays small, and their
+    text is read from the note when asked for. Daily notes keep their planned
+    blocks. Like the Jira cache, files of another version are ignored, and
+    the index is saved when dropped rather than after every walk.
+*/
+pub const INDEX_VERSION: u32 = 5;
+
+const SETTLE_TIME: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct Stamp {
+    // Since the Unix epoch
+    modified_nanos: u128,
+    size: u64,
+}
+
+impl Stamp {
+    fn of(metadata: &std::fs::Metadata) -> Self {
+        let modified_nanos = metadata
+            .modified()
+            .ok()
+            .and_then(|x| x.duration_since(UNIX_EPOCH).ok())
+            .map(|x| x.as_nanos())
+            .unwrap_or_default();
+        Self {
+            modified_nanos,
+            size: metadata.len(),
+        }
+    }
+
+    // Whether the file can no longer change within the same modification time
+    fn is_settled(&self) -> bool {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|x| x.as_nanos())
+            .unwrap_or_default();
+        now.saturating_sub(self.modified_nanos) > SETTLE_TIME.as_nanos()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Indexed<T> {
+    stamp: Stamp,
+    // Whether the stamp was settled when the file was read, see `SETTLE_TIME`
+    #[serde(default)]
+    settled: bool,
+    hash: u64,
+    value: T,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct IndexData {
+    version: u32,
+    // None for the notes that are not tasks
+    #[serde(default)]
+    tasks: BTreeMap<String, Indexed<Option<TaskProperties>>>,
+    #[serde(default)]
+    plans: BTreeMap<String, Indexed<DayPlan>>,
+    // What the plans were read with
+    #[serde(default)]
+    planner: PlannerSettings,
+}
+
+impl Default for IndexData {
+    fn default() -> Self {
+        Self {
+            version: INDEX_VERSION,
+            tasks: BTreeMap::new(),
+            plans: BTreeMap::new(),
+            planner: PlannerSettings::default(),
+        }
+    }
+}
+
+// Shared between the threads reading the vault, notes are parsed without holding the lock
+#[derive(Debug)]
+pub struct VaultIndex {
+    path: PathBuf,
+    data: Mutex<IndexData>,
+    // Whether there is something new to save
+    changed: AtomicBool,
+}
+
+impl VaultIndex {
+    pub fn load<P: Into<PathBuf>>(path: P) -> Self {
+        let path = path.into();
+        let data = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|text| serde_json::from_str::<IndexData>(&text).ok())
+            .filter(|data| data.version == INDEX_VERSION)
+            .unwrap_or_default();
+        Self {
+            path,
+            data: Mutex::new(data),
+            changed: AtomicBool::new(false),
+        }
+    }
+
+    // Only writes when something changed since it was loaded or saved
+    pub fn save(&self) -> std::io::Result<()> {
+        let data = self.data.lock().unwrap();
+        if !self.changed.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+        crate::jira::cache::write_atomically(&self.path, &*data)?;
+        self.changed.store(false, Ordering::Relaxed);
+        Ok(())
+    }
+
+    // `path` is relative to the vault, None for notes that are missing or not tasks
+    pub fn get_task(&self, vault_path: &Path, path: &Path) -> Option<TaskFile> {
+        self.lookup(
+            |x| &mut x.tasks,
+            vault_path,
+            path,
+            |text| {
+                TaskFile::parse(path, text)
+                    .ok()
+                    .map(TaskFile::into_properties)
+            },
+        )
+        .flatten()
+        .map(|x| TaskFile::from_properties(path, x))
+    }
+
+    // None when there is no daily note. The plans read with other settings are dropped.
+    pub fn get_day_plan(
+        &self,
+        vault_path: &Path,
+        path: &Path,
+        date: &Date,
+        settings: &PlannerSettings,
+    ) -> Option<DayPlan> {
+        {
+            let mut data = self.data.lock().unwrap();
+            if data.planner != *settings {
+                data.plans.clear();
+                data.planner = settings.clone();
+                self.changed.store(true, Ordering::Relaxed);
+            }
+        }
+        self.lookup(
+            |x| &mut x.plans,
+            vault_path,
+            path,
+            |text| planner::parse_day_plan(text, date, settings),
+        )
+    }
+
+    // Forgets the tasks of a folder that were not seen while reading all of it
+    pub fn forget_missing(&self, folder: &Path, seen: &HashSet<PathBuf>) {
+        let folder = key_of(folder);
+        let seen: HashSet<String> = seen.iter().map(|x| key_of(x)).collect();
+        let mut data = self.data.lock().unwrap();
+        let before = data.tasks.len();
+        data.tasks.retain(|key, _| {
+            !(folder.is_empty() || key.starts_with(&format!("{}/", folder))) || seen.contains(key)
+        });
+        if data.tasks.len() != before {
+            self.changed.store(true, Ordering::Relaxed);
+        }
+    }
+
+    fn lookup<T: Clone>(
+        &self,
+        get_map: fn(&mut IndexData) -> &mut BTreeMap<String, Indexed<T>>,
+        vault_path: &Path,
+        path: &Path,
+        parse: impl FnOnce(&str) -> T,
+    ) -> Option<T> {
+        let key = key_of(path);
+        let complete_path = vault_path.join(path);
+        let Ok(metadata) = std::fs::metadata(&complete_path) else {
+            if get_map(&mut self.data.lock().unwrap())
+                .remove(&key)
+                .is_some()
+            {
+                self.changed.store(true, Ordering::Relaxed);
+            }
+            return None;
+        };
+
+        let stamp = Stamp::of(&metadata);
+        if let Some(indexed) = get_map(&mut self.data.lock().unwrap())
+            .get(&key)
+            .filter(|x| x.stamp == stamp && x.settled)
+        {
+            return Some(indexed.value.clone());
+        }
+
+        // Checked before reading, a change made while reading is seen next time
+        let settled = stamp.is_settled();
+        let text = std::fs::read_to_string(&complete_path).ok()?;
+        let hash = hash(&text);
+        if let Some(indexed) = get_map(&mut self.data.lock().unwrap())
+            .get_mut(&key)
+            .filter(|x| x.hash == hash)
+        {
+            if indexed.stamp != stamp || indexed.settled != settled {
+                indexed.stamp = stamp;
+                indexed.settled = settled;
+                self.changed.store(true, Ordering::Relaxed);
+            }
+            return Some(indexed.value.clone());
+        }
+        self.changed.store(true, Ordering::Relaxed);
+
+        let value = parse(&text);
+        get_map(&mut self.data.lock().unwrap()).insert(
+            key,
+            Indexed {
+                stamp,
+                settled,
+                hash,
+                value: value.clone(),
+            },
+        );
+        Some(value)
+    }
+}
+
+impl Drop for VaultIndex {
+    fn drop(&mut self) {
+        // The index only saves time, the notes are right without it
+        let _ = self.save();
+    }
+}
+
+fn key_of(path: &Path) -> String {
+    path.to_string_lossy()
+        .replace('\\', "/")
+        .trim_matches('/')
+        .to_owned()
+}
+
+// FNV-1a, stable between builds unlike the hasher of the standard library
+fn hash(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{PlannerSettings, VaultIndex};
+    use crate::commons::Status;
+    use std::path::Path;
+    use std::sync::atomic::Ordering;
+
+    #[test]
+    fn parses_only_what_changed() {
+        let vault = crate::test_vault::temporary_vault("index");
+        let task_path = Path::new("Projects/Report.md");
+        std::fs::write(
+            vault.join(task_path),
+            "---\npriority: 2\nstatus: To Do\n---\nBody of the report\n",
+        )
+        .unwrap();
+        std::fs::write(vault.join("Projects/Plain.md"), "No properties\n").unwrap();
+
+        let index_path = vault.join(".obsidian-jira/index.json");
+        let index = VaultIndex::load(&index_path);
+        let task = index.get_task(&vault, task_path).unwrap();
+        assert_eq!(task.get_status(), Status::ToDo);
+        assert!(index
+            .get_task(&vault, Path::new("Projects/Plain.md"))
+            .is_none());
+        index.save().unwrap();
+        // The text stays in the note
+        assert!(!std::fs::read_to_string(&index_path)
+            .unwrap()
+            .contains("Body of the report"));
+
+        // Reloaded from the disk, a note whose file is the same is not parsed
+        let index = VaultIndex::load(&index_path);
+        assert!(!index.changed.load(Ordering::Relaxed));
+        index
+            .data
+            .lock()
+            .unwrap()
+            .tasks
+            .get_mut("Projects/Report.md")
+            .unwrap()
+            .value = None;
+        assert!(index.get_task(&vault, task_path).is_none());
+        assert!(!index.changed.load(Ordering::Relaxed));
+
+        // Written again with other text, it is parsed again
+        std::fs::write(
+            vault.join(task_path),
+            "---\npriority: 2\nstatus: Done\n---\nOther text\n",
+        )
+        .unwrap();
+        assert_eq!(
+            index.get_task(&vault, task_path).unwrap().get_status(),
+            Status::Done
+        );
+        assert!(index.changed.load(Ordering::Relaxed));
+
+        std::fs::remove_file(vault.join(task_path)).unwrap();
+        assert!(index.get_task(&vault, task_path).is_none());
+        assert!(!index
+            .data
+            .lock()
+            .unwrap()
+            .tasks
+            .contains_key("Projects/Report.md"));
+
+        let date = crate::commons::Date::from("2024-03-05");
+        std::fs::write(
+            vault.join("2024-03-05.md"),
+            "- [x] 9:00 - 10:30 [[Report]]\n",
+        )
+        .unwrap();
+        let plan = index
+            .get_day_plan(
+                &vault,
+                Path::new("2024-03-05.md"),
+                &date,
+                &PlannerSettings::default(),
+            )
+            .unwrap();
+        assert_eq!(plan.get_tasks().len(), 1);
+        assert!(index
+            .get_day_plan(
+                &vault,
+                Path::new("2024-03-06.md"),
+                &date,
+                &PlannerSettings::default(),
+            )
+            .is_none());
+    }
+
+    #[test]
+    fn saves_when_dropped() {
+        use crate::test_vault::temporary_vault;
+
+        let vault = temporary_vault("index-drop");
+        let task_path = Path::new("Projects/Report.md");
+        std::fs::write(
+            vault.join(task_path),
+            "---\npriority: 2\nstatus: To Do\n---\n",
+        )
+        .unwrap();
+        let index_path = vault.join(".obsidian-jira/index.json");
+
+        let index = VaultIndex::load(&index_path);
+        assert!(index.get_task(&vault, task_path).is_some());
+        assert!(!index_path.exists());
+        drop(index);
+
+        let index = VaultIndex::load(&index_path);
+        assert!(index
+            .data
+            .lock()
+            .unwrap()
+            .tasks
+            .contains_key("Projects/Report.md"));
+    }
+
+    #[test]
+    fn reads_again_what_changed_within_the_same_time() {
+        use crate::test_vault::temporary_vault;
+
+        let vault = temporary_vault("index-settle");
+        let path = Path::new("Daily/2024-03-05.md");
+        let date = crate::commons::Date::from("2024-03-05");
+        let settings = PlannerSettings::default();
+        std::fs::write(vault.join(path), "- [ ] 9:00 - 10:30 [[Report]]\n").unwrap();
+        let modified = std::fs::metadata(vault.join(path))
+            .unwrap()
+            .modified()
+            .unwrap();
+
+        let index = VaultIndex::load(vault.join(".obsidian-jira/index.json"));
+        let plan = index.get_day_plan(&vault, path, &date, &settings).unwrap();
+        assert!(!plan.get_tasks()[0].is_completed());
+
+        // Ticked with the same size, on a file system that keeps the same time
+        std::fs::write(vault.join(path), "- [x] 9:00 - 10:30 [[Report]]\n").unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(vault.join(path))
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+        let plan = index.get_day_plan(&vault, path, &date, &settings).unwrap();
+        assert!(plan.get_tasks()[0].is_completed());
+    }
+}
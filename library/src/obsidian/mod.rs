@@ -1,11 +1,25 @@
+pub mod comments;
+pub mod date_format;
+pub mod glob;
+pub mod index;
+pub mod plan_check;
 pub mod planner;
+pub mod predicate;
+pub mod query;
+pub mod scheduler;
+pub mod settings;
 pub mod task_file;
+pub mod wikilink;
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+
+use crate::context::Context;
 
 #[derive(Debug, Clone)]
 pub struct TaskTimeData {
     pub name: String,
+    // The note of the task, relative to the vault, for the tasks of the sprint
+    pub path: Option<std::path::PathBuf>,
     pub in_sprint: bool,
     pub remaining_time: time::Duration,
     pub uncompleted_time: time::Duration,
@@ -15,62 +29,92 @@ pub struct TaskTimeData {
 #[derive(Debug, Clone)]
 pub struct SprintTimeBalance {
     pub tasks: Vec<TaskTimeData>,
+    // Planned blocks naming no note, alias or task, like meetings or typos
+    pub unmatched: Vec<String>,
+    // Lines of the daily notes that could not be read, what looks wrong in
+    // the plans, like `2024-03-05, line 4 ends before it starts`, and the
+    // names shared by several tasks
+    pub warnings: Vec<String>,
 }
 
 impl SprintTimeBalance {
-    pub fn new(year: i32, iso_week: u8) -> Self {
-        let mut sprint_tasks_filter = task_file::TaskFilter::new();
-
+    pub fn new(context: &Context, year: i32, iso_week: u8) -> Self {
         let sprint = crate::commons::Sprint::from(
-            format!("Y{}W{:0<2}", &year.to_string()[2..], iso_week).as_str(),
+            format!("Y{}W{:0>2}", &year.to_string()[2..], iso_week).as_str(),
         );
-        let first_day = time::Date::from_iso_week_date(2024, iso_week, time::Weekday::Monday)
+        let first_day = time::Date::from_iso_week_date(year, iso_week, time::Weekday::Monday)
             .unwrap()
             .into();
-        let last_day = time::Date::from_iso_week_date(2024, iso_week, time::Weekday::Sunday)
+        let last_day = time::Date::from_iso_week_date(year, iso_week, time::Weekday::Sunday)
             .unwrap()
             .into();
 
-        let sprint_tasks = sprint_tasks_filter
-            .set_sprints(&[sprint])
-            .set_path(crate::config::CONFIG.get_project_path())
-            .get_tasks();
+        // The project is read once, for the tasks of the sprint and for the
+        // names and aliases of every task, which match the blocks that do not
+        // link to one. A name shared by several tasks matches none.
+        let project_tasks = task_file::TaskFilter::new()
+            .set_path(context.get_config().get_project_path())
+            .set_recursive(true)
+            .get_tasks(context);
+        let names = task_file::TaskNames::new(&project_tasks);
+        let sprint_tasks = project_tasks
+            .iter()
+            .filter(|x| x.get_sprints().contains(&sprint));
 
-        let sprint_schedule = planner::TaskSchedule::new(&first_day, &last_day);
+        let sprint_schedule = planner::TaskSchedule::new(context, &first_day, &last_day);
 
-        let mut added_tasks: HashSet<String> = HashSet::new();
+        let mut unmatched = Vec::new();
+        let mut allocations: HashMap<planner::Allocated, planner::TimeAllocation> = HashMap::new();
+        for (key, time_allocation) in sprint_schedule.iter_time_allocations() {
+            let key = match key {
+                planner::Allocated::Text(text) => match names.find(text) {
+                    Some(path) => planner::Allocated::Note(wikilink::note_id(path)),
+                    None => {
+                        unmatched.push(text.clone());
+                        key.clone()
+                    }
+                },
+                planner::Allocated::Note(_) => key.clone(),
+            };
+            *allocations.entry(key).or_default() += time_allocation;
+        }
+        unmatched.sort();
+
+        let mut added_tasks: HashSet<planner::Allocated> = HashSet::new();
 
         let mut task_times = Vec::new();
 
         for task in sprint_tasks {
             let task_name = task.get_name();
             let remaining_time = task.get_remaining_time();
-            let uncompleted_time = sprint_schedule
-                .get_task_time_allocation(&task_name)
+            let key = planner::Allocated::Note(wikilink::note_id(task.get_path()));
+            let allocation = allocations.get(&key);
+            let uncompleted_time = allocation
                 .map(|x| x.get_uncompleted_time())
                 .unwrap_or(time::Duration::ZERO);
 
-            let completed_time = sprint_schedule
-                .get_task_time_allocation(&task_name)
+            let completed_time = allocation
                 .map(|x| x.get_completed_time())
                 .unwrap_or(time::Duration::ZERO);
 
             task_times.push(TaskTimeData {
                 name: task_name.clone(),
+                path: Some(task.get_path().to_owned()),
                 in_sprint: true,
                 remaining_time,
                 uncompleted_time,
                 completed_time,
             });
-            added_tasks.insert(task_name);
+            added_tasks.insert(key);
         }
 
-        for (task_name, time_allocation) in sprint_schedule.iter_time_allocations() {
-            if added_tasks.contains(task_name) {
+        for (key, time_allocation) in allocations.iter() {
+            if added_tasks.contains(key) {
                 continue;
             }
             task_times.push(TaskTimeData {
-                name: task_name.clone(),
+                name: key.get_name().to_owned(),
+                path: None,
                 in_sprint: false,
                 remaining_time: time::Duration::ZERO,
                 uncompleted_time: time_allocation.get_uncompleted_time(),
@@ -89,7 +133,38 @@ impl SprintTimeBalance {
             (false, false) => a.name.partial_cmp(&b.name).unwrap(),
         });
 
-        Self { tasks: task_times }
+        let mut warnings: Vec<(time::Date, String)> = sprint_schedule
+            .get_diagnostics()
+            .iter()
+            .map(|(date, x)| (date.get_date(), x.to_string()))
+            .chain(
+                sprint_schedule
+                    .get_warnings()
+                    .iter()
+                    .map(|(date, x)| (date.get_date(), x.to_string())),
+            )
+            .collect();
+        warnings.sort_by_key(|(date, _)| *date);
+
+        Self {
+            tasks: task_times,
+            unmatched,
+            warnings: warnings
+                .into_iter()
+                .map(|(date, x)| format!("{}, {}", date, x))
+                .chain(names.get_shared().map(|(name, notes)| {
+                    let notes: Vec<String> = notes
+                        .iter()
+                        .map(|x| format!("`{}`", wikilink::note_id(x)))
+                        .collect();
+                    format!(
+                        "`{}` names several tasks, {}, so its blocks count for none",
+                        name,
+                        notes.join(", ")
+                    )
+                }))
+                .collect(),
+        }
     }
 }
 
@@ -235,7 +310,57 @@ mod test {
     use super::*;
     #[test]
     fn test_sprint_balance() {
-        let sprint_balance = SprintTimeBalance::new(2024, 10);
+        let sprint_balance = SprintTimeBalance::new(Context::global(), 2024, 10);
         println!("{:#?}", sprint_balance);
     }
+
+    #[test]
+    fn merges_blocks_naming_an_alias() {
+        use crate::test_vault::{context_for, temporary_vault};
+        let vault = temporary_vault("aliases");
+        std::fs::write(
+            vault.join("Projects/Public API redesign.md"),
+            "---\npriority: 2\nstatus: To Do\njira key: MB-9\naliases:\n  - API\n  - Endpoints v2\nsprints:\n  - Y24W10\n---\n",
+        )
+        .unwrap();
+        std::fs::write(
+            vault.join("Projects/Billing.md"),
+            "---\npriority: 2\nstatus: To Do\naliases: Invoices, Payments\n---\n",
+        )
+        .unwrap();
+        std::fs::write(
+            vault.join("Projects/Invoicing.md"),
+            "---\npriority: 2\nstatus: To Do\naliases: Invoices\n---\n",
+        )
+        .unwrap();
+        std::fs::write(
+            vault.join("Daily/2024-03-05.md"),
+            "- [x] 9:00 - 10:00 [[API]]\n- [ ] 10:00 - 11:00 endpoints v2\n- [ ] 11:00 - 11:30 [[Public API redesign]]\n- [ ] 13:00 - 14:00 [[Payments]]\n- [ ] 14:00 - 15:00 Standup\n- [ ] 15:00 - 16:00 Invoices\n",
+        )
+        .unwrap();
+
+        let context = context_for(&vault);
+        assert_eq!(
+            crate::obsidian::task_file::find_jira_key(
+                &context,
+                &wikilink::Wikilink::parse("API"),
+                std::path::Path::new("Daily")
+            ),
+            Some(crate::jira::JiraKey::new("MB-9"))
+        );
+        let balance = crate::obsidian::SprintTimeBalance::new(&context, 2024, 10);
+        let api = &balance.tasks[0];
+        assert_eq!(api.name, "Public API redesign");
+        assert_eq!(api.completed_time, time::Duration::hours(1));
+        assert_eq!(api.uncompleted_time, time::Duration::minutes(90));
+        assert_eq!(balance.tasks[1].name, "Billing");
+        assert!(!balance.tasks[1].in_sprint);
+        assert_eq!(balance.tasks[2].name, "Invoices");
+        assert_eq!(balance.tasks[3].name, "Standup");
+        assert_eq!(balance.unmatched, ["Invoices", "Standup"]);
+        assert_eq!(
+            balance.warnings,
+            ["`Invoices` names several tasks, `Projects/Billing`, `Projects/Invoicing`, so its blocks count for none"]
+        );
+    }
 }
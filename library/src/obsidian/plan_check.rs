@@ -0,0 +1,223 @@
+use time::{Duration, Time};
+
+use super::planner::{format_time, PlannedTask, PlannerSettings, WorkingHours};
+
+// Shorter gaps between blocks are not worth a warning
+pub const MIN_GAP: Duration = Duration::minutes(15);
+
+/*
+    What looks wrong in the plan of a day:
+    ~ blocks planned at the same time, the time is counted for both
+    ~ blocks ending before they start, they are left out of the schedule
+    ~ time of the working hours where nothing is planned, only for days
+      with a plan
+    ~ more planned time than the daily maximum
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlanWarning {
+    // The lines of the two blocks, the one starting first first
+    Overlap {
+        line: usize,
+        other_line: usize,
+        duration: Duration,
+    },
+    Reversed {
+        line: usize,
+    },
+    Gap {
+        start: Time,
+        end: Time,
+    },
+    AboveMaximum {
+        total: Duration,
+        maximum: Duration,
+    },
+}
+
+impl PlanWarning {
+    // In the daily note, None for the warnings about the whole day
+    pub fn get_line(&self) -> Option<usize> {
+        match self {
+            PlanWarning::Overlap { line, .. } | PlanWarning::Reversed { line } => Some(*line),
+            PlanWarning::Gap { .. } | PlanWarning::AboveMaximum { .. } => None,
+        }
+    }
+}
+
+impl std::fmt::Display for PlanWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let format_duration = |x: &Duration| super::fmt_duration(*x, false).trim().to_owned();
+        match self {
+            PlanWarning::Overlap {
+                line,
+                other_line,
+                duration,
+            } => write!(
+                f,
+                "line {} overlaps line {} for {}",
+                line,
+                other_line,
+                format_duration(duration)
+            ),
+            PlanWarning::Reversed { line } => write!(f, "line {} ends before it starts", line),
+            PlanWarning::Gap { start, end } => write!(
+                f,
+                "nothing planned from {} to {}",
+                format_time(*start),
+                format_time(*end)
+            ),
+            PlanWarning::AboveMaximum { total, maximum } => write!(
+                f,
+                "{} planned, more than the maximum of {}",
+                format_duration(total),
+                format_duration(maximum)
+            ),
+        }
+    }
+}
+
+// The blocks of a single day
+pub fn check_day_plan(tasks: &[PlannedTask], settings: &PlannerSettings) -> Vec<PlanWarning> {
+    let mut warnings = Vec::new();
+
+    // From the start of the day, the end can be on the next one
+    let mut blocks: Vec<(Duration, Duration, usize)> = Vec::new();
+    for task in tasks {
+        let duration = task.get_duration();
+        if duration.is_negative() {
+            warnings.push(PlanWarning::Reversed {
+                line: task.get_line(),
+            });
+            continue;
+        }
+        let start = task.get_start() - Time::MIDNIGHT;
+        blocks.push((start, start + duration, task.get_line()));
+    }
+    blocks.sort();
+
+    for (index, (_, end, line)) in blocks.iter().enumerate() {
+        for (other_start, other_end, other_line) in &blocks[index + 1..] {
+            if other_start >= end {
+                break;
+            }
+            warnings.push(PlanWarning::Overlap {
+                line: *line,
+                other_line: *other_line,
+                duration: *end.min(other_end) - *other_start,
+            });
+        }
+    }
+
+    if let (Some(hours), false) = (settings.get_working_hours(), blocks.is_empty()) {
+        for (start, end) in find_free_time(tasks, hours, MIN_GAP) {
+            warnings.push(PlanWarning::Gap { start, end });
+        }
+    }
+
+    let total: Duration = blocks.iter().map(|(start, end, _)| *end - *start).sum();
+    if let Some(maximum) = settings.get_daily_max().filter(|x| total > *x) {
+        warnings.push(PlanWarning::AboveMaximum { total, maximum });
+    }
+
+    warnings
+}
+
+// The times of the working hours without blocks, at least `minimum` long.
+// Reversed blocks take no time.
+pub fn find_free_time(
+    tasks: &[PlannedTask],
+    hours: &WorkingHours,
+    minimum: Duration,
+) -> Vec<(Time, Time)> {
+    let mut blocks: Vec<(Duration, Duration)> = tasks
+        .iter()
+        .filter(|x| !x.get_duration().is_negative())
+        .map(|x| {
+            let start = x.get_start() - Time::MIDNIGHT;
+            (start, start + x.get_duration())
+        })
+        .collect();
+    blocks.sort();
+
+    let mut free_time = Vec::new();
+    let day_end = hours.get_end() - Time::MIDNIGHT;
+    let mut covered = hours.get_start() - Time::MIDNIGHT;
+    for (start, end) in blocks.into_iter().chain([(day_end, day_end)]) {
+        let start = start.min(day_end);
+        if start - covered >= minimum {
+            free_time.push((Time::MIDNIGHT + covered, Time::MIDNIGHT + start));
+        }
+        covered = covered.max(end);
+    }
+    free_time
+}
+
+#[cfg(test)]
+mod test {
+    use super::PlanWarning;
+    use crate::obsidian::planner::{parse_day_plan, PlanFormat, PlannerSettings, WorkingHours};
+    use time::macros::time;
+    use time::Duration;
+
+    #[test]
+    fn warns_about_the_plan() {
+        let date = crate::commons::Date::from("2024-03-05");
+        let mut settings = PlannerSettings::new(PlanFormat::Standard, &[]);
+        settings
+            .set_working_hours(Some(WorkingHours::new(time!(9:00), time!(18:00))))
+            .set_daily_max(Some(Duration::hours(6)));
+        let plan = parse_day_plan(
+            "- [ ] 9:00 - 10:30 Report\n\
+             - [ ] 10:00 - 11:00 Standup\n\
+             - [ ] 10:15 - 10:45 Call\n\
+             - [ ] 11:05 - 12:00 Review\n\
+             - [ ] 15:00 - 14:00 Backwards\n\
+             - [ ] 14:00 - 17:00 Release\n\
+             - [ ] 23:00 - 1:00 Late\n",
+            &date,
+            &settings,
+        );
+        let warnings = super::check_day_plan(plan.get_tasks(), &settings);
+        assert_eq!(
+            warnings,
+            [
+                PlanWarning::Reversed { line: 5 },
+                PlanWarning::Overlap {
+                    line: 1,
+                    other_line: 2,
+                    duration: Duration::minutes(30),
+                },
+                PlanWarning::Overlap {
+                    line: 1,
+                    other_line: 3,
+                    duration: Duration::minutes(15),
+                },
+                PlanWarning::Overlap {
+                    line: 2,
+                    other_line: 3,
+                    duration: Duration::minutes(30),
+                },
+                PlanWarning::Gap {
+                    start: time!(12:00),
+                    end: time!(14:00),
+                },
+                PlanWarning::Gap {
+                    start: time!(17:00),
+                    end: time!(18:00),
+                },
+                PlanWarning::AboveMaximum {
+                    total: Duration::minutes(535),
+                    maximum: Duration::hours(6),
+                },
+            ]
+        );
+        assert_eq!(warnings[1].to_string(), "line 1 overlaps line 2 for 0:30");
+        assert_eq!(
+            warnings[6].to_string(),
+            "8:55 planned, more than the maximum of 6:00"
+        );
+
+        // A day without blocks has nothing to warn about
+        assert!(super::check_day_plan(&[], &settings).is_empty());
+    }
+}
@@ -1,19 +1,377 @@
 use crate::commons::Date;
+use crate::context::Context;
+use crate::obsidian::date_format::format_date;
+use crate::obsidian::plan_check::{check_day_plan, PlanWarning};
+use crate::obsidian::wikilink::{find_wikilinks, note_id, Wikilink};
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
 
-use time::macros::format_description;
 use time::Time;
 
-use std::fs::read_to_string;
+/*
+    A block of the day plan is a task with a time, like:
 
-#[derive(Debug)]
+        - [ ] 9:00 - 10:30 [[Write the report]] #review
+        - [x] 2pm 45m Call with the client @support
+            - they want the export before Friday
+
+    ~ times are of 24 hours like 9:00, 09:00 or 21:30, or of 12 hours like
+      9am or 9:30 PM
+    ~ the start is followed by an end time after a dash, or by a duration like
+      30m, 1h or 1h30m. A block ending before it starts crosses midnight when
+      that makes it shorter than 12 hours, like 23:00 - 1:00, and is reversed
+      otherwise.
+    ~ `#tags` and `@contexts` are taken out of the name, and tell meetings,
+      reviews and support apart from the rest of the work
+    ~ bullets indented under a block are its notes
+*/
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PlannedTask {
     start: Time,
     end: Time,
+    // Whether the end is on the day after the start
+    next_day: bool,
     date: Date,
+    // The name of the linked note, or the text of the block
     name: String,
-    linked: bool,
+    // The first link of the text, like `[[Write the report|report]]`
+    link: Option<Wikilink>,
     completed: bool,
+    kind: BlockKind,
+    // Without the `#` and the `@`
+    tags: Vec<String>,
+    contexts: Vec<String>,
+    notes: Vec<String>,
+    // In the daily note, from 1
+    line: usize,
+}
+
+impl PlannedTask {
+    pub fn get_start(&self) -> Time {
+        self.start
+    }
+
+    pub fn get_end(&self) -> Time {
+        self.end
+    }
+
+    pub fn ends_next_day(&self) -> bool {
+        self.next_day
+    }
+
+    // Negative for reversed blocks
+    pub fn get_duration(&self) -> time::Duration {
+        match self.next_day {
+            true => self.end - self.start + time::Duration::DAY,
+            false => self.end - self.start,
+        }
+    }
+
+    pub fn get_date(&self) -> &Date {
+        &self.date
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_link(&self) -> Option<&Wikilink> {
+        self.link.as_ref()
+    }
+
+    pub fn is_completed(&self) -> bool {
+        self.completed
+    }
+
+    pub fn get_kind(&self) -> BlockKind {
+        self.kind
+    }
+
+    pub fn get_tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    pub fn get_contexts(&self) -> &[String] {
+        &self.contexts
+    }
+
+    pub fn get_notes(&self) -> &[String] {
+        &self.notes
+    }
+
+    pub fn get_line(&self) -> usize {
+        self.line
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum BlockKind {
+    #[default]
+    Work,
+    Meeting,
+    Review,
+    Support,
+}
+
+impl BlockKind {
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            BlockKind::Work => "work",
+            BlockKind::Meeting => "meeting",
+            BlockKind::Review => "review",
+            BlockKind::Support => "support",
+        }
+    }
+
+    // From a tag or a context, like `#meeting` or `@support`
+    pub fn from_marker(marker: &str) -> Option<Self> {
+        match marker.to_lowercase().as_str() {
+            "meeting" | "meetings" => Some(BlockKind::Meeting),
+            "review" | "reviews" => Some(BlockKind::Review),
+            "support" => Some(BlockKind::Support),
+            _ => None,
+        }
+    }
+}
+
+// A line that looks like a block but could not be read
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlanDiagnostic {
+    pub line: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for PlanDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DayPlan {
+    tasks: Vec<PlannedTask>,
+    diagnostics: Vec<PlanDiagnostic>,
+}
+
+impl DayPlan {
+    pub fn get_tasks(&self) -> &[PlannedTask] {
+        &self.tasks
+    }
+
+    pub fn get_diagnostics(&self) -> &[PlanDiagnostic] {
+        &self.diagnostics
+    }
+
+    pub fn into_tasks(self) -> Vec<PlannedTask> {
+        self.tasks
+    }
+}
+
+/*
+    How the daily notes are written, from `planner` in the config:
+
+        planner:
+          format: day_planner
+          sections: [Plan, Log]
+          working_hours: 9:00 - 17:30
+          daily_max: 7h30m
+
+    ~ `standard` is the syntax described above
+    ~ `day_planner` is the one of the Day Planner plugin of Obsidian, where
+      the checkbox can be left out and a block without an end time lasts until
+      the start of the next one. Durations are not read.
+    With sections, only the blocks under a heading with one of these titles
+    are read, up to the next heading of the same or a higher level, so the
+    checkboxes of meeting notes or of a journal are left out. `heading` is
+    taken for a single section, like in the settings of the plugin.
+
+    The working hours and the daily maximum are used to warn about the plans,
+    see `plan_check`, and to propose them, see `scheduler`. New blocks are
+    written in the first section, or in a `Plan` one.
+*/
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PlannerSettings {
+    #[serde(default)]
+    format: PlanFormat,
+    #[serde(default)]
+    #[serde(alias = "heading")]
+    #[serde(deserialize_with = "deserialize_sections")]
+    sections: Vec<String>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    working_hours: Option<WorkingHours>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    daily_max: Option<PlanDuration>,
+}
+
+impl PlannerSettings {
+    pub fn new(format: PlanFormat, sections: &[&str]) -> Self {
+        Self {
+            format,
+            sections: sections.iter().map(|x| x.to_string()).collect(),
+            working_hours: None,
+            daily_max: None,
+        }
+    }
+
+    pub fn set_working_hours(&mut self, working_hours: Option<WorkingHours>) -> &mut Self {
+        self.working_hours = working_hours;
+        self
+    }
+
+    pub fn set_daily_max(&mut self, daily_max: Option<time::Duration>) -> &mut Self {
+        self.daily_max = daily_max.map(PlanDuration);
+        self
+    }
+
+    pub fn get_format(&self) -> PlanFormat {
+        self.format
+    }
+
+    // Empty to read the whole note
+    pub fn get_sections(&self) -> &[String] {
+        &self.sections
+    }
+
+    pub fn get_working_hours(&self) -> Option<&WorkingHours> {
+        self.working_hours.as_ref()
+    }
+
+    pub fn get_daily_max(&self) -> Option<time::Duration> {
+        self.daily_max.map(|x| x.0)
+    }
+}
+
+// Written like `9:00 - 17:30` or `9am - 5:30pm`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct WorkingHours {
+    start: Time,
+    end: Time,
+}
+
+impl WorkingHours {
+    pub const fn new(start: Time, end: Time) -> Self {
+        Self { start, end }
+    }
+
+    pub fn get_start(&self) -> Time {
+        self.start
+    }
+
+    pub fn get_end(&self) -> Time {
+        self.end
+    }
+}
+
+impl TryFrom<String> for WorkingHours {
+    type Error = String;
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        match parse_times(&text, PlanFormat::Standard) {
+            Some(Ok((span, rest))) if rest.trim().is_empty() && span.end > Some(span.start) => {
+                Ok(Self::new(span.start, span.end.unwrap()))
+            }
+            Some(Err(message)) => Err(message),
+            _ => Err(format!(
+                "working hours are written like `9:00 - 17:30`, found `{}`",
+                text
+            )),
+        }
+    }
+}
+
+impl From<WorkingHours> for String {
+    fn from(hours: WorkingHours) -> String {
+        format!("{} - {}", format_time(hours.start), format_time(hours.end))
+    }
+}
+
+// Written like `45m`, `8h` or `7h30m`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+struct PlanDuration(time::Duration);
+
+impl TryFrom<String> for PlanDuration {
+    type Error = String;
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        match take_duration(text.trim()) {
+            Some((duration, rest)) if rest.trim().is_empty() => Ok(Self(duration)),
+            _ => Err(format!(
+                "durations are written like `45m`, `8h` or `7h30m`, found `{}`",
+                text
+            )),
+        }
+    }
+}
+
+impl From<PlanDuration> for String {
+    fn from(duration: PlanDuration) -> String {
+        let minutes = duration.0.whole_minutes();
+        format!("{}h{}m", minutes / 60, minutes % 60)
+    }
+}
+
+// Like 9:05 or 17:30
+pub fn format_time(time: Time) -> String {
+    format!("{}:{:02}", time.hour(), time.minute())
+}
+
+// As written in the blocks, the Day Planner plugin wants two digits for the hours
+pub fn write_time(time: Time, format: PlanFormat) -> String {
+    match format {
+        PlanFormat::Standard => format_time(time),
+        PlanFormat::DayPlanner => format!("{:02}:{:02}", time.hour(), time.minute()),
+    }
+}
+
+// A list of titles, or a single one
+fn deserialize_sections<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Sections {
+        List(Vec<String>),
+        Text(String),
+    }
+    Ok(match Sections::deserialize(deserializer)? {
+        Sections::List(sections) => sections,
+        Sections::Text(section) => vec![section],
+    })
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlanFormat {
+    #[default]
+    Standard,
+    DayPlanner,
+}
+
+// What the Day Planner plugin gives to the last block of a day without an end
+pub const DAY_PLANNER_DURATION: time::Duration = time::Duration::minutes(30);
+
+// What the time of a block is credited to
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Allocated {
+    // A note of the vault, as given by `wikilink::note_id`
+    Note(String),
+    // Plain text, or a link to a note that does not exist
+    Text(String),
+}
+
+impl Allocated {
+    pub fn get_name(&self) -> &str {
+        match self {
+            Allocated::Note(id) => id.rsplit('/').next().unwrap_or_default(),
+            Allocated::Text(text) => text,
+        }
+    }
 }
 
 #[derive(Default, Debug)]
@@ -31,126 +389,1089 @@ impl TimeAllocation {
     }
 }
 
+impl std::ops::AddAssign<&TimeAllocation> for TimeAllocation {
+    fn add_assign(&mut self, other: &TimeAllocation) {
+        self.uncompleted_time += other.uncompleted_time;
+        self.completed_time += other.completed_time;
+    }
+}
+
 #[derive(Debug)]
 pub struct TaskSchedule {
     planned_tasks: Vec<PlannedTask>,
-    time_allocations: HashMap<String, TimeAllocation>,
+    time_allocations: HashMap<Allocated, TimeAllocation>,
+    diagnostics: Vec<(Date, PlanDiagnostic)>,
+    warnings: Vec<(Date, PlanWarning)>,
 }
 
 impl TaskSchedule {
-    pub fn new(start_date: &Date, end_date: &Date) -> Self {
+    pub fn new(context: &Context, start_date: &Date, end_date: &Date) -> Self {
         let mut planned_tasks = Vec::new();
+        let mut keys = Vec::new();
+        let mut diagnostics = Vec::new();
+        let mut warnings = Vec::new();
+        let resolver = context.get_note_resolver();
 
         for date in crate::commons::DateIterator::new(start_date, end_date) {
-            let tasks = read_day_plan(&date);
-            if let Some(mut tasks) = tasks {
-                planned_tasks.append(&mut tasks);
+            let plan = read_day_plan_with_diagnostics(context, &date);
+            if let Some(plan) = plan {
+                diagnostics.extend(plan.diagnostics.iter().map(|x| (date.clone(), x.clone())));
+                let day_warnings = check_day_plan(&plan.tasks, context.get_config().get_planner());
+                warnings.extend(day_warnings.into_iter().map(|x| (date.clone(), x)));
+                // Links are resolved from the folder of the daily note
+                let note_path = daily_note_path(context, &date);
+                let folder = note_path.parent().unwrap_or(Path::new(""));
+                for task in plan.tasks {
+                    keys.push(
+                        match task.link.as_ref().and_then(|x| resolver.resolve(x, folder)) {
+                            Some(path) => Allocated::Note(note_id(path)),
+                            None => Allocated::Text(task.name.clone()),
+                        },
+                    );
+                    planned_tasks.push(task);
+                }
             }
         }
-
         let mut time_allocations = HashMap::new();
-        for planned_task in planned_tasks.iter() {
+        for (planned_task, key) in planned_tasks.iter().zip(keys) {
+            // Reversed blocks are in the warnings, they would take time away
+            if planned_task.get_duration().is_negative() {
+                continue;
+            }
             let allocation = time_allocations
-                .entry(planned_task.name.clone())
+                .entry(key)
                 .or_insert_with(|| TimeAllocation::default());
 
             if planned_task.completed {
-                allocation.completed_time += planned_task.end - planned_task.start;
+                allocation.completed_time += planned_task.get_duration();
             } else {
-                allocation.uncompleted_time += planned_task.end - planned_task.start;
+                allocation.uncompleted_time += planned_task.get_duration();
             }
         }
         Self {
             planned_tasks,
             time_allocations,
+            diagnostics,
+            warnings,
         }
     }
 
-    pub fn get_task_time_allocation(&self, task_name: &str) -> Option<&TimeAllocation> {
-        self.time_allocations.get(task_name)
+    pub fn get_planned_tasks(&self) -> &[PlannedTask] {
+        &self.planned_tasks
     }
 
-    pub fn iter_time_allocations(&self) -> impl Iterator<Item = (&String, &TimeAllocation)> {
+    // The lines of the daily notes that could not be read
+    pub fn get_diagnostics(&self) -> &[(Date, PlanDiagnostic)] {
+        &self.diagnostics
+    }
+
+    // What looks wrong in the plans that could be read, see `plan_check`
+    pub fn get_warnings(&self) -> &[(Date, PlanWarning)] {
+        &self.warnings
+    }
+
+    pub fn get_task_time_allocation(&self, key: &Allocated) -> Option<&TimeAllocation> {
+        self.time_allocations.get(key)
+    }
+
+    pub fn iter_time_allocations(&self) -> impl Iterator<Item = (&Allocated, &TimeAllocation)> {
         self.time_allocations.iter()
     }
 }
 
-pub fn read_day_plan(date: &Date) -> Option<Vec<PlannedTask>> {
-    // println!("{:?}", date);
-    let vault_path = crate::config::CONFIG.get_vault_path();
-    let day_planner_path = crate::config::CONFIG.get_daily_notes_path();
-    let date_string: String = date.clone().into();
-
-    let mut total_path: std::path::PathBuf = [vault_path, day_planner_path, &date_string]
+// Relative to the vault
+pub fn daily_note_path(context: &Context, date: &Date) -> PathBuf {
+    let config = context.get_config();
+    // The format can add folders, like YYYY/MM/YYYY-MM-DD, and dots
+    let note_name = format_date(&date.get_date(), config.get_daily_note_format());
+    [config.get_daily_notes_path(), &format!("{}.md", note_name)]
         .iter()
-        .collect();
+        .collect()
+}
 
-    total_path.set_extension("md");
-
-    let time_format = format_description!("[hour padding:none]:[minute]");
-    let mut output = Vec::new();
-    let text = read_to_string(total_path).ok()?;
-    /*
-        Okay, so here comes the explanation:
-        ~ - Matches the dash at the beginning of the task
-        ~ \[(.)\]        Matches one character surrounded by [], so [ ] and [X] are valid
-                         The inside is the capture group 1
-        ~ \s+            Matches as much whitespace as needed
-        ~ (\d+:\d\d)     Matches a time as capture group 2, like 9:30 or 18:15
-        ~ \s*-\s*        A dash surrounded by arbitrary whitespace
-        ~ (\d+:\d\d)     Again, matches a time, this time for capture group 3
-        ~ \s*            As much whitespace as needed
-        ~ (.*)           One last match of anything
-    */
-    let re = regex::Regex::new(r"- \[(.)\]\s+(\d+:\d\d)\s*-\s*(\d+:\d\d)\s*(.*)").unwrap();
-    for line in text.lines() {
-        let Some(capture) = re.captures(line) else {
+pub fn read_day_plan(context: &Context, date: &Date) -> Option<Vec<PlannedTask>> {
+    read_day_plan_with_diagnostics(context, date).map(DayPlan::into_tasks)
+}
+
+pub fn read_day_plan_with_diagnostics(context: &Context, date: &Date) -> Option<DayPlan> {
+    context.get_index().get_day_plan(
+        context.get_vault_path(),
+        &daily_note_path(context, date),
+        date,
+        context.get_config().get_planner(),
+    )
+}
+
+pub fn parse_day_plan(text: &str, date: &Date, settings: &PlannerSettings) -> DayPlan {
+    let entry = entry_regex(settings.format);
+    static BULLET: OnceLock<regex::Regex> = OnceLock::new();
+    let bullet = BULLET.get_or_init(|| regex::Regex::new(r"^\s*[-*+]\s+(.*)$").unwrap());
+    let in_section = match settings.sections.is_empty() {
+        true => vec![true; text.lines().count()],
+        false => find_sections(text, &settings.sections),
+    };
+    let mut plan = DayPlan::default();
+    // Indentation of the last block, the bullets under it are its notes
+    let mut parent: Option<usize> = None;
+    // Blocks lasting until the next one
+    let mut open_blocks = Vec::new();
+
+    for (index, line) in text.lines().enumerate() {
+        let number = index + 1;
+        let indent = indentation(line);
+        if !in_section[index] {
+            parent = None;
             continue;
-        };
-        // println!("{:?}", capture);
+        }
+
+        if let Some(capture) = entry.captures(line) {
+            let completed = capture.get(1).is_some_and(|x| x.as_str() != " ");
+            match parse_times(&capture[2], settings.format) {
+                Some(Ok((span, text))) => {
+                    if span.end.is_none() {
+                        open_blocks.push(plan.tasks.len());
+                    }
+                    plan.tasks
+                        .push(new_block(date, number, completed, span, text));
+                    parent = Some(indent);
+                    continue;
+                }
+                Some(Err(message)) => {
+                    plan.diagnostics.push(PlanDiagnostic {
+                        line: number,
+                        message,
+                    });
+                    parent = None;
+                    continue;
+                }
+                // A task without a time, which can be a note
+                None => (),
+            }
+        }
 
-        if capture.len() != 5 {
+        match parent {
+            Some(parent_indent) if indent > parent_indent => {
+                if let Some(capture) = bullet.captures(line) {
+                    let task = plan.tasks.last_mut().unwrap();
+                    task.notes.push(capture[1].trim().to_owned());
+                }
+            }
+            _ if line.trim().is_empty() => (),
+            _ => parent = None,
+        }
+    }
+
+    // Like the plugin, the next block closes an open one, and the last one of
+    // the day gets the default duration
+    for index in open_blocks {
+        let start = plan.tasks[index].start;
+        let duration = plan
+            .tasks
+            .get(index + 1)
+            .map(|next| match next.start - start {
+                x if x.is_negative() && x + time::Duration::DAY < time::Duration::hours(12) => {
+                    x + time::Duration::DAY
+                }
+                x => x,
+            })
+            .filter(|x| x.is_positive())
+            .unwrap_or(DAY_PLANNER_DURATION);
+        let task = &mut plan.tasks[index];
+        task.end = start + duration;
+        task.next_day = start - Time::MIDNIGHT + duration >= time::Duration::DAY;
+    }
+
+    plan
+}
+
+// A checkbox, maybe indented, and what follows it. The Day Planner plugin
+// also reads list items without one. Compiled once per format.
+fn entry_regex(format: PlanFormat) -> &'static regex::Regex {
+    static STANDARD: OnceLock<regex::Regex> = OnceLock::new();
+    static DAY_PLANNER: OnceLock<regex::Regex> = OnceLock::new();
+    match format {
+        PlanFormat::Standard => {
+            STANDARD.get_or_init(|| regex::Regex::new(r"^\s*[-*+] \[(.)\]\s+(.*)$").unwrap())
+        }
+        PlanFormat::DayPlanner => DAY_PLANNER
+            .get_or_init(|| regex::Regex::new(r"^\s*[-*+] (?:\[(.)\]\s+)?(.*)$").unwrap()),
+    }
+}
+
+// One flag per line, for whether it is under a heading with one of these
+// titles, up to the next heading of the same or a higher level. The case and
+// the `#` written in the titles are ignored.
+fn find_sections(text: &str, titles: &[String]) -> Vec<bool> {
+    let wanted: Vec<String> = titles.iter().map(|x| section_title(x)).collect();
+    // Level of the heading of the section the lines are in
+    let mut section: Option<usize> = None;
+    let mut headings = Headings::default();
+    text.lines()
+        .map(|line| {
+            let Some((level, title)) = headings.read(line) else {
+                return section.is_some();
+            };
+            if section.is_some_and(|x| level <= x) {
+                section = None;
+            }
+            if section.is_none() && wanted.contains(&section_title(title)) {
+                section = Some(level);
+            }
+            // The heading itself is not in its section
+            false
+        })
+        .collect()
+}
+
+// The lines of the first section with that title, from its heading
+fn find_section_lines(text: &str, title: &str) -> Option<std::ops::Range<usize>> {
+    let wanted = section_title(title);
+    let mut section: Option<(usize, usize)> = None;
+    let mut headings = Headings::default();
+    for (index, line) in text.lines().enumerate() {
+        let Some((level, title)) = headings.read(line) else {
             continue;
+        };
+        match section {
+            Some((start, section_level)) if level <= section_level => return Some(start..index),
+            None if section_title(title) == wanted => section = Some((index, level)),
+            _ => (),
         }
+    }
+    section.map(|(start, _)| start..text.lines().count())
+}
 
-        let completed = !(capture.get(1).unwrap().as_str() == " ");
-        let start = Time::parse(
-            capture.get(2).unwrap().as_str().trim_start_matches("0"),
-            time_format,
+fn section_title(title: &str) -> String {
+    title.trim_start_matches('#').trim().to_lowercase()
+}
+
+// Reads the headings of a note line after line
+#[derive(Default)]
+struct Headings {
+    // `#` starts comments in code blocks, not headings
+    in_code: bool,
+}
+
+impl Headings {
+    // The level and the title of a heading
+    fn read<'a>(&mut self, line: &'a str) -> Option<(usize, &'a str)> {
+        if line.trim_start().starts_with("```") {
+            self.in_code = !self.in_code;
+            return None;
+        }
+        let level = line.chars().take_while(|x| *x == '#').count();
+        let title = &line[level..];
+        if self.in_code
+            || level == 0
+            || level > 6
+            || !(title.is_empty() || title.starts_with(char::is_whitespace))
+        {
+            return None;
+        }
+        Some((level, title))
+    }
+}
+
+/*
+    Adds lines at the end of the section with that title, after its last line
+    that is not blank, or in a new section at the end of the note. The rest of
+    the text is left as it is, line endings included.
+*/
+pub fn append_to_section(text: &str, title: &str, lines: &[String]) -> String {
+    let newline = if text.contains("\r\n") { "\r\n" } else { "\n" };
+    let mut added: String = lines.iter().map(|x| format!("{}{}", x, newline)).collect();
+    let offset = match find_section_lines(text, title) {
+        Some(range) => {
+            let all: Vec<&str> = text.split_inclusive('\n').collect();
+            let last = range
+                .clone()
+                .rev()
+                .find(|x| !all[*x].trim().is_empty())
+                .unwrap_or(range.start);
+            if !all[last].ends_with('\n') {
+                added.insert_str(0, newline);
+            }
+            all[..=last].iter().map(|x| x.len()).sum()
+        }
+        None => {
+            let heading = format!("## {}{}", title.trim_start_matches('#').trim(), newline);
+            added.insert_str(0, &heading);
+            if !text.is_empty() {
+                if !text.ends_with('\n') {
+                    added.insert_str(0, newline);
+                }
+                if !text.trim_end_matches(['\r', '\n']).is_empty()
+                    && !text.ends_with(&format!("{}{}", newline, newline))
+                {
+                    added.insert_str(0, newline);
+                }
+            }
+            text.len()
+        }
+    };
+    let mut output = text.to_owned();
+    output.insert_str(offset, &added);
+    output
+}
+
+// Where new blocks go when the config has no sections
+pub const DEFAULT_SECTION: &str = "Plan";
+
+#[derive(Debug)]
+pub enum PlanEditError {
+    Io(PathBuf, std::io::Error),
+    // The line of the block changed since the plan was read
+    Changed(PathBuf, usize),
+    // Like a split outside of the block
+    Invalid(String),
+}
+
+impl std::fmt::Display for PlanEditError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PlanEditError::Io(path, error) => {
+                write!(f, "Could not edit {}: {}", path.display(), error)
+            }
+            PlanEditError::Changed(path, line) => write!(
+                f,
+                "Line {} of {} changed since the plan was read",
+                line,
+                path.display()
+            ),
+            PlanEditError::Invalid(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for PlanEditError {}
+
+/*
+    Changes to the plan of a day, written to the daily notes:
+    ~ the block is the one read from the note, it is looked for again at its
+      line and nothing is written when that line changed since
+    ~ only the lines of the block change, the rest of the note is left as it
+      is. The notes under a block go with it to another day, up to the first
+      checkbox or time under it, which is a block of its own.
+    ~ new blocks go at the end of the first section of the plan, see
+      `append_to_section`
+*/
+pub fn set_block_completed(
+    context: &Context,
+    task: &PlannedTask,
+    completed: bool,
+) -> Result<(), PlanEditError> {
+    let found = find_block(context, task)?;
+    let line = found.line.with_checkbox(completed);
+    write_note(&found.path, &found.replaced(found.first_line(), &line))
+}
+
+// Keeps the duration of the block
+pub fn move_block(
+    context: &Context,
+    task: &PlannedTask,
+    date: &Date,
+    start: Time,
+) -> Result<(), PlanEditError> {
+    if task.get_duration().is_negative() {
+        return Err(PlanEditError::Invalid(format!(
+            "The block of line {} ends before it starts",
+            task.line
+        )));
+    }
+    let found = find_block(context, task)?;
+    let format = context.get_config().get_planner().format;
+    let moved = found
+        .line
+        .with_times(start, start + task.get_duration(), format);
+    if date.get_date() == task.date.get_date() {
+        return write_note(&found.path, &found.replaced(found.first_line(), &moved));
+    }
+
+    // The notes keep their indentation under the block
+    let indent = &found.line.text[..found.line.text.len() - found.line.text.trim_start().len()];
+    let lines: Vec<String> = std::iter::once(moved.trim_start().to_owned())
+        .chain(
+            found.text[found.range.clone()]
+                .lines()
+                .skip(1)
+                .map(|x| x.strip_prefix(indent).unwrap_or(x).to_owned()),
         )
-        .unwrap();
-        let end = Time::parse(capture.get(3).unwrap().as_str(), time_format).unwrap();
-        let mut name = capture.get(4).unwrap().as_str().to_owned();
-        // This is a mess, do it right later
-        let linked: bool;
-        if &name[..2] == "[[" {
-            name = name[2..name.len() - 2].to_owned();
-            linked = true;
-        } else {
-            linked = false;
-        }
-
-        output.push(PlannedTask {
-            start,
-            end,
-            date: date.clone(),
-            name,
-            linked,
-            completed,
+        .collect();
+    // Removed first and put back on failure, so the block is never on both days
+    write_note(&found.path, &found.replaced(found.range.clone(), ""))?;
+    if let Err(error) = append_to_day_plan(context, date, &lines) {
+        write_note(&found.path, &found.text)?;
+        return Err(error);
+    }
+    Ok(())
+}
+
+// In two blocks, the second one after the notes of the first
+pub fn split_block(context: &Context, task: &PlannedTask, at: Time) -> Result<(), PlanEditError> {
+    let mut offset = at - task.start;
+    if offset.is_negative() {
+        offset += time::Duration::DAY;
+    }
+    if !offset.is_positive() || offset >= task.get_duration() {
+        return Err(PlanEditError::Invalid(format!(
+            "{} is not within the block of line {}",
+            format_time(at),
+            task.line
+        )));
+    }
+    let found = find_block(context, task)?;
+    let format = context.get_config().get_planner().format;
+    let block = &found.text[found.range.clone()];
+    let newline = if found.text.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let mut replacement = found.line.with_times(task.start, at, format);
+    replacement.push_str(&found.text[found.first_line().end..found.range.end]);
+    if !block.ends_with('\n') {
+        replacement.push_str(newline);
+    }
+    replacement.push_str(&found.line.with_times(at, task.end, format));
+    if block.ends_with('\n') {
+        replacement.push_str(newline);
+    }
+    write_note(
+        &found.path,
+        &found.replaced(found.range.clone(), &replacement),
+    )
+}
+
+// `text` is what follows the times, like `[[Write the report]] #review`
+pub fn append_block(
+    context: &Context,
+    date: &Date,
+    start: Time,
+    end: Time,
+    text: &str,
+) -> Result<(), PlanEditError> {
+    let format = context.get_config().get_planner().format;
+    let line = format!(
+        "- [ ] {} - {} {}",
+        write_time(start, format),
+        write_time(end, format),
+        text.trim()
+    );
+    append_to_day_plan(context, date, &[line])
+}
+
+// Creates the daily note or its section when needed
+pub fn append_to_day_plan(
+    context: &Context,
+    date: &Date,
+    lines: &[String],
+) -> Result<(), PlanEditError> {
+    if lines.is_empty() {
+        return Ok(());
+    }
+    let path = context
+        .get_vault_path()
+        .join(daily_note_path(context, date));
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(error) => return Err(PlanEditError::Io(path, error)),
+    };
+    let section = context
+        .get_config()
+        .get_planner()
+        .sections
+        .first()
+        .map_or(DEFAULT_SECTION, String::as_str);
+    write_note(&path, &append_to_section(&text, section, lines))
+}
+
+// A block as it is written in its daily note
+struct FoundBlock {
+    path: PathBuf,
+    text: String,
+    // In bytes, the line of the block and the notes under it with their line endings
+    range: std::ops::Range<usize>,
+    line: BlockLine,
+}
+
+impl FoundBlock {
+    // Without its line ending
+    fn first_line(&self) -> std::ops::Range<usize> {
+        self.range.start..self.range.start + self.line.text.len()
+    }
+
+    fn replaced(&self, range: std::ops::Range<usize>, replacement: &str) -> String {
+        let mut text = self.text.clone();
+        text.replace_range(range, replacement);
+        text
+    }
+}
+
+// Where the parts of a block are in its line
+struct BlockLine {
+    text: String,
+    // The mark between the brackets
+    checkbox: Option<std::ops::Range<usize>>,
+    // Where the text after the bullet starts, for a checkbox to add
+    text_start: usize,
+    times: std::ops::Range<usize>,
+}
+
+impl BlockLine {
+    fn with_times(&self, start: Time, end: Time, format: PlanFormat) -> String {
+        let mut line = self.text.clone();
+        let times = format!(
+            "{} - {}",
+            write_time(start, format),
+            write_time(end, format)
+        );
+        line.replace_range(self.times.clone(), &times);
+        line
+    }
+
+    fn with_checkbox(&self, completed: bool) -> String {
+        let mark = if completed { "x" } else { " " };
+        let mut line = self.text.clone();
+        match &self.checkbox {
+            Some(range) => line.replace_range(range.clone(), mark),
+            None => line.insert_str(self.text_start, &format!("[{}] ", mark)),
+        }
+        line
+    }
+}
+
+fn find_block(context: &Context, task: &PlannedTask) -> Result<FoundBlock, PlanEditError> {
+    let settings = context.get_config().get_planner();
+    let path = context
+        .get_vault_path()
+        .join(daily_note_path(context, &task.date));
+    let text = std::fs::read_to_string(&path).map_err(|x| PlanEditError::Io(path.clone(), x))?;
+    let changed = || PlanEditError::Changed(path.clone(), task.line);
+
+    let plan = parse_day_plan(&text, &task.date, settings);
+    let unchanged = plan.tasks.iter().any(|x| {
+        x.line == task.line
+            && x.start == task.start
+            && x.end == task.end
+            && x.name == task.name
+            && x.completed == task.completed
+    });
+    if !unchanged {
+        return Err(changed());
+    }
+
+    let regex = entry_regex(settings.format);
+    let is_block = |line: &str| {
+        regex.captures(line.trim_end()).is_some_and(|x| {
+            x.get(1).is_some() || parse_times(x.get(2).unwrap().as_str(), settings.format).is_some()
         })
+    };
+
+    let lines: Vec<&str> = text.split_inclusive('\n').collect();
+    let index = task.line - 1;
+    let start: usize = lines[..index].iter().map(|x| x.len()).sum();
+    let content = lines[index].trim_end_matches(['\r', '\n']);
+    // The notes are the lines indented under the block, up to a block under it
+    let indent = indentation(content);
+    let mut end = start + lines[index].len();
+    let mut offset = end;
+    for line in &lines[index + 1..] {
+        offset += line.len();
+        if line.trim().is_empty() {
+            continue;
+        }
+        if indentation(line) <= indent || is_block(line) {
+            break;
+        }
+        end = offset;
+    }
+
+    let capture = regex.captures(content).ok_or_else(changed)?;
+    let after = capture.get(2).unwrap();
+    let Some(Ok((_, rest))) = parse_times(after.as_str(), settings.format) else {
+        return Err(changed());
+    };
+    let times = &content[after.start()..after.end() - rest.len()];
+    let line = BlockLine {
+        text: content.to_owned(),
+        checkbox: capture.get(1).map(|x| x.range()),
+        text_start: after.start(),
+        times: after.start()..after.start() + times.trim_end().len(),
+    };
+    Ok(FoundBlock {
+        path,
+        text,
+        range: start..end,
+        line,
+    })
+}
+
+// The daily notes cannot be rebuilt, they are never left half written
+fn write_note(path: &Path, text: &str) -> Result<(), PlanEditError> {
+    crate::jira::cache::write_file_atomically(path, text.as_bytes())
+        .map_err(|x| PlanEditError::Io(path.to_owned(), x))
+}
+
+fn new_block(date: &Date, line: usize, completed: bool, span: Span, text: &str) -> PlannedTask {
+    let (tags, contexts, rest) = take_markers(text);
+    let link = find_wikilinks(&rest)
+        .into_iter()
+        .map(|(_, link)| link)
+        .find(|x| !x.embed);
+    let name = match &link {
+        Some(link) => link.get_note_name().to_owned(),
+        None if rest.is_empty() => text.trim().to_owned(),
+        None => rest,
+    };
+    let kind = tags
+        .iter()
+        .chain(contexts.iter())
+        .find_map(|x| BlockKind::from_marker(x))
+        .unwrap_or_default();
+
+    PlannedTask {
+        start: span.start,
+        end: span.end.unwrap_or(span.start),
+        next_day: span.next_day,
+        date: date.clone(),
+        name,
+        link,
+        completed,
+        kind,
+        tags,
+        contexts,
+        notes: Vec::new(),
+        line,
+    }
+}
+
+// Tabs count as 4 spaces
+fn indentation(line: &str) -> usize {
+    line.chars()
+        .take_while(|x| x.is_whitespace())
+        .map(|x| if x == '\t' { 4 } else { 1 })
+        .sum()
+}
+
+struct Span {
+    start: Time,
+    // None until the next block for the Day Planner format
+    end: Option<Time>,
+    next_day: bool,
+}
+
+// The times of a block and the rest of the text, None when the text does not
+// start with a time
+fn parse_times(text: &str, format: PlanFormat) -> Option<Result<(Span, &str), String>> {
+    let (start, rest) = match take_time(text)? {
+        Ok(x) => x,
+        Err(message) => return Some(Err(message)),
+    };
+    let rest = rest.trim_start();
+
+    if let Some(after) = ["-", "–", "—"].iter().find_map(|x| rest.strip_prefix(x)) {
+        let after = after.trim_start();
+        return Some(match take_time(after) {
+            Some(Ok((end, rest))) => {
+                let next_day =
+                    end < start && end - start + time::Duration::DAY < time::Duration::hours(12);
+                Ok((
+                    Span {
+                        start,
+                        end: Some(end),
+                        next_day,
+                    },
+                    rest,
+                ))
+            }
+            Some(Err(message)) => Err(message),
+            None => Err(format!(
+                "expected an end time after the dash, found '{}'",
+                first_word(after)
+            )),
+        });
+    }
+
+    if format == PlanFormat::DayPlanner {
+        let span = Span {
+            start,
+            end: None,
+            next_day: false,
+        };
+        return Some(Ok((span, rest)));
+    }
+
+    Some(match take_duration(rest) {
+        Some((duration, _)) if duration >= time::Duration::DAY => {
+            Err(String::from("a block has to last less than a day"))
+        }
+        Some((duration, rest)) => {
+            let next_day = start - Time::MIDNIGHT + duration >= time::Duration::DAY;
+            Ok((
+                Span {
+                    start,
+                    end: Some(start + duration),
+                    next_day,
+                },
+                rest,
+            ))
+        }
+        None => Err(format!(
+            "expected an end time or a duration like 30m after the start, found '{}'",
+            first_word(rest)
+        )),
+    })
+}
+
+// A time like 9:30, 09:30, 21:30, 9am or 9:30 p.m., and the text after it.
+// None when the text does not start with one, like `3 apples`.
+fn take_time(text: &str) -> Option<Result<(Time, &str), String>> {
+    let digits = text
+        .find(|x: char| !x.is_ascii_digit())
+        .unwrap_or(text.len());
+    if digits == 0 || digits > 2 {
+        return None;
+    }
+    let mut hour: u8 = text[..digits].parse().ok()?;
+    let mut minute = None;
+    let mut rest = &text[digits..];
+    if let Some(after) = rest.strip_prefix(':') {
+        let digits = after
+            .find(|x: char| !x.is_ascii_digit())
+            .unwrap_or(after.len());
+        if digits != 2 {
+            return Some(Err(format!("'{}' is not a time", first_word(text))));
+        }
+        minute = after[..2].parse::<u8>().ok();
+        rest = &after[2..];
+    }
+
+    let trimmed = rest.trim_start();
+    let lowercase = trimmed.to_ascii_lowercase();
+    let meridiem = ["a.m.", "p.m.", "am", "pm"].into_iter().find(|x| {
+        lowercase.starts_with(x) && !lowercase[x.len()..].starts_with(char::is_alphanumeric)
+    });
+    let written = &text[..text.len() - rest.len()];
+    match meridiem {
+        Some(meridiem) => {
+            rest = &trimmed[meridiem.len()..];
+            if !(1..=12).contains(&hour) {
+                return Some(Err(format!("'{} {}' is not a time", written, meridiem)));
+            }
+            hour = hour % 12 + if meridiem.starts_with('p') { 12 } else { 0 };
+        }
+        // A number alone is not a time
+        None if minute.is_none() => return None,
+        None => (),
     }
 
-    Some(output)
+    Some(
+        Time::from_hms(hour, minute.unwrap_or(0), 0)
+            .map(|x| (x, rest))
+            .map_err(|_| format!("'{}' is not a time", written)),
+    )
+}
+
+// A duration like 45m, 45min, 2h, 1h30m or 1h 30m, and the text after it
+fn take_duration(text: &str) -> Option<(time::Duration, &str)> {
+    fn number(text: &str) -> Option<(i64, &str)> {
+        let digits = text
+            .find(|x: char| !x.is_ascii_digit())
+            .unwrap_or(text.len());
+        Some((text[..digits].parse().ok()?, &text[digits..]))
+    }
+    let mut minutes = None;
+    let mut rest = text;
+    if let Some((hours, after)) =
+        number(rest).and_then(|(hours, after)| Some((hours, after.strip_prefix('h')?)))
+    {
+        minutes = Some(hours.checked_mul(60)?);
+        rest = after;
+    }
+    if let Some((count, after)) = number(rest.trim_start()).and_then(|(count, after)| {
+        Some((
+            count,
+            after.strip_prefix("min").or(after.strip_prefix('m'))?,
+        ))
+    }) {
+        minutes = Some(minutes.unwrap_or(0).checked_add(count)?);
+        rest = after;
+    }
+
+    match minutes {
+        Some(minutes) if rest.is_empty() || rest.starts_with(char::is_whitespace) => {
+            Some((time::Duration::seconds(minutes.checked_mul(60)?), rest))
+        }
+        _ => None,
+    }
+}
+
+// The `#tags` and `@contexts` written outside of links, and the text without them
+fn take_markers(text: &str) -> (Vec<String>, Vec<String>, String) {
+    let links = find_wikilinks(text);
+    let mut tags = Vec::new();
+    let mut contexts = Vec::new();
+    let mut rest = String::new();
+    let mut index = 0;
+
+    while index < text.len() {
+        if let Some((range, _)) = links.iter().find(|(range, _)| range.start == index) {
+            rest.push_str(&text[range.clone()]);
+            index = range.end;
+            continue;
+        }
+        let character = text[index..].chars().next().unwrap();
+        let starts_word = index == 0 || text[..index].ends_with(char::is_whitespace);
+        if starts_word && (character == '#' || character == '@') {
+            let name = &text[index + 1..];
+            let name = &name[..name
+                .find(|x: char| !(x.is_alphanumeric() || "-_/".contains(x)))
+                .unwrap_or(name.len())];
+            // Like Obsidian, `#123` is not a tag
+            if !name.is_empty() && !name.chars().all(|x| x.is_ascii_digit()) {
+                match character {
+                    '#' => tags.push(name.to_owned()),
+                    _ => contexts.push(name.to_owned()),
+                }
+                index += 1 + name.len();
+                continue;
+            }
+        }
+        rest.push(character);
+        index += character.len_utf8();
+    }
+
+    let rest = rest.split_whitespace().collect::<Vec<_>>().join(" ");
+    (tags, contexts, rest)
+}
+
+fn first_word(text: &str) -> &str {
+    text.split_whitespace().next().unwrap_or_default()
 }
 
 #[cfg(test)]
 mod test {
+    #[test]
+    fn parses_links_in_blocks() {
+        let date = crate::commons::Date::from("2024-03-05");
+        let tasks = super::parse_day_plan(
+            "- [ ] 9:00 - 10:00 [[Projects/Report#Draft|report]] first part\n- [x] 10:00 - 11:00 Review of [[Report]]\n- [ ] 11:00 - 12:00 Meeting\n",
+            &date,
+            &Default::default(),
+        )
+        .into_tasks();
+        assert_eq!(tasks.len(), 3);
+        assert_eq!(tasks[0].name, "Report");
+        let link = tasks[0].link.as_ref().unwrap();
+        assert_eq!(link.path, "Projects/Report");
+        assert_eq!(link.heading.as_deref(), Some("Draft"));
+        assert_eq!(tasks[1].name, "Report");
+        assert_eq!(tasks[2].name, "Meeting");
+        assert!(tasks[2].link.is_none());
+    }
+
+    #[test]
+    fn reads_durations() {
+        use super::take_duration;
+        assert_eq!(
+            take_duration("1h 30m Standup"),
+            Some((time::Duration::minutes(90), " Standup"))
+        );
+        // Digits out of the notes must not overflow
+        assert_eq!(take_duration("99999999999999999h"), None);
+        assert_eq!(take_duration("153722867280912931m"), None);
+        assert_eq!(take_duration("99999999999999999999m"), None);
+    }
+
+    #[test]
+    fn reads_the_richer_syntax() {
+        use super::BlockKind;
+        use time::macros::time;
+        let date = crate::commons::Date::from("2024-03-05");
+        let plan = super::parse_day_plan(
+            "# Tuesday\n\
+             - [ ] 09:00 30m [[Report]] #review\n\
+             \x20   - check the numbers\n\
+             \t- and the charts\n\
+             - [x] 2pm - 3:30 PM Call with the client @support #urgent\n\
+             - [ ] 1:30pm 1h 15m Standup #meeting with [[Team#Notes]]\n\
+             - [ ] 23:00 - 1:00 Release\n\
+             - [ ] 10:00 - 9:00 Backwards\n\
+             - [ ] 23:30 45m Late\n\
+             - [ ] 3 apples\n\
+             - [ ] 25:00 - 26:00 Nothing\n\
+             - [ ] 9:00 Missing the end\n\
+             - [ ] 13pm - 2pm Nope\n\
+             \x20 - not a note anymore\n",
+            &date,
+            &Default::default(),
+        );
+        let tasks = plan.get_tasks();
+        assert_eq!(tasks.len(), 6);
+
+        assert_eq!(tasks[0].get_start(), time!(9:00));
+        assert_eq!(tasks[0].get_end(), time!(9:30));
+        assert_eq!(tasks[0].get_name(), "Report");
+        assert_eq!(tasks[0].get_kind(), BlockKind::Review);
+        assert_eq!(
+            tasks[0].get_notes(),
+            ["check the numbers", "and the charts"]
+        );
+        assert_eq!(tasks[0].get_line(), 2);
+
+        assert_eq!(tasks[1].get_duration(), time::Duration::minutes(90));
+        assert_eq!(tasks[1].get_name(), "Call with the client");
+        assert_eq!(tasks[1].get_kind(), BlockKind::Support);
+        assert_eq!(tasks[1].get_tags(), ["urgent"]);
+        assert_eq!(tasks[1].get_contexts(), ["support"]);
+        assert!(tasks[1].is_completed());
+
+        assert_eq!(tasks[2].get_end(), time!(14:45));
+        assert_eq!(tasks[2].get_name(), "Team");
+        assert_eq!(tasks[2].get_kind(), BlockKind::Meeting);
+
+        assert!(tasks[3].ends_next_day());
+        assert_eq!(tasks[3].get_duration(), time::Duration::hours(2));
+        assert!(!tasks[4].ends_next_day());
+        assert_eq!(tasks[4].get_duration(), time::Duration::hours(-1));
+        assert!(tasks[5].ends_next_day());
+        assert_eq!(tasks[5].get_end(), time!(0:15));
+
+        let diagnostics: Vec<_> = plan
+            .get_diagnostics()
+            .iter()
+            .map(|x| x.to_string())
+            .collect();
+        assert_eq!(
+            diagnostics,
+            [
+                "line 11: '25:00' is not a time",
+                "line 12: expected an end time or a duration like 30m after the start, found 'Missing'",
+                "line 13: '13 pm' is not a time",
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_the_day_planner_format() {
+        use super::{PlanFormat, PlannerSettings};
+        use time::macros::time;
+        let date = crate::commons::Date::from("2024-03-05");
+        let text = "# Journal\n\
+                    - [ ] 08:00 - 08:30 Not planned\n\
+                    ## Day planner\n\
+                    - [x] 09:00 - 10:00 [[Report]]\n\
+                    - 10:00 Standup\n\
+                    \x20 - with the notes\n\
+                    - [ ] 11:30 Lunch\n\
+                    ### Afternoon\n\
+                    - [ ] 14:00 Review\n\
+                    ## Notes\n\
+                    - [ ] 16:00 - 17:00 Not planned either\n";
+        let names = |settings: &PlannerSettings| {
+            super::parse_day_plan(text, &date, settings)
+                .into_tasks()
+                .iter()
+                .map(|x| (x.get_name().to_owned(), x.get_start(), x.get_end()))
+                .collect::<Vec<_>>()
+        };
+
+        let settings = PlannerSettings::new(PlanFormat::DayPlanner, &["## Day Planner"]);
+        let plan = super::parse_day_plan(text, &date, &settings);
+        assert!(plan.get_tasks()[0].is_completed());
+        assert_eq!(plan.get_tasks()[1].get_notes(), ["with the notes"]);
+        assert_eq!(
+            names(&settings),
+            [
+                (String::from("Report"), time!(9:00), time!(10:00)),
+                (String::from("Standup"), time!(10:00), time!(11:30)),
+                (String::from("Lunch"), time!(11:30), time!(14:00)),
+                (String::from("Review"), time!(14:00), time!(14:30)),
+            ]
+        );
+
+        // Without a heading, the whole note is read
+        let settings = PlannerSettings::new(PlanFormat::DayPlanner, &[]);
+        assert_eq!(names(&settings).len(), 6);
+        // The standard format needs the end and the checkbox
+        assert_eq!(names(&PlannerSettings::default()).len(), 3);
+    }
+
+    #[test]
+    fn reads_only_the_sections() {
+        use super::{PlanFormat, PlannerSettings};
+        let date = crate::commons::Date::from("2024-03-05");
+        let text = "- [ ] 7:00 - 7:30 Before any heading\n\
+                    ## Plan\n\
+                    - [ ] 9:00 - 10:00 Report\n\
+                    ### Morning\n\
+                    - [ ] 10:00 - 11:00 Still planned\n\
+                    ```\n\
+                    # not a heading\n\
+                    ```\n\
+                    - [ ] 11:00 - 11:30 After the code\n\
+                    ## Meeting notes\n\
+                    - [ ] 12:00 - 13:00 Follow up\n\
+                    # Log\n\
+                    ## Morning\n\
+                    - [x] 8:00 - 8:30 Emails\n\
+                    # Journal\n\
+                    - [ ] 20:00 - 21:00 Reading\n";
+        let names = |sections: &[&str]| {
+            let settings = PlannerSettings::new(PlanFormat::Standard, sections);
+            super::parse_day_plan(text, &date, &settings)
+                .into_tasks()
+                .iter()
+                .map(|x| x.get_name().to_owned())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(names(&[]).len(), 7);
+        assert_eq!(
+            names(&["plan"]),
+            ["Report", "Still planned", "After the code"]
+        );
+        assert_eq!(
+            names(&["Plan", "# Log"]),
+            ["Report", "Still planned", "After the code", "Emails"]
+        );
+        assert!(names(&["Missing"]).is_empty());
+    }
+
+    #[test]
+    fn appends_to_a_section() {
+        use super::append_to_section;
+        let lines = ["- [ ] 9:00 - 10:00 [[Report]]".to_owned()];
+        assert_eq!(
+            append_to_section(
+                "# Day\r\n## Plan\r\n- [x] 8:00 - 9:00 Emails\r\n\r\n## Log\r\nText",
+                "plan",
+                &lines
+            ),
+            "# Day\r\n## Plan\r\n- [x] 8:00 - 9:00 Emails\r\n- [ ] 9:00 - 10:00 [[Report]]\r\n\r\n## Log\r\nText"
+        );
+        assert_eq!(
+            append_to_section("## Plan", "Plan", &lines),
+            "## Plan\n- [ ] 9:00 - 10:00 [[Report]]\n"
+        );
+        assert_eq!(
+            append_to_section("Some text\n", "Plan", &lines),
+            "Some text\n\n## Plan\n- [ ] 9:00 - 10:00 [[Report]]\n"
+        );
+        assert_eq!(
+            append_to_section("", "Plan", &lines),
+            "## Plan\n- [ ] 9:00 - 10:00 [[Report]]\n"
+        );
+    }
+
     #[test]
     fn read_simple_day() {
         use super::read_day_plan;
+        use crate::context::Context;
         let date = crate::commons::Date::from("2024-02-14");
 
-        let tasks = read_day_plan(&date).unwrap();
+        let tasks = read_day_plan(Context::global(), &date).unwrap();
         for task in tasks {
             println!("{:#?}", task);
         }
@@ -160,8 +1481,95 @@ mod test {
         let start_date = crate::commons::Date::from("2024-02-26");
         let end_date = crate::commons::Date::from("2024-03-01");
 
-        let schedule = super::TaskSchedule::new(&start_date, &end_date);
+        let schedule =
+            super::TaskSchedule::new(crate::context::Context::global(), &start_date, &end_date);
 
         println!("{:#?}", schedule);
     }
+
+    #[test]
+    fn edits_the_day_plan() {
+        use crate::obsidian::planner::{
+            append_block, move_block, read_day_plan, set_block_completed, split_block,
+        };
+        use crate::test_vault::{context_for, temporary_vault};
+        use time::macros::time;
+        let vault = temporary_vault("plan-edits");
+        std::fs::write(
+            vault.join("Daily/2024-03-05.md"),
+            "# Tuesday\r\n\r\n- [ ] 9:00 - 11:00 [[Report]] #review\r\n    - draft first\r\n- [ ] 11:00 45m Call\r\n- [x] 14:00 - 15:00 Standup\r\nThe end",
+        )
+        .unwrap();
+        let context = context_for(&vault);
+        let tuesday = crate::commons::Date::from("2024-03-05");
+        let wednesday = crate::commons::Date::from("2024-03-06");
+        let block = |name: &str| {
+            read_day_plan(&context, &tuesday)
+                .unwrap()
+                .into_iter()
+                .find(|x| x.get_name() == name)
+                .unwrap()
+        };
+
+        let report = block("Report");
+        split_block(&context, &report, time!(10:00)).unwrap();
+        // The block read before the split is no longer in the note
+        assert!(set_block_completed(&context, &report, true).is_err());
+        assert!(split_block(&context, &block("Call"), time!(12:00)).is_err());
+        set_block_completed(&context, &block("Report"), true).unwrap();
+        set_block_completed(&context, &block("Standup"), false).unwrap();
+        move_block(&context, &block("Call"), &tuesday, time!(16:00)).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(vault.join("Daily/2024-03-05.md")).unwrap(),
+            "# Tuesday\r\n\r\n- [x] 9:00 - 10:00 [[Report]] #review\r\n    - draft first\r\n- [ ] 10:00 - 11:00 [[Report]] #review\r\n- [ ] 16:00 - 16:45 Call\r\n- [ ] 14:00 - 15:00 Standup\r\nThe end"
+        );
+
+        let report = read_day_plan(&context, &tuesday).unwrap().remove(0);
+        move_block(&context, &report, &wednesday, time!(13:30)).unwrap();
+        append_block(&context, &wednesday, time!(9:00), time!(9:30), "Emails").unwrap();
+        assert_eq!(
+            std::fs::read_to_string(vault.join("Daily/2024-03-05.md")).unwrap(),
+            "# Tuesday\r\n\r\n- [ ] 10:00 - 11:00 [[Report]] #review\r\n- [ ] 16:00 - 16:45 Call\r\n- [ ] 14:00 - 15:00 Standup\r\nThe end"
+        );
+        assert_eq!(
+            std::fs::read_to_string(vault.join("Daily/2024-03-06.md")).unwrap(),
+            "## Plan\n- [x] 13:30 - 14:30 [[Report]] #review\n    - draft first\n- [ ] 9:00 - 9:30 Emails\n"
+        );
+    }
+
+    #[test]
+    fn moves_a_block_without_the_blocks_under_it() {
+        use crate::obsidian::planner::{move_block, read_day_plan};
+        use crate::test_vault::{context_for, temporary_vault};
+        use time::macros::time;
+        let vault = temporary_vault("plan-nested");
+        let tuesday_note = "- [ ] 9:00 - 10:00 Parent\n    - a note\n    - [ ] 9:15 - 9:30 Child\n    - about the child\n- [ ] 11:00 - 12:00 Other\n";
+        std::fs::write(vault.join("Daily/2024-03-05.md"), tuesday_note).unwrap();
+        // A folder where the note of Thursday goes, so it cannot be written
+        std::fs::create_dir_all(vault.join("Daily/2024-03-07.md")).unwrap();
+        let context = context_for(&vault);
+        let tuesday = crate::commons::Date::from("2024-03-05");
+        let parent = read_day_plan(&context, &tuesday).unwrap().remove(0);
+        assert_eq!(parent.get_name(), "Parent");
+
+        let thursday = crate::commons::Date::from("2024-03-07");
+        assert!(move_block(&context, &parent, &thursday, time!(13:00)).is_err());
+        assert_eq!(
+            std::fs::read_to_string(vault.join("Daily/2024-03-05.md")).unwrap(),
+            tuesday_note
+        );
+
+        let wednesday = crate::commons::Date::from("2024-03-06");
+        move_block(&context, &parent, &wednesday, time!(13:00)).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(vault.join("Daily/2024-03-05.md")).unwrap(),
+            "    - [ ] 9:15 - 9:30 Child\n    - about the child\n- [ ] 11:00 - 12:00 Other\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(vault.join("Daily/2024-03-06.md")).unwrap(),
+            "## Plan\n- [ ] 13:00 - 14:00 Parent\n    - a note\n"
+        );
+        // Written through a temporary file renamed over the note
+        assert!(!vault.join("Daily/.2024-03-05.md.tmp").exists());
+    }
 }
@@ -0,0 +1,145 @@
+use std::ops::{BitAnd, BitOr, Not, RangeInclusive};
+
+use crate::commons::{IssueType, Priority, Sprint, Status};
+
+use super::task_file::TaskFile;
+
+/*
+    Questions about a task, combined with `&`, `|` and `!`. Overdue high
+    priority tasks that are not in this sprint are:
+
+        TaskPredicate::DueBefore(today)
+            & TaskPredicate::Priority(Priority::High..=Priority::VeryHigh)
+            & !TaskPredicate::InSprint(sprint)
+
+    Tasks without a due date are neither due before nor after any date.
+*/
+#[derive(Debug, Clone)]
+pub enum TaskPredicate {
+    Status(Status),
+    Priority(RangeInclusive<Priority>),
+    // Both exclusive
+    DueBefore(time::Date),
+    DueAfter(time::Date),
+    // Without the `#`, ignoring the case
+    Tag(String),
+    InSprint(Sprint),
+    HasJiraKey,
+    IssueType(IssueType),
+    HasRemainingTime,
+    // In the name of the note, ignoring the case
+    TitleContains(String),
+    And(Vec<TaskPredicate>),
+    Or(Vec<TaskPredicate>),
+    Not(Box<TaskPredicate>),
+}
+
+impl TaskPredicate {
+    pub fn matches(&self, task: &TaskFile) -> bool {
+        use TaskPredicate::*;
+        match self {
+            Status(status) => task.get_status() == *status,
+            Priority(range) => range.contains(&task.get_priority()),
+            DueBefore(date) => task.get_due_date().is_some_and(|x| x.get_date() < *date),
+            DueAfter(date) => task.get_due_date().is_some_and(|x| x.get_date() > *date),
+            Tag(tag) => task.get_tags().iter().any(|x| x.eq_ignore_ascii_case(tag)),
+            InSprint(sprint) => task.get_sprints().contains(sprint),
+            HasJiraKey => task.get_jira_key().is_some(),
+            IssueType(issue_type) => task.get_issue_type() == Some(*issue_type),
+            HasRemainingTime => task.get_remaining_time().is_positive(),
+            TitleContains(text) => task
+                .get_name()
+                .to_lowercase()
+                .contains(&text.to_lowercase()),
+            And(predicates) => predicates.iter().all(|x| x.matches(task)),
+            Or(predicates) => predicates.iter().any(|x| x.matches(task)),
+            Not(predicate) => !predicate.matches(task),
+        }
+    }
+}
+
+impl BitAnd for TaskPredicate {
+    type Output = TaskPredicate;
+
+    fn bitand(self, other: Self) -> Self {
+        match self {
+            TaskPredicate::And(mut predicates) => {
+                predicates.push(other);
+                TaskPredicate::And(predicates)
+            }
+            _ => TaskPredicate::And(vec![self, other]),
+        }
+    }
+}
+
+impl BitOr for TaskPredicate {
+    type Output = TaskPredicate;
+
+    fn bitor(self, other: Self) -> Self {
+        match self {
+            TaskPredicate::Or(mut predicates) => {
+                predicates.push(other);
+                TaskPredicate::Or(predicates)
+            }
+            _ => TaskPredicate::Or(vec![self, other]),
+        }
+    }
+}
+
+impl Not for TaskPredicate {
+    type Output = TaskPredicate;
+
+    fn not(self) -> Self {
+        match self {
+            TaskPredicate::Not(predicate) => *predicate,
+            _ => TaskPredicate::Not(Box::new(self)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::TaskPredicate;
+    use crate::commons::{IssueType, Priority, Sprint, Status};
+    use crate::obsidian::task_file::TaskFile;
+    use time::macros::date;
+
+    fn task(name: &str, properties: &str) -> TaskFile {
+        let text = format!("---\n{}\n---\n", properties);
+        TaskFile::parse(std::path::Path::new(name), &text).unwrap()
+    }
+
+    #[test]
+    fn combines_predicates() {
+        let report = task(
+            "Projects/Write the report.md",
+            "priority: 2\nstatus: In Progress\ndue date: 2024-03-01\nsprints:\n  - Y24W10\ntags: [docs, '#Writing']\nremaining time: '3:00'",
+        );
+        let bug = task(
+            "Projects/Fix the login.md",
+            "priority: 1\nstatus: To Do\ndue date: 2024-02-20\njira key: MB-8\nissue type: Sub-task\ntags: bug",
+        );
+        let idea = task("Projects/Idea.md", "priority: 5\nstatus: Done");
+
+        let today = date!(2024 - 03 - 05);
+        let overdue_not_in_sprint = TaskPredicate::DueBefore(today)
+            & TaskPredicate::Priority(Priority::High..=Priority::VeryHigh)
+            & !TaskPredicate::InSprint(Sprint::from("Y24W10"));
+        assert!(!overdue_not_in_sprint.matches(&report));
+        assert!(overdue_not_in_sprint.matches(&bug));
+        assert!(!overdue_not_in_sprint.matches(&idea));
+
+        assert!(TaskPredicate::Tag(String::from("writing")).matches(&report));
+        assert!(TaskPredicate::Tag(String::from("bug")).matches(&bug));
+        assert!(TaskPredicate::HasRemainingTime.matches(&report));
+        assert!(!TaskPredicate::HasRemainingTime.matches(&bug));
+        assert!(TaskPredicate::IssueType(IssueType::SubTask).matches(&bug));
+        assert!(TaskPredicate::TitleContains(String::from("LOGIN")).matches(&bug));
+        assert!(!TaskPredicate::DueAfter(today).matches(&idea));
+
+        let open_or_tracked = !TaskPredicate::Status(Status::Done) | TaskPredicate::HasJiraKey;
+        assert!(open_or_tracked.matches(&report));
+        assert!(!open_or_tracked.matches(&idea));
+        assert!((!!TaskPredicate::HasJiraKey).matches(&bug));
+    }
+}
@@ -0,0 +1,801 @@
+use std::cmp::Ordering;
+
+use crate::commons::{IssueType, Priority, Sprint, Status};
+use crate::context::Context;
+
+use super::predicate::TaskPredicate;
+use super::task_file::{TaskFile, TaskFilter};
+
+/*
+    Queries in the style of Dataview, to write the same questions in the CLI,
+    the TUI and the saved views of the config:
+
+        FROM "Projects" WHERE status != "Done" AND sprints CONTAINS "Y24W10" SORT priority
+
+    ~ FROM "folder" or FROM #tag   the project folder when missing
+    ~ WHERE condition              with AND, OR, NOT and parentheses
+    ~ SORT field [ASC|DESC], ...   ties keep the order of the files
+    ~ GROUP BY field               groups in the order they first appear
+
+    Keywords ignore the case. The fields and what they accept:
+    ~ status     = != "In Progress"
+    ~ priority   = != < <= > >= with the number of the notes, 1 is the highest
+    ~ due        = != < <= > >= 2024-03-01, date(today), tomorrow or yesterday
+    ~ tags       CONTAINS "tag"
+    ~ sprints    CONTAINS "Y24W10"
+    ~ type       = != "Story"
+    ~ name       CONTAINS "text", in the name of the note
+    ~ jira       alone, for tasks with a Jira key
+    ~ remaining  alone or `> 0`, for tasks with remaining time
+    ~ folder     only to sort or group
+*/
+#[derive(Debug, Clone)]
+pub struct Query {
+    source: Option<Source>,
+    predicate: Option<TaskPredicate>,
+    sort: Vec<SortKey>,
+    group_by: Option<Field>,
+}
+
+#[derive(Debug, Clone)]
+enum Source {
+    Folder(String),
+    Tag(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Status,
+    Priority,
+    Due,
+    Tags,
+    Sprints,
+    JiraKey,
+    IssueType,
+    Remaining,
+    Name,
+    Folder,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SortKey {
+    pub field: Field,
+    pub descending: bool,
+}
+
+// Tasks sharing the value of the GROUP BY field, or all of them with no key
+#[derive(Debug)]
+pub struct TaskGroup {
+    // Empty for the tasks without a value
+    pub key: Option<String>,
+    pub tasks: Vec<TaskFile>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryError {
+    // In characters from the start of the query
+    pub position: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for QueryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "at character {}: {}", self.position + 1, self.message)
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+impl Query {
+    // Relative dates like `today` are taken from the local date
+    pub fn parse(text: &str) -> Result<Self, QueryError> {
+        let today = time::OffsetDateTime::now_local()
+            .unwrap_or_else(|_| time::OffsetDateTime::now_utc())
+            .date();
+        Self::parse_on(text, today)
+    }
+
+    pub fn parse_on(text: &str, today: time::Date) -> Result<Self, QueryError> {
+        let mut parser = Parser {
+            tokens: tokenize(text)?,
+            index: 0,
+            end: text.chars().count(),
+            today,
+        };
+        parser.parse_query()
+    }
+
+    pub fn get_sort(&self) -> &[SortKey] {
+        &self.sort
+    }
+
+    pub fn get_group_by(&self) -> Option<Field> {
+        self.group_by
+    }
+
+    pub fn to_filter(&self, context: &Context) -> TaskFilter {
+        let mut filter = TaskFilter::new();
+        filter.set_recursive(true);
+        let mut predicates = Vec::new();
+        match &self.source {
+            None => {
+                filter.set_path(context.get_config().get_project_path());
+            }
+            Some(Source::Folder(folder)) => {
+                filter.set_path(folder.trim_matches('/'));
+            }
+            // Like Dataview, tags are looked for in the whole vault
+            Some(Source::Tag(tag)) => predicates.push(TaskPredicate::Tag(tag.clone())),
+        }
+        predicates.extend(self.predicate.clone());
+        match predicates.len() {
+            0 => {}
+            1 => {
+                filter.set_predicate(predicates.remove(0));
+            }
+            _ => {
+                filter.set_predicate(TaskPredicate::And(predicates));
+            }
+        }
+        filter
+    }
+
+    pub fn run(&self, context: &Context) -> Vec<TaskGroup> {
+        self.arrange(self.to_filter(context).get_tasks(context))
+    }
+
+    // Sorts and groups tasks already filtered
+    pub fn arrange(&self, mut tasks: Vec<TaskFile>) -> Vec<TaskGroup> {
+        tasks.sort_by(|a, b| {
+            self.sort
+                .iter()
+                .map(|key| {
+                    let ordering = compare(key.field, a, b);
+                    if key.descending {
+                        ordering.reverse()
+                    } else {
+                        ordering
+                    }
+                })
+                .find(|x| x.is_ne())
+                .unwrap_or(Ordering::Equal)
+        });
+
+        let Some(field) = self.group_by else {
+            return vec![TaskGroup { key: None, tasks }];
+        };
+        let mut groups: Vec<TaskGroup> = Vec::new();
+        for task in tasks {
+            let key = field_text(field, &task);
+            match groups
+                .iter_mut()
+                .find(|x| x.key.as_deref() == Some(key.as_str()))
+            {
+                Some(group) => group.tasks.push(task),
+                None => groups.push(TaskGroup {
+                    key: Some(key),
+                    tasks: vec![task],
+                }),
+            }
+        }
+        groups
+    }
+}
+
+impl std::str::FromStr for Query {
+    type Err = QueryError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        Self::parse(text)
+    }
+}
+
+impl Field {
+    fn from_name(name: &str) -> Option<Self> {
+        use Field::*;
+        Some(match name.to_lowercase().as_str() {
+            "status" => Status,
+            "priority" => Priority,
+            "due" | "due-date" | "due_date" => Due,
+            "tags" | "tag" | "file.tags" => Tags,
+            "sprints" | "sprint" => Sprints,
+            "jira" | "jira-key" | "jira_key" | "key" => JiraKey,
+            "type" | "issue-type" | "issue_type" => IssueType,
+            "remaining" | "remaining-time" | "remaining_time" => Remaining,
+            "name" | "title" | "file.name" => Name,
+            "folder" | "file.folder" => Folder,
+            _ => return None,
+        })
+    }
+
+    pub fn get_name(&self) -> &'static str {
+        use Field::*;
+        match self {
+            Status => "status",
+            Priority => "priority",
+            Due => "due",
+            Tags => "tags",
+            Sprints => "sprints",
+            JiraKey => "jira",
+            IssueType => "type",
+            Remaining => "remaining",
+            Name => "name",
+            Folder => "folder",
+        }
+    }
+}
+
+fn folder(task: &TaskFile) -> String {
+    task.get_path()
+        .parent()
+        .map(|x| x.to_string_lossy().replace('\\', "/"))
+        .unwrap_or_default()
+}
+
+// Tasks without a value go last
+fn compare(field: Field, a: &TaskFile, b: &TaskFile) -> Ordering {
+    fn missing_last<T: Ord>(a: Option<T>, b: Option<T>) -> Ordering {
+        match (a, b) {
+            (Some(a), Some(b)) => a.cmp(&b),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        }
+    }
+    let status_rank = |task: &TaskFile| {
+        [
+            Status::ToDo,
+            Status::InProgress,
+            Status::Blocked,
+            Status::Done,
+        ]
+        .iter()
+        .position(|x| *x == task.get_status())
+    };
+
+    match field {
+        Field::Priority => a
+            .get_priority()
+            .to_number()
+            .cmp(&b.get_priority().to_number()),
+        Field::Status => status_rank(a).cmp(&status_rank(b)),
+        Field::Due => missing_last(
+            a.get_due_date().map(|x| x.get_date()),
+            b.get_due_date().map(|x| x.get_date()),
+        ),
+        Field::Remaining => a.get_remaining_time().cmp(&b.get_remaining_time()),
+        Field::Name => a
+            .get_name()
+            .to_lowercase()
+            .cmp(&b.get_name().to_lowercase()),
+        Field::Folder => folder(a).cmp(&folder(b)),
+        Field::IssueType => missing_last(
+            a.get_issue_type().map(|x| x.get_name()),
+            b.get_issue_type().map(|x| x.get_name()),
+        ),
+        Field::JiraKey => missing_last(
+            a.get_jira_key().map(|x| x.to_string()),
+            b.get_jira_key().map(|x| x.to_string()),
+        ),
+        Field::Tags | Field::Sprints => missing_last(
+            Some(field_text(field, a)).filter(|x| !x.is_empty()),
+            Some(field_text(field, b)).filter(|x| !x.is_empty()),
+        ),
+    }
+}
+
+fn field_text(field: Field, task: &TaskFile) -> String {
+    match field {
+        Field::Status => task.get_status().get_name().to_owned(),
+        Field::Priority => task.get_priority().to_number().to_string(),
+        Field::Due => task
+            .get_due_date()
+            .map(|x| x.get_date().to_string())
+            .unwrap_or_default(),
+        Field::Tags => task.get_tags().join(", "),
+        Field::Sprints => task
+            .get_sprints()
+            .iter()
+            .map(|x| x.get_name())
+            .collect::<Vec<_>>()
+            .join(", "),
+        Field::JiraKey => task
+            .get_jira_key()
+            .map(|x| x.to_string())
+            .unwrap_or_default(),
+        Field::IssueType => task
+            .get_issue_type()
+            .map(|x| x.get_name().to_owned())
+            .unwrap_or_default(),
+        Field::Remaining => {
+            let minutes = task.get_remaining_time().whole_minutes();
+            format!("{}:{:0>2}", minutes / 60, minutes % 60)
+        }
+        Field::Name => task.get_name(),
+        Field::Folder => folder(task),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Text(String),
+    Tag(String),
+    Symbol(&'static str),
+}
+
+const SYMBOLS: [&str; 10] = ["!=", "<=", ">=", "=", "<", ">", "!", "(", ")", ","];
+
+fn tokenize(text: &str) -> Result<Vec<(Token, usize)>, QueryError> {
+    let chars: Vec<char> = text.chars().collect();
+    let is_word = |x: char| x.is_alphanumeric() || "_-.:/".contains(x);
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let start = i;
+        let character = chars[i];
+        if character.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        if character == '"' || character == '\'' {
+            let Some(length) = chars[i + 1..].iter().position(|x| *x == character) else {
+                return Err(QueryError {
+                    position: start,
+                    message: String::from("the text is not closed"),
+                });
+            };
+            tokens.push((
+                Token::Text(chars[i + 1..i + 1 + length].iter().collect()),
+                start,
+            ));
+            i += length + 2;
+        } else if character == '#' {
+            i += 1;
+            while i < chars.len() && (is_word(chars[i]) || chars[i] == '#') {
+                i += 1;
+            }
+            tokens.push((Token::Tag(chars[start + 1..i].iter().collect()), start));
+        } else if let Some(symbol) = SYMBOLS.iter().find(|x| {
+            x.chars()
+                .enumerate()
+                .all(|(offset, y)| chars.get(i + offset) == Some(&y))
+        }) {
+            tokens.push((Token::Symbol(symbol), start));
+            i += symbol.len();
+        } else if is_word(character) {
+            while i < chars.len() && is_word(chars[i]) {
+                i += 1;
+            }
+            tokens.push((Token::Word(chars[start..i].iter().collect()), start));
+        } else {
+            return Err(QueryError {
+                position: start,
+                message: format!("unexpected `{}`", character),
+            });
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    index: usize,
+    // Position of the end of the query, for the errors there
+    end: usize,
+    today: time::Date,
+}
+
+impl Parser {
+    fn position(&self) -> usize {
+        self.tokens.get(self.index).map_or(self.end, |x| x.1)
+    }
+
+    fn error<T>(&self, message: String) -> Result<T, QueryError> {
+        Err(QueryError {
+            position: self.position(),
+            message,
+        })
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.index).map(|x| &x.0)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.index).map(|x| x.0.clone());
+        self.index += 1;
+        token
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Word(x)) if x.eq_ignore_ascii_case(keyword))
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let found = self.is_keyword(keyword);
+        if found {
+            self.index += 1;
+        }
+        found
+    }
+
+    fn eat_symbol(&mut self, symbol: &'static str) -> bool {
+        let found = self.peek() == Some(&Token::Symbol(symbol));
+        if found {
+            self.index += 1;
+        }
+        found
+    }
+
+    fn parse_query(&mut self) -> Result<Query, QueryError> {
+        let mut query = Query {
+            source: None,
+            predicate: None,
+            sort: Vec::new(),
+            group_by: None,
+        };
+
+        if self.eat_keyword("from") {
+            query.source = Some(match self.next() {
+                Some(Token::Text(folder)) => Source::Folder(folder),
+                Some(Token::Tag(tag)) => Source::Tag(tag),
+                _ => {
+                    self.index -= 1;
+                    return self.error(String::from(
+                        "FROM is followed by a folder in quotes or a #tag",
+                    ));
+                }
+            });
+        }
+
+        while self.peek().is_some() {
+            if self.eat_keyword("where") {
+                let predicate = self.parse_or()?;
+                query.predicate = Some(match query.predicate.take() {
+                    Some(previous) => previous & predicate,
+                    None => predicate,
+                });
+            } else if self.eat_keyword("sort") {
+                loop {
+                    let field = self.parse_field()?;
+                    let descending = if self.eat_keyword("desc") {
+                        true
+                    } else {
+                        self.eat_keyword("asc");
+                        false
+                    };
+                    query.sort.push(SortKey { field, descending });
+                    if !self.eat_symbol(",") {
+                        break;
+                    }
+                }
+            } else if self.eat_keyword("group") {
+                if !self.eat_keyword("by") {
+                    return self.error(String::from("GROUP is followed by BY"));
+                }
+                query.group_by = Some(self.parse_field()?);
+            } else {
+                return self.error(String::from("expected WHERE, SORT or GROUP BY"));
+            }
+        }
+        Ok(query)
+    }
+
+    fn parse_or(&mut self) -> Result<TaskPredicate, QueryError> {
+        let mut predicate = self.parse_and()?;
+        while self.eat_keyword("or") {
+            predicate = predicate | self.parse_and()?;
+        }
+        Ok(predicate)
+    }
+
+    fn parse_and(&mut self) -> Result<TaskPredicate, QueryError> {
+        let mut predicate = self.parse_unary()?;
+        while self.eat_keyword("and") {
+            predicate = predicate & self.parse_unary()?;
+        }
+        Ok(predicate)
+    }
+
+    fn parse_unary(&mut self) -> Result<TaskPredicate, QueryError> {
+        if self.eat_keyword("not") || self.eat_symbol("!") {
+            return Ok(!self.parse_unary()?);
+        }
+        if self.eat_symbol("(") {
+            let predicate = self.parse_or()?;
+            if !self.eat_symbol(")") {
+                return self.error(String::from("expected `)`"));
+            }
+            return Ok(predicate);
+        }
+        self.parse_comparison()
+    }
+
+    fn parse_field(&mut self) -> Result<Field, QueryError> {
+        match self.peek() {
+            Some(Token::Word(name)) => match Field::from_name(name) {
+                Some(field) => {
+                    self.index += 1;
+                    Ok(field)
+                }
+                None => self.error(format!("unknown field `{}`", name)),
+            },
+            _ => self.error(String::from("expected a field, like `status`")),
+        }
+    }
+
+    // Text in quotes or a single word, `date(...)` around it is left out
+    fn parse_value(&mut self) -> Result<String, QueryError> {
+        if self.is_keyword("date")
+            && self.tokens.get(self.index + 1).map(|x| &x.0) == Some(&Token::Symbol("("))
+        {
+            self.index += 2;
+            let value = self.parse_value()?;
+            if !self.eat_symbol(")") {
+                return self.error(String::from("expected `)`"));
+            }
+            return Ok(value);
+        }
+        match self.peek().cloned() {
+            Some(Token::Word(value) | Token::Text(value)) => {
+                self.index += 1;
+                Ok(value)
+            }
+            Some(Token::Tag(tag)) => {
+                self.index += 1;
+                Ok(tag)
+            }
+            _ => self.error(String::from("expected a value")),
+        }
+    }
+
+    fn parse_comparison(&mut self) -> Result<TaskPredicate, QueryError> {
+        let field_position = self.position();
+        let field = self.parse_field()?;
+
+        let operator = match self.peek() {
+            Some(Token::Symbol(symbol)) if ["=", "!=", "<", "<=", ">", ">="].contains(symbol) => {
+                let symbol = *symbol;
+                self.index += 1;
+                symbol
+            }
+            Some(Token::Word(word)) if word.eq_ignore_ascii_case("contains") => {
+                self.index += 1;
+                "contains"
+            }
+            // A field alone, like `WHERE jira`
+            _ => {
+                return match field {
+                    Field::JiraKey => Ok(TaskPredicate::HasJiraKey),
+                    Field::Remaining => Ok(TaskPredicate::HasRemainingTime),
+                    _ => Err(QueryError {
+                        position: field_position,
+                        message: format!("`{}` has to be compared", field.get_name()),
+                    }),
+                }
+            }
+        };
+        let value_position = self.position();
+        let value = self.parse_value()?;
+        let invalid = |message: String| {
+            Err(QueryError {
+                position: value_position,
+                message,
+            })
+        };
+        let unsupported = || {
+            Err(QueryError {
+                position: field_position,
+                message: format!(
+                    "`{}` cannot be used with `{}`",
+                    field.get_name(),
+                    operator.to_uppercase()
+                ),
+            })
+        };
+        let negate = |predicate: TaskPredicate| match operator {
+            "!=" => !predicate,
+            _ => predicate,
+        };
+
+        match (field, operator) {
+            (Field::Status, "=" | "!=") => match Status::from_name(&value) {
+                Some(status) => Ok(negate(TaskPredicate::Status(status))),
+                None => invalid(format!(
+                    "unknown status `{}`, it is one of To Do, In Progress, Blocked or Done",
+                    value
+                )),
+            },
+            (Field::IssueType, "=" | "!=") => match IssueType::from_name(&value) {
+                Some(issue_type) => Ok(negate(TaskPredicate::IssueType(issue_type))),
+                None => invalid(format!(
+                    "unknown type `{}`, it is one of Story, Task, Sub-task or Epic",
+                    value
+                )),
+            },
+            (Field::Priority, "contains") => unsupported(),
+            (Field::Priority, _) => {
+                let Some(number) = value.parse::<u8>().ok().filter(|x| (1..=5).contains(x)) else {
+                    return invalid(format!(
+                        "`priority` is compared with a number from 1 to 5, found `{}`",
+                        value
+                    ));
+                };
+                // The numbers matching, 1 being the highest priority
+                let (first, last) = match operator {
+                    "<" => (1, number - 1),
+                    "<=" => (1, number),
+                    ">" => (number + 1, 5),
+                    ">=" => (number, 5),
+                    _ => (number, number),
+                };
+                let predicate = match (Priority::from_number(last), Priority::from_number(first)) {
+                    (Some(lowest), Some(highest)) if first <= last => {
+                        TaskPredicate::Priority(lowest..=highest)
+                    }
+                    // Nothing matches
+                    _ => TaskPredicate::Or(Vec::new()),
+                };
+                Ok(negate(predicate))
+            }
+            (Field::Due, "contains") => unsupported(),
+            (Field::Due, _) => {
+                let date = match value.to_lowercase().as_str() {
+                    "today" => Some(self.today),
+                    "tomorrow" => self.today.next_day(),
+                    "yesterday" => self.today.previous_day(),
+                    _ => time::Date::parse(
+                        &value,
+                        time::macros::format_description!("[year]-[month]-[day]"),
+                    )
+                    .ok(),
+                };
+                let Some(date) = date else {
+                    return invalid(format!(
+                        "`due` is compared with a date like 2024-03-01 or today, found `{}`",
+                        value
+                    ));
+                };
+                let before = |x: Option<time::Date>| {
+                    x.map_or(TaskPredicate::And(Vec::new()), TaskPredicate::DueBefore)
+                };
+                let after = |x: Option<time::Date>| {
+                    x.map_or(TaskPredicate::And(Vec::new()), TaskPredicate::DueAfter)
+                };
+                Ok(match operator {
+                    "<" => TaskPredicate::DueBefore(date),
+                    "<=" => before(date.next_day()),
+                    ">" => TaskPredicate::DueAfter(date),
+                    ">=" => after(date.previous_day()),
+                    _ => negate(after(date.previous_day()) & before(date.next_day())),
+                })
+            }
+            (Field::Tags, "contains") => {
+                Ok(TaskPredicate::Tag(value.trim_start_matches('#').to_owned()))
+            }
+            (Field::Sprints, "contains") => {
+                Ok(TaskPredicate::InSprint(Sprint::from(value.as_str())))
+            }
+            (Field::Name, "contains") => Ok(TaskPredicate::TitleContains(value)),
+            (Field::Remaining, ">") if value == "0" => Ok(TaskPredicate::HasRemainingTime),
+            (Field::Remaining, "=") if value == "0" => Ok(!TaskPredicate::HasRemainingTime),
+            (Field::Remaining, _) => invalid(String::from(
+                "`remaining` is only compared with `> 0` or `= 0`",
+            )),
+            _ => unsupported(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Field, Query};
+    use crate::obsidian::task_file::TaskFile;
+    use time::macros::date;
+
+    fn task(path: &str, properties: &str) -> TaskFile {
+        let text = format!("---\n{}\n---\n", properties);
+        TaskFile::parse(std::path::Path::new(path), &text).unwrap()
+    }
+
+    #[test]
+    fn parses_dataview_queries() {
+        let tasks = [
+            task(
+                "Projects/Report.md",
+                "priority: 3\nstatus: In Progress\ndue date: 2024-03-01\nsprints:\n  - Y24W10",
+            ),
+            task(
+                "Projects/Api/Login.md",
+                "priority: 1\nstatus: To Do\ndue date: 2024-03-08\nsprints:\n  - Y24W10\ntags: [bug]",
+            ),
+            task("Projects/Api/Docs.md", "priority: 2\nstatus: Done\nsprints:\n  - Y24W10"),
+            task("Projects/Idea.md", "priority: 5\nstatus: To Do"),
+        ];
+        let today = date!(2024 - 03 - 05);
+        let names = |query: &str| {
+            let query = Query::parse_on(query, today).unwrap();
+            tasks
+                .iter()
+                .filter(|x| query.predicate.as_ref().is_none_or(|p| p.matches(x)))
+                .map(|x| x.get_name())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            names(
+                r#"FROM "Projects" WHERE status != "Done" AND sprints CONTAINS "Y24W10" SORT priority"#
+            ),
+            ["Report", "Login"]
+        );
+        assert_eq!(names("where priority <= 2"), ["Login", "Docs"]);
+        assert_eq!(
+            names("WHERE priority > 4 OR tags contains #bug"),
+            ["Login", "Idea"]
+        );
+        assert_eq!(names("WHERE due < date(today)"), ["Report"]);
+        assert_eq!(
+            names("WHERE due >= 2024-03-08 and not (status = 'done')"),
+            ["Login"]
+        );
+        assert_eq!(names("WHERE name CONTAINS 'log' OR NOT remaining").len(), 4);
+
+        let query = Query::parse_on(
+            r#"FROM "Projects" WHERE status != "Done" SORT priority DESC GROUP BY folder"#,
+            today,
+        )
+        .unwrap();
+        assert_eq!(query.get_group_by(), Some(Field::Folder));
+        let open: Vec<TaskFile> = tasks
+            .into_iter()
+            .filter(|x| query.predicate.as_ref().unwrap().matches(x))
+            .collect();
+        let groups = query.arrange(open);
+        let groups: Vec<(String, Vec<String>)> = groups
+            .into_iter()
+            .map(|x| {
+                (
+                    x.key.unwrap(),
+                    x.tasks.iter().map(|x| x.get_name()).collect(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            groups,
+            [
+                (
+                    String::from("Projects"),
+                    vec![String::from("Idea"), String::from("Report")]
+                ),
+                (String::from("Projects/Api"), vec![String::from("Login")]),
+            ]
+        );
+    }
+
+    #[test]
+    fn points_at_mistakes() {
+        let error = |query: &str| Query::parse_on(query, date!(2024 - 03 - 05)).unwrap_err();
+        assert_eq!(error("WHERE stat = 'Done'").position, 6);
+        assert_eq!(
+            error("WHERE status = 'Finished'").message,
+            "unknown status `Finished`, it is one of To Do, In Progress, Blocked or Done"
+        );
+        assert_eq!(error("WHERE priority = 7").position, 17);
+        assert_eq!(
+            error("WHERE tags = 'x'").message,
+            "`tags` cannot be used with `=`"
+        );
+        assert_eq!(error("WHERE (jira").message, "expected `)`");
+        assert_eq!(
+            error("FROM Projects").to_string(),
+            "at character 6: FROM is followed by a folder in quotes or a #tag"
+        );
+        assert_eq!(error("WHERE name contains 'x").position, 20);
+        assert_eq!(
+            error("SORT priority LIMIT 3").message,
+            "expected WHERE, SORT or GROUP BY"
+        );
+    }
+}
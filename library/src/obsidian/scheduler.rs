@@ -0,0 +1,268 @@
+use std::path::Path;
+
+use time::macros::time;
+use time::{Duration, Time};
+
+use crate::commons::{Date, Priority, Status};
+use crate::context::Context;
+
+use super::plan_check::find_free_time;
+use super::planner::{
+    append_to_day_plan, daily_note_path, read_day_plan, write_time, PlanEditError, PlanFormat,
+    PlannedTask, WorkingHours,
+};
+use super::wikilink::{note_id, Wikilink};
+use super::SprintTimeBalance;
+
+// Free time shorter than this is left free, unless it finishes a task
+pub const MIN_BLOCK: Duration = Duration::minutes(15);
+
+// When the config has none
+pub const DEFAULT_WORKING_HOURS: WorkingHours = WorkingHours::new(time!(9:00), time!(17:00));
+
+// A task that still needs time in the sprint
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkItem {
+    // What goes between the brackets of the link
+    pub link: String,
+    pub needed: Duration,
+    pub priority: Priority,
+    pub due_date: Option<time::Date>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProposedBlock {
+    pub start: Time,
+    pub end: Time,
+    pub link: String,
+}
+
+impl ProposedBlock {
+    // The line of the daily note, like `- [ ] 9:00 - 10:30 [[Write the report]]`
+    pub fn to_line(&self, format: PlanFormat) -> String {
+        format!(
+            "- [ ] {} - {} [[{}]]",
+            write_time(self.start, format),
+            write_time(self.end, format),
+            self.link
+        )
+    }
+}
+
+/*
+    Fills the free time of a day with the work left:
+    ~ the free time is the working hours without the blocks already planned,
+      which are never moved
+    ~ the tasks due first go first, then the ones of the highest priority, and
+      the order given for the rest
+    ~ a task is split over the free time until it has what it needs
+    ~ the day stops at the daily maximum, the blocks already planned included
+*/
+pub fn schedule_day(
+    items: &[WorkItem],
+    planned: &[PlannedTask],
+    hours: &WorkingHours,
+    daily_max: Option<Duration>,
+) -> Vec<ProposedBlock> {
+    let mut free_time = find_free_time(planned, hours, MIN_BLOCK);
+    let planned_time: Duration = planned
+        .iter()
+        .map(|x| x.get_duration())
+        .filter(|x| !x.is_negative())
+        .sum();
+    let mut budget = daily_max.map_or(Duration::DAY, |x| x - planned_time);
+
+    let mut items: Vec<&WorkItem> = items.iter().filter(|x| x.needed.is_positive()).collect();
+    items.sort_by_key(|x| {
+        (
+            x.due_date.is_none(),
+            x.due_date,
+            std::cmp::Reverse(x.priority),
+        )
+    });
+
+    let mut blocks = Vec::new();
+    'items: for item in items {
+        let mut needed = item.needed;
+        while needed.is_positive() {
+            let Some((start, end)) = free_time.first_mut() else {
+                break 'items;
+            };
+            let length = needed.min(*end - *start).min(budget);
+            if length < MIN_BLOCK && length < needed {
+                if length == budget {
+                    break 'items;
+                }
+                free_time.remove(0);
+                continue;
+            }
+            blocks.push(ProposedBlock {
+                start: *start,
+                end: *start + length,
+                link: item.link.clone(),
+            });
+            *start += length;
+            needed -= length;
+            budget -= length;
+            if *end - *start < MIN_BLOCK {
+                free_time.remove(0);
+            }
+        }
+    }
+    blocks
+}
+
+/*
+    The blocks to add to the plan of a day for the sprint of its week. A task
+    needs its remaining time less what is already planned for it in the sprint,
+    so proposing twice adds nothing. The tasks done or blocked are left out.
+*/
+pub fn propose_day_plan(context: &Context, date: &Date) -> Vec<ProposedBlock> {
+    let settings = context.get_config().get_planner();
+    let (year, week, _) = date.get_date().to_iso_week_date();
+    let balance = SprintTimeBalance::new(context, year, week);
+
+    let resolver = context.get_note_resolver();
+    let note_path = daily_note_path(context, date);
+    let folder = note_path.parent().unwrap_or(Path::new(""));
+
+    let items: Vec<WorkItem> = balance
+        .tasks
+        .iter()
+        .filter_map(|x| {
+            let needed = x.remaining_time - x.completed_time - x.uncompleted_time;
+            let path = x.path.as_ref().filter(|_| needed.is_positive())?;
+            let task = context
+                .get_index()
+                .get_task(context.get_vault_path(), path)?;
+            if matches!(task.get_status(), Status::Done | Status::Blocked) {
+                return None;
+            }
+            // The name alone when it finds the note from the daily note
+            let link = match resolver.resolve(&Wikilink::parse(&x.name), folder) {
+                Some(found) if found == path.as_path() => x.name.clone(),
+                _ => note_id(path),
+            };
+            Some(WorkItem {
+                link,
+                needed,
+                priority: task.get_priority(),
+                due_date: task.get_due_date().map(|x| x.get_date()),
+            })
+        })
+        .collect();
+
+    let planned = read_day_plan(context, date).unwrap_or_default();
+    schedule_day(
+        &items,
+        &planned,
+        settings
+            .get_working_hours()
+            .unwrap_or(&DEFAULT_WORKING_HOURS),
+        settings.get_daily_max(),
+    )
+}
+
+// Adds the blocks to the plan in the daily note, see `append_to_day_plan`
+pub fn write_day_plan(
+    context: &Context,
+    date: &Date,
+    blocks: &[ProposedBlock],
+) -> Result<(), PlanEditError> {
+    let format = context.get_config().get_planner().get_format();
+    let lines: Vec<String> = blocks.iter().map(|x| x.to_line(format)).collect();
+    append_to_day_plan(context, date, &lines)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::obsidian::planner::{parse_day_plan, PlannerSettings};
+
+    #[test]
+    fn fills_the_free_time() {
+        let date = Date::from("2024-03-05");
+        let settings = PlannerSettings::new(PlanFormat::Standard, &[]);
+        let planned = parse_day_plan(
+            "- [ ] 10:00 - 11:00 Standup\n- [ ] 11:10 - 12:00 Review\n",
+            &date,
+            &settings,
+        );
+        let item = |link: &str, minutes, priority, due_date: Option<&str>| WorkItem {
+            link: link.to_owned(),
+            needed: Duration::minutes(minutes),
+            priority,
+            due_date: due_date.map(|x| Date::from(x).get_date()),
+        };
+        let items = [
+            item("Docs", 120, Priority::VeryHigh, None),
+            item("Release", 90, Priority::Low, Some("2024-03-06")),
+            item("Cleanup", 60, Priority::Low, None),
+        ];
+        let hours = WorkingHours::new(time!(9:00), time!(17:00));
+        let blocks = schedule_day(
+            &items,
+            planned.get_tasks(),
+            &hours,
+            Some(Duration::hours(6)),
+        );
+        let lines: Vec<String> = blocks
+            .iter()
+            .map(|x| x.to_line(PlanFormat::Standard))
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                "- [ ] 9:00 - 10:00 [[Release]]",
+                "- [ ] 12:00 - 12:30 [[Release]]",
+                "- [ ] 12:30 - 14:30 [[Docs]]",
+                "- [ ] 14:30 - 15:10 [[Cleanup]]",
+            ]
+        );
+        assert_eq!(
+            blocks[0].to_line(PlanFormat::DayPlanner),
+            "- [ ] 09:00 - 10:00 [[Release]]"
+        );
+    }
+
+    #[test]
+    fn proposes_a_day_plan() {
+        use crate::obsidian::scheduler::{propose_day_plan, write_day_plan};
+        use crate::test_vault::{context_for, temporary_vault};
+        let vault = temporary_vault("scheduler");
+        std::fs::write(
+            vault.join("Projects/Write the report.md"),
+            "---\npriority: 2\nstatus: In Progress\nsprints:\n  - Y24W10\nremaining time: '3:00'\n---\n",
+        )
+        .unwrap();
+        std::fs::write(
+            vault.join("Projects/Fix the login.md"),
+            "---\npriority: 1\nstatus: To Do\nsprints:\n  - Y24W10\nremaining time: '1:00'\n---\n",
+        )
+        .unwrap();
+        std::fs::write(
+            vault.join("Projects/Old bug.md"),
+            "---\npriority: 1\nstatus: Done\nsprints:\n  - Y24W10\nremaining time: '2:00'\n---\n",
+        )
+        .unwrap();
+        std::fs::write(
+            vault.join("Daily/2024-03-04.md"),
+            "- [x] 9:00 - 10:00 [[Write the report]]\n",
+        )
+        .unwrap();
+        let note = "# Tuesday\n\n## Plan\n- [ ] 10:00 - 11:00 Standup\n\n## Log\n- [ ] Call back\n";
+        std::fs::write(vault.join("Daily/2024-03-05.md"), note).unwrap();
+
+        let context = context_for(&vault);
+        let date = crate::commons::Date::from("2024-03-05");
+        let blocks = propose_day_plan(&context, &date);
+        write_day_plan(&context, &date, &blocks).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(vault.join("Daily/2024-03-05.md")).unwrap(),
+            "# Tuesday\n\n## Plan\n- [ ] 10:00 - 11:00 Standup\n- [ ] 9:00 - 10:00 [[Fix the login]]\n- [ ] 11:00 - 13:00 [[Write the report]]\n\n## Log\n- [ ] Call back\n"
+        );
+
+        // What was written counts as planned
+        assert!(propose_day_plan(&context, &date).is_empty());
+    }
+}
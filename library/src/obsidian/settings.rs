@@ -0,0 +1,230 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::Deserialize;
+
+/*
+    Settings Obsidian keeps in the `.obsidian` folder of the vault, used as the
+    defaults of the config so they do not have to be written twice. A missing
+    or unreadable file is the same as an empty one, like Obsidian does.
+
+    ~ daily-notes.json    folder, date format and template of the daily notes
+    ~ templates.json      folder of the templates
+    ~ app.json            where new notes and attachments go, excluded files
+    ~ core-plugins.json   which core plugins are enabled
+*/
+
+// .obsidian/daily-notes.json, empty values mean Obsidian's defaults
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct DailyNotesSettings {
+    #[serde(default)]
+    pub folder: String,
+    #[serde(default)]
+    pub format: String,
+    #[serde(default)]
+    pub template: String,
+}
+
+impl DailyNotesSettings {
+    pub fn read(vault_path: &Path) -> Self {
+        read_json(&vault_path.join(".obsidian").join("daily-notes.json"))
+    }
+
+    pub fn get_format(&self) -> Option<&str> {
+        Some(self.format.trim()).filter(|x| !x.is_empty())
+    }
+}
+
+// .obsidian/templates.json
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct TemplatesSettings {
+    #[serde(default)]
+    pub folder: String,
+}
+
+// .obsidian/app.json, only the settings about files
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppSettings {
+    // "root", "current" (folder of the open note) or "folder"
+    #[serde(default)]
+    pub new_file_location: String,
+    #[serde(default)]
+    pub new_file_folder_path: String,
+    // "/" is the root, "./" and below are relative to each note
+    #[serde(default)]
+    pub attachment_folder_path: String,
+    // "Excluded files" in the settings
+    #[serde(default)]
+    pub user_ignore_filters: Vec<String>,
+}
+
+// Older versions list the enabled plugins, newer ones map every plugin to whether it is enabled
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum CorePlugins {
+    Enabled(Vec<String>),
+    All(BTreeMap<String, bool>),
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct VaultSettings {
+    pub daily_notes: DailyNotesSettings,
+    pub templates: TemplatesSettings,
+    pub app: AppSettings,
+    core_plugins: Option<CorePlugins>,
+}
+
+impl VaultSettings {
+    pub fn read(vault_path: &Path) -> Self {
+        let folder = vault_path.join(".obsidian");
+        Self {
+            daily_notes: DailyNotesSettings::read(vault_path),
+            templates: read_json(&folder.join("templates.json")),
+            app: read_json(&folder.join("app.json")),
+            core_plugins: read_json(&folder.join("core-plugins.json")),
+        }
+    }
+
+    // Without core-plugins.json, plugins are assumed to be enabled
+    pub fn is_core_plugin_enabled(&self, plugin: &str) -> bool {
+        match &self.core_plugins {
+            None => true,
+            Some(CorePlugins::Enabled(plugins)) => plugins.iter().any(|x| x == plugin),
+            Some(CorePlugins::All(plugins)) => plugins.get(plugin).copied().unwrap_or(false),
+        }
+    }
+
+    // Relative to the vault, empty for its root
+    pub fn get_daily_notes_folder(&self) -> Option<&str> {
+        self.is_core_plugin_enabled("daily-notes")
+            .then(|| trim_folder(&self.daily_notes.folder))
+    }
+
+    pub fn get_daily_note_format(&self) -> Option<&str> {
+        self.is_core_plugin_enabled("daily-notes")
+            .then(|| self.daily_notes.get_format())
+            .flatten()
+    }
+
+    pub fn get_templates_folder(&self) -> Option<&str> {
+        Some(trim_folder(&self.templates.folder))
+            .filter(|x| !x.is_empty() && self.is_core_plugin_enabled("templates"))
+    }
+
+    // Only when new notes go to a given folder
+    pub fn get_new_note_folder(&self) -> Option<&str> {
+        (self.app.new_file_location == "folder")
+            .then(|| trim_folder(&self.app.new_file_folder_path))
+    }
+
+    // Only when attachments go to a given folder, not next to each note or at the root
+    pub fn get_attachment_folder(&self) -> Option<&str> {
+        let folder = self.app.attachment_folder_path.trim();
+        if folder.starts_with("./") || folder == "." {
+            return None;
+        }
+        Some(trim_folder(folder)).filter(|x| !x.is_empty())
+    }
+
+    pub fn get_ignore_filters(&self) -> &[String] {
+        &self.app.user_ignore_filters
+    }
+}
+
+// Excluded files are path prefixes, or regular expressions between slashes like `/\.excalidraw/`
+pub fn is_ignored(filter: &str, relative_path: &str) -> bool {
+    let filter = filter.trim();
+    if filter.len() > 2 && filter.starts_with('/') && filter.ends_with('/') {
+        return regex::Regex::new(&filter[1..filter.len() - 1])
+            .is_ok_and(|x| x.is_match(relative_path));
+    }
+    !filter.is_empty()
+        && (relative_path.starts_with(filter) || format!("{}/", relative_path).starts_with(filter))
+}
+
+fn trim_folder(folder: &str) -> &str {
+    folder.trim().trim_matches('/')
+}
+
+fn read_json<T: Default + for<'de> Deserialize<'de>>(path: &Path) -> T {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use super::VaultSettings;
+
+    #[test]
+    fn reads_the_obsidian_folder() {
+        let vault = crate::test_vault::temporary_vault("settings");
+        let folder = vault.join(".obsidian");
+        std::fs::create_dir_all(&folder).unwrap();
+        std::fs::write(
+            folder.join("daily-notes.json"),
+            r#"{"folder": "Journal/", "format": "YYYY/YYYY-MM-DD"}"#,
+        )
+        .unwrap();
+        std::fs::write(folder.join("templates.json"), r#"{"folder": "/Templates"}"#).unwrap();
+        std::fs::write(
+            folder.join("app.json"),
+            r#"{"newFileLocation": "folder", "newFileFolderPath": "Inbox", "attachmentFolderPath": "./assets", "userIgnoreFilters": ["Archive/"]}"#,
+        )
+        .unwrap();
+        std::fs::write(
+            folder.join("core-plugins.json"),
+            r#"{"daily-notes": true, "templates": false}"#,
+        )
+        .unwrap();
+
+        let settings = VaultSettings::read(&vault);
+        assert_eq!(settings.get_daily_notes_folder(), Some("Journal"));
+        assert_eq!(settings.get_daily_note_format(), Some("YYYY/YYYY-MM-DD"));
+        assert_eq!(settings.get_templates_folder(), None);
+        assert_eq!(settings.get_new_note_folder(), Some("Inbox"));
+        assert_eq!(settings.get_attachment_folder(), None);
+        assert_eq!(settings.get_ignore_filters(), ["Archive/"]);
+        assert!(super::is_ignored("Archive/", "Archive"));
+        assert!(super::is_ignored("Archive/", "Archive/Old.md"));
+        assert!(!super::is_ignored("Archive/", "Archived.md"));
+        assert!(super::is_ignored(
+            "/\\.excalidraw/",
+            "Drawings/Plan.excalidraw.md"
+        ));
+
+        std::fs::write(folder.join("core-plugins.json"), r#"["templates"]"#).unwrap();
+        let settings = VaultSettings::read(&vault);
+        assert_eq!(settings.get_daily_notes_folder(), None);
+        assert_eq!(settings.get_templates_folder(), Some("Templates"));
+    }
+
+    #[test]
+    fn finds_daily_notes_with_the_obsidian_format() {
+        use crate::test_vault::{context_for, temporary_vault};
+        let vault = temporary_vault("daily-format");
+        std::fs::create_dir_all(vault.join(".obsidian")).unwrap();
+        std::fs::write(
+            vault.join(".obsidian/daily-notes.json"),
+            r#"{"folder": "Daily", "format": "YYYY/MM/YYYY-MM-DD ddd"}"#,
+        )
+        .unwrap();
+        std::fs::create_dir_all(vault.join("Daily/2024/03")).unwrap();
+        std::fs::write(
+            vault.join("Daily/2024/03/2024-03-05 Tue.md"),
+            "- [ ] 9:00 - 10:00 Planning\n",
+        )
+        .unwrap();
+
+        let context = context_for(&vault);
+        assert_eq!(
+            context.get_config().get_daily_note_format(),
+            "YYYY/MM/YYYY-MM-DD ddd"
+        );
+        let date = crate::commons::Date::from("2024-03-05");
+        let tasks = crate::obsidian::planner::read_day_plan(&context, &date).unwrap();
+        assert_eq!(tasks.len(), 1);
+    }
+}
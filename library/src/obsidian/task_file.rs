@@ -1,54 +1,101 @@
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fs::read_dir;
 use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::OnceLock;
 
 use std::fs::read_to_string;
 
-use crate::commons::{Date, Priority, Sprint, Status, TimeEstimate};
-use crate::jira::TimeTrackingJira;
+use crate::commons::{Date, IssueType, Priority, Sprint, Status, TimeEstimate};
+use crate::context::Context;
+use crate::jira::{JiraKey, TimeTrackingJira};
+
+use super::glob::Glob;
+use super::predicate::TaskPredicate;
+use super::settings;
+use super::wikilink::Wikilink;
 
 use serde;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde_yaml;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct TaskFile {
     path: PathBuf,
-    content: String,
+    // The text after the properties, read from the note the first time it is
+    // asked for when the task comes from the index
+    content: OnceLock<String>,
     properties: TaskProperties,
 }
 
 impl TaskFile {
-    pub fn read<P: AsRef<Path>>(path: P) -> Self {
+    pub fn read<P: AsRef<Path>>(context: &Context, path: P) -> Self {
+        Self::try_read(context, path).unwrap()
+    }
+
+    // Fails on notes that cannot be read or have no task properties
+    pub fn try_read<P: AsRef<Path>>(context: &Context, path: P) -> Result<Self, String> {
         let mut complete_path = PathBuf::new();
-        complete_path.push(crate::config::CONFIG.get_vault_path());
+        complete_path.push(context.get_vault_path());
         complete_path.push(&path);
         complete_path.set_extension("md");
 
-        let full_content = read_to_string(&complete_path).unwrap();
-        let _has_properties = full_content.starts_with("---");
+        let full_content = read_to_string(&complete_path)
+            .map_err(|error| format!("{}: {}", complete_path.display(), error))?;
+        Self::parse(path.as_ref(), &full_content)
+            .map_err(|error| format!("{}: {}", complete_path.display(), error))
+    }
+
+    // From the text of the note, `path` is relative to the vault
+    pub(crate) fn parse(path: &Path, full_content: &str) -> Result<Self, String> {
+        if !full_content.starts_with("---") {
+            return Err(String::from("no properties"));
+        }
 
-        let end_of_properties = full_content[3..].find("---").unwrap() + 3;
+        let end_of_properties = full_content[3..]
+            .find("---")
+            .ok_or_else(|| String::from("properties are not closed"))?
+            + 3;
 
-        let properties: TaskProperties =
-            serde_yaml::from_str(&full_content[3..end_of_properties]).unwrap();
+        let properties: TaskProperties = serde_yaml::from_str(&full_content[3..end_of_properties])
+            .map_err(|error| error.to_string())?;
 
-        let content: String = full_content[(end_of_properties + 5)..].to_owned();
+        let after_properties = &full_content[(end_of_properties + 3)..];
+        let content: String = after_properties
+            .strip_prefix("\r\n")
+            .or_else(|| after_properties.strip_prefix('\n'))
+            .unwrap_or(after_properties)
+            .to_owned();
 
-        return TaskFile {
-            path: path.as_ref().to_owned(),
-            content,
+        Ok(TaskFile {
+            path: path.to_owned(),
+            content: OnceLock::from(content),
             properties,
-        };
+        })
     }
 
-    pub fn save(&self) -> Result<(), ()> {
+    // Without the text, see `get_content`
+    pub(crate) fn from_properties(path: &Path, properties: TaskProperties) -> Self {
+        TaskFile {
+            path: path.to_owned(),
+            content: OnceLock::new(),
+            properties,
+        }
+    }
+
+    pub(crate) fn into_properties(self) -> TaskProperties {
+        self.properties
+    }
+
+    pub fn save(&self, context: &Context) -> std::io::Result<()> {
         let mut complete_path = PathBuf::new();
-        complete_path.push(crate::config::CONFIG.get_vault_path());
+        complete_path.push(context.get_vault_path());
         complete_path.push(&self.path);
         complete_path.set_extension("md");
 
-        let mut file = std::fs::File::create(complete_path).unwrap();
+        let mut file = std::fs::File::create(complete_path)?;
         let mut content = String::new();
         content.push_str("---\n");
         content.push_str(&format!(
@@ -56,16 +103,97 @@ impl TaskFile {
             serde_yaml::to_string(&self.properties).unwrap()
         ));
         content.push_str("\n---\n");
+        content.push_str(self.get_content(context));
 
-        file.write(content.as_bytes()).unwrap();
-
-        Ok(())
+        file.write_all(content.as_bytes())
     }
 
     pub fn get_name(&self) -> String {
         self.path.file_stem().unwrap().to_str().unwrap().to_owned()
     }
 
+    // Empty when the note cannot be read anymore
+    pub fn get_content(&self, context: &Context) -> &str {
+        self.content.get_or_init(|| {
+            Self::try_read(context, &self.path)
+                .ok()
+                .and_then(|x| x.content.into_inner())
+                .unwrap_or_default()
+        })
+    }
+
+    pub fn set_content(&mut self, content: String) {
+        self.content = OnceLock::from(content);
+    }
+
+    pub fn get_path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn get_jira_key(&self) -> Option<&JiraKey> {
+        self.properties.jira_key.as_ref()
+    }
+
+    pub fn get_priority(&self) -> Priority {
+        self.properties.priority
+    }
+
+    pub fn get_status(&self) -> Status {
+        self.properties.status
+    }
+
+    pub fn get_issue_type(&self) -> Option<IssueType> {
+        self.properties.issue_type
+    }
+
+    pub fn get_due_date(&self) -> Option<&Date> {
+        self.properties.due_date.as_ref()
+    }
+
+    pub fn get_sprints(&self) -> &[Sprint] {
+        &self.properties.sprints
+    }
+
+    // Without the `#`
+    pub fn get_tags(&self) -> &[String] {
+        &self.properties.tags
+    }
+
+    // Other names of the note, from the `aliases` property
+    pub fn get_aliases(&self) -> &[String] {
+        &self.properties.aliases
+    }
+
+    // Whether the note is called that way, by its name or an alias
+    pub fn is_named(&self, name: &str) -> bool {
+        self.get_name() == name || self.get_aliases().iter().any(|x| x == name)
+    }
+
+    // Posts the comments written in the note and mirrors the ones in Jira
+    pub fn sync_jira_comments(&mut self, context: &Context) -> Result<(), crate::jira::JiraError> {
+        let Some(key) = self.get_jira_key().cloned() else {
+            return Ok(());
+        };
+
+        for comment in super::comments::take_new_comments(self.get_content(context)) {
+            crate::jira::add_comment(context, &key, &comment)?;
+            // Posted or queued, a failure on the next one must not post it again
+            self.set_content(super::comments::remove_new_comment(
+                self.get_content(context),
+                &comment,
+            ));
+            self.save(context)?;
+        }
+
+        let section = super::comments::render_section(&crate::jira::get_comments(context, &key)?);
+        self.set_content(super::comments::replace_section(
+            self.get_content(context),
+            &section,
+        ));
+        self.save(context)?;
+        Ok(())
+    }
+
     pub fn get_remaining_time(&self) -> time::Duration {
         self.properties
             .time_tracking
@@ -75,21 +203,36 @@ impl TaskFile {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct TaskProperties {
     #[serde(deserialize_with = "Priority::deserialize_from_number")]
     #[serde(serialize_with = "Priority::serialize_to_number")]
     priority: Priority,
     status: Status,
-    // issue_type: crate::commons::IssueType,
+    #[serde(default)]
+    #[serde(rename = "issue type")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    issue_type: Option<IssueType>,
     #[serde(default)]
     #[serde(rename = "due date")]
     #[serde(skip_serializing_if = "Option::is_none")]
     due_date: Option<Date>,
-    // jira_key: crate::jira::JiraKey,
+    #[serde(default)]
+    #[serde(rename = "jira key")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    jira_key: Option<JiraKey>,
     #[serde(default)]
     #[serde(deserialize_with = "deserialize_sprints")]
     sprints: Vec<Sprint>,
+    #[serde(default)]
+    #[serde(deserialize_with = "deserialize_tags")]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    #[serde(default)]
+    #[serde(alias = "alias")]
+    #[serde(deserialize_with = "deserialize_aliases")]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    aliases: Vec<String>,
     #[serde(flatten)]
     time_tracking: TimeTrackingObsidian,
     // #[serde(skip_serializing_if = "Option::is_none")]
@@ -107,7 +250,54 @@ pub fn deserialize_sprints<'de, D: Deserializer<'de>>(
     Ok(Deserialize::deserialize(deserialize).unwrap_or(vec![]))
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+// Obsidian accepts a list as well as a single text separated by commas or spaces
+pub fn deserialize_tags<'de, D: Deserializer<'de>>(
+    deserialize: D,
+) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Tags {
+        List(Vec<String>),
+        Text(String),
+    }
+    let tags = match Deserialize::deserialize(deserialize).ok() {
+        Some(Tags::List(tags)) => tags,
+        Some(Tags::Text(text)) => text
+            .split(|x: char| x == ',' || x.is_whitespace())
+            .map(str::to_owned)
+            .collect(),
+        None => Vec::new(),
+    };
+    Ok(tags
+        .iter()
+        .map(|x| x.trim().trim_start_matches('#').to_owned())
+        .filter(|x| !x.is_empty())
+        .collect())
+}
+
+// A list, or a single text separated by commas since aliases can have spaces
+pub fn deserialize_aliases<'de, D: Deserializer<'de>>(
+    deserialize: D,
+) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Aliases {
+        List(Vec<String>),
+        Text(String),
+    }
+    let aliases = match Deserialize::deserialize(deserialize).ok() {
+        Some(Aliases::List(aliases)) => aliases,
+        Some(Aliases::Text(text)) => text.split(',').map(str::to_owned).collect(),
+        None => Vec::new(),
+    };
+    Ok(aliases
+        .iter()
+        .map(|x| x.trim().to_owned())
+        .filter(|x| !x.is_empty())
+        .collect())
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct TimeTrackingObsidian {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(default)]
@@ -164,18 +354,114 @@ impl<'de> Deserialize<'de> for LinkedFilename {
     }
 }
 
+/*
+    The names and aliases of tasks, for text naming a task without a link. The
+    case is ignored. A name several tasks share names none of them, it is kept
+    with the way it was first written and the notes sharing it.
+*/
+#[derive(Debug, Default)]
+pub struct TaskNames {
+    // Lowercase names to the notes, None for the shared ones
+    notes: HashMap<String, Option<PathBuf>>,
+    shared: BTreeMap<String, (String, BTreeSet<PathBuf>)>,
+}
+
+impl TaskNames {
+    pub fn new<'a, I: IntoIterator<Item = &'a TaskFile>>(tasks: I) -> Self {
+        let mut names = Self::default();
+        for task in tasks {
+            for name in std::iter::once(task.get_name()).chain(task.get_aliases().iter().cloned()) {
+                let key = name.to_lowercase();
+                match names.notes.entry(key.clone()) {
+                    Entry::Vacant(entry) => {
+                        entry.insert(Some(task.path.clone()));
+                    }
+                    Entry::Occupied(mut entry) if entry.get().as_ref() != Some(&task.path) => {
+                        let (_, notes) = names
+                            .shared
+                            .entry(key)
+                            .or_insert_with(|| (name, BTreeSet::new()));
+                        notes.extend(entry.get_mut().take());
+                        notes.insert(task.path.clone());
+                    }
+                    Entry::Occupied(_) => {}
+                }
+            }
+        }
+        names
+    }
+
+    // The note of the only task with this name or alias
+    pub fn find(&self, name: &str) -> Option<&Path> {
+        self.notes.get(&name.to_lowercase())?.as_deref()
+    }
+
+    // The names shared by several tasks, as first written, with their notes
+    pub fn get_shared(&self) -> impl Iterator<Item = (&str, &BTreeSet<PathBuf>)> {
+        self.shared
+            .values()
+            .map(|(name, notes)| (name.as_str(), notes))
+    }
+}
+
+/*
+    Used to turn wikilinks into links to the Jira issue of the note. The note
+    is found like Obsidian does from `source_folder`, or by the alias of a
+    task. A name several notes share, with none of them closer to the link,
+    finds none rather than a wrong issue.
+*/
+pub fn find_jira_key(context: &Context, link: &Wikilink, source_folder: &Path) -> Option<JiraKey> {
+    let resolver = context.get_note_resolver();
+    let path = match resolver.resolve(link, source_folder) {
+        Some(_) => resolver.resolve_unique(link, source_folder)?.to_owned(),
+        None => context.get_task_names().find(&link.path)?.to_owned(),
+    };
+    context
+        .get_index()
+        .get_task(context.get_vault_path(), &path)?
+        .properties
+        .jira_key
+}
+
+/*
+    Finds the task notes under a folder of the vault. Only Markdown files are
+    read, and notes without task properties are left out. Skipped on the way:
+    ~ hidden files and folders, like `.obsidian` and `.trash`
+    ~ the attachments folder of the config
+    ~ the files excluded in the settings of Obsidian
+    ~ whatever matches an exclude glob, or no include glob when there are some
+    ~ the subfolders, unless it is recursive
+    ~ links to notes or folders outside of the vault
+    Folders linked more than once, or linking to themselves, are read once.
+    The tasks found are then kept when they are in one of the sprints and match
+    the predicate, when those are set.
+
+    Folders and notes are read on several threads, which matters on network
+    drives, and the tasks come sorted by path whatever the threads do.
+*/
 pub struct TaskFilter {
     sprints: Option<Vec<Sprint>>,
+    predicate: Option<TaskPredicate>,
     path: PathBuf,
     recursive: bool,
+    include: Vec<Glob>,
+    exclude: Vec<Glob>,
+    max_threads: usize,
 }
 
 impl TaskFilter {
     pub fn new() -> Self {
         Self {
             sprints: None,
+            predicate: None,
             path: PathBuf::new(),
             recursive: false,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            // Reading files waits more than it computes, so a few more than the cores
+            max_threads: std::thread::available_parallelism()
+                .map_or(4, |x| x.get() * 2)
+                .min(16),
         }
     }
 
@@ -184,20 +470,55 @@ impl TaskFilter {
         self
     }
 
+    // Replaces the previous predicate, combine them with `&` to keep both
+    pub fn set_predicate(&mut self, predicate: TaskPredicate) -> &mut Self {
+        self.predicate = Some(predicate);
+        self
+    }
+
     pub fn set_path<P: Into<PathBuf>>(&mut self, path: P) -> &mut Self {
         self.path = path.into();
         self
     }
 
-    pub fn get_tasks(&self) -> Vec<TaskFile> {
-        use crate::config::CONFIG;
-        let mut complete_path = PathBuf::new();
-        complete_path.push(CONFIG.get_vault_path());
-        complete_path.push(self.path.clone());
+    pub fn set_recursive(&mut self, recursive: bool) -> &mut Self {
+        self.recursive = recursive;
+        self
+    }
 
-        let mut output = Vec::new();
-        self.read_directory(complete_path, &mut output).unwrap();
-        output
+    // 1 reads everything on the current thread
+    pub fn set_max_threads(&mut self, max_threads: usize) -> &mut Self {
+        self.max_threads = max_threads.max(1);
+        self
+    }
+
+    // Globs are matched against the path relative to the vault
+    pub fn add_include(&mut self, glob: Glob) -> &mut Self {
+        self.include.push(glob);
+        self
+    }
+
+    pub fn add_exclude(&mut self, glob: Glob) -> &mut Self {
+        self.exclude.push(glob);
+        self
+    }
+
+    pub fn get_tasks(&self, context: &Context) -> Vec<TaskFile> {
+        let notes = self.find_notes(context);
+        let index = context.get_index();
+        let tasks = parallel_map(&notes, self.max_threads, |path| {
+            index.get_task(context.get_vault_path(), path)
+        });
+
+        // Only a complete walk tells which notes are gone
+        if self.recursive && self.include.is_empty() && self.exclude.is_empty() {
+            index.forget_missing(&self.path, &notes.iter().cloned().collect());
+        }
+        tasks
+            .into_iter()
+            .flatten()
+            .filter(|x| self.task_fulfills_criteria(x))
+            .collect()
     }
 
     fn task_fulfills_criteria(&self, task_file: &TaskFile) -> bool {
@@ -210,41 +531,261 @@ impl TaskFilter {
             }
         }
 
-        true
+        self.predicate.as_ref().is_none_or(|x| x.matches(task_file))
     }
 
-    fn read_directory(
-        &self,
-        complete_path: PathBuf,
-        files: &mut Vec<TaskFile>,
-    ) -> Result<(), String> {
-        // println!("{:?}", complete_path);
-        for entry in read_dir(&complete_path).unwrap() {
-            let entry = entry.unwrap();
-            let entry_path = entry.path();
-            if entry.file_type().unwrap().is_dir() {
+    fn is_skipped(&self, context: &Context, relative_path: &str, is_dir: bool) -> bool {
+        let config = context.get_config();
+        let name = relative_path.rsplit('/').next().unwrap_or_default();
+        if name.starts_with('.') {
+            return true;
+        }
+        if is_dir
+            && config
+                .get_attachments_path()
+                .is_some_and(|x| !x.is_empty() && x == relative_path)
+        {
+            return true;
+        }
+        if config
+            .get_ignore_filters()
+            .iter()
+            .any(|x| settings::is_ignored(x, relative_path))
+        {
+            return true;
+        }
+        if self.exclude.iter().any(|x| x.is_match(relative_path)) {
+            return true;
+        }
+        if is_dir {
+            return false;
+        }
+
+        !name.ends_with(".md")
+            || (!self.include.is_empty() && !self.include.iter().any(|x| x.is_match(relative_path)))
+    }
+
+    // Notes to read, relative to the vault and sorted. The folders are read a
+    // level at a time, the ones of a level in parallel.
+    pub fn find_notes(&self, context: &Context) -> Vec<PathBuf> {
+        let Ok(vault) = context.get_vault_path().canonicalize() else {
+            return Vec::new();
+        };
+        let mut visited = HashSet::new();
+        let mut folders = vec![context.get_vault_path().join(&self.path)];
+        let mut notes = Vec::new();
+
+        while !folders.is_empty() {
+            folders.retain(|x| x.canonicalize().is_ok_and(|x| visited.insert(x)));
+            let contents = parallel_map(&folders, self.max_threads, |folder| {
+                self.read_folder(context, &vault, folder)
+            });
+            folders = Vec::new();
+            for (subfolders, folder_notes) in contents {
                 if self.recursive {
-                    self.read_directory(entry_path, files).unwrap();
+                    folders.extend(subfolders);
                 }
-            } else {
-                let reduced_path = entry_path
-                    .strip_prefix(crate::config::CONFIG.get_vault_path())
-                    .unwrap();
+                notes.extend(folder_notes);
+            }
+        }
 
-                let task_file = TaskFile::read(reduced_path);
-                if self.task_fulfills_criteria(&task_file) {
-                    files.push(task_file)
-                }
+        notes.sort();
+        notes
+    }
+
+    // The folders and notes that are not skipped, unreadable folders are left out
+    // instead of failing the whole search
+    fn read_folder(
+        &self,
+        context: &Context,
+        vault: &Path,
+        folder: &Path,
+    ) -> (Vec<PathBuf>, Vec<PathBuf>) {
+        let mut subfolders = Vec::new();
+        let mut notes = Vec::new();
+        let Ok(entries) = read_dir(folder) else {
+            return (subfolders, notes);
+        };
+
+        for entry in entries.flatten() {
+            let entry_path = entry.path();
+            let Ok(reduced_path) = entry_path.strip_prefix(context.get_vault_path()) else {
+                continue;
+            };
+            let relative_path = reduced_path.to_string_lossy().replace('\\', "/");
+            // Follows symbolic links within the vault, to notes as well as folders,
+            // the visited folders keep loops out
+            if entry.file_type().is_ok_and(|x| x.is_symlink())
+                && !entry_path
+                    .canonicalize()
+                    .is_ok_and(|x| x.starts_with(vault))
+            {
+                continue;
+            }
+            let is_dir = entry_path.is_dir();
+            if self.is_skipped(context, &relative_path, is_dir) {
+                continue;
+            }
+
+            if is_dir {
+                subfolders.push(entry_path);
+            } else {
+                notes.push(reduced_path.to_owned());
             }
         }
+        (subfolders, notes)
+    }
+}
 
-        Ok(())
+// Runs `function` on `threads` threads at most, the results keep the order of the items
+fn parallel_map<T: Sync, R: Send>(
+    items: &[T],
+    threads: usize,
+    function: impl Fn(&T) -> R + Sync,
+) -> Vec<R> {
+    let threads = threads.min(items.len());
+    if threads <= 1 {
+        return items.iter().map(function).collect();
     }
+
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<Option<R>> = std::iter::repeat_with(|| None).take(items.len()).collect();
+    std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(item) = items.get(index) else {
+                            return done;
+                        };
+                        done.push((index, function(item)));
+                    }
+                })
+            })
+            .collect();
+        for worker in workers {
+            for (index, result) in worker.join().unwrap() {
+                results[index] = Some(result);
+            }
+        }
+    });
+    results.into_iter().map(Option::unwrap).collect()
 }
 
 #[cfg(test)]
 mod test {
 
+    #[test]
+    fn finds_the_jira_key_of_a_link() {
+        use crate::obsidian::wikilink::Wikilink;
+        use crate::test_vault::{context_for, temporary_vault};
+        use std::path::Path;
+        let vault = temporary_vault("jira-keys");
+        for folder in ["Projects/Web", "Projects/Mobile"] {
+            std::fs::create_dir_all(vault.join(folder)).unwrap();
+        }
+        let task = |key: &str, aliases: &str| {
+            format!(
+                "---\npriority: 2\nstatus: To Do\njira key: {}\naliases: {}\n---\n",
+                key, aliases
+            )
+        };
+        std::fs::write(vault.join("Projects/Web/Release.md"), task("WEB-1", "Ship")).unwrap();
+        std::fs::write(
+            vault.join("Projects/Mobile/Release.md"),
+            task("APP-1", "Ship"),
+        )
+        .unwrap();
+        std::fs::write(vault.join("Projects/Billing.md"), task("PAY-1", "Invoices")).unwrap();
+
+        let context = context_for(&vault);
+        let key = |link: &str| {
+            super::find_jira_key(&context, &Wikilink::parse(link), Path::new("Projects"))
+                .map(|x| x.to_string())
+        };
+        assert_eq!(key("Projects/Web/Release").as_deref(), Some("WEB-1"));
+        assert_eq!(
+            key("Mobile/Release#Notes|the app").as_deref(),
+            Some("APP-1")
+        );
+        assert_eq!(key("invoices").as_deref(), Some("PAY-1"));
+        // Shared by two notes, or by two aliases
+        assert_eq!(key("Release"), None);
+        assert_eq!(key("Ship"), None);
+    }
+
+    #[test]
+    fn scans_nested_project_folders() {
+        use crate::test_vault::{context_for, temporary_vault};
+        let vault = temporary_vault("scan");
+        std::fs::create_dir_all(vault.join(".obsidian")).unwrap();
+        std::fs::write(
+            vault.join(".obsidian/app.json"),
+            r#"{"attachmentFolderPath": "Projects/Files", "userIgnoreFilters": ["Projects/Archive/"]}"#,
+        )
+        .unwrap();
+        let task = "---\npriority: 3\nstatus: To Do\n---\n";
+        for folder in ["Area/Sub", "Archive", "Files", "Drafts", ".trash"] {
+            std::fs::create_dir_all(vault.join("Projects").join(folder)).unwrap();
+        }
+        for note in [
+            "Top.md",
+            "Area/Sub/Deep.md",
+            "Archive/Old.md",
+            "Files/Attached.md",
+            "Drafts/Draft.md",
+            ".trash/Deleted.md",
+        ] {
+            std::fs::write(vault.join("Projects").join(note), task).unwrap();
+        }
+        std::fs::write(vault.join("Projects/Area/No properties.md"), "Just text\n").unwrap();
+        std::fs::write(
+            vault.join("Projects/Area/picture.png"),
+            [0u8, 159, 146, 150],
+        )
+        .unwrap();
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(vault.join("Projects"), vault.join("Projects/Area/Loop"))
+                .unwrap();
+            let outside = temporary_vault("scan-outside");
+            std::fs::write(outside.join("Outside.md"), task).unwrap();
+            std::os::unix::fs::symlink(&outside, vault.join("Projects/Area/Outside")).unwrap();
+            std::os::unix::fs::symlink(
+                outside.join("Outside.md"),
+                vault.join("Projects/Area/Sub/Linked.md"),
+            )
+            .unwrap();
+        }
+
+        let context = context_for(&vault);
+        let names = |filter: &crate::obsidian::task_file::TaskFilter| {
+            filter
+                .get_tasks(&context)
+                .iter()
+                .map(|x| x.get_name())
+                .collect::<Vec<_>>()
+        };
+
+        let mut filter = crate::obsidian::task_file::TaskFilter::new();
+        filter.set_path("Projects");
+        assert_eq!(names(&filter), ["Top"]);
+
+        filter.set_recursive(true);
+        assert_eq!(names(&filter), ["Deep", "Draft", "Top"]);
+
+        filter.add_exclude(crate::obsidian::glob::Glob::new("Drafts").unwrap());
+        assert_eq!(names(&filter), ["Deep", "Top"]);
+
+        filter.add_include(crate::obsidian::glob::Glob::new("Projects/Area/**").unwrap());
+        assert_eq!(names(&filter), ["Deep"]);
+
+        filter.set_recursive(false);
+        assert!(names(&filter).is_empty());
+    }
+
     #[test]
     fn create_file() {
         use super::{TaskFile, TaskProperties, TimeTrackingObsidian};
@@ -252,12 +793,14 @@ mod test {
             priority: crate::commons::Priority::High,
             due_date: Some(crate::commons::Date::from("2024-03-14")),
             status: crate::commons::Status::InProgress,
-            // issue_type: crate::commons::IssueType::Task,
-            // jira_key: crate::jira::JiraKey::new("MB-123"),
+            issue_type: Some(crate::commons::IssueType::Task),
+            jira_key: Some(crate::jira::JiraKey::new("MB-123")),
             sprints: vec![
                 crate::commons::Sprint::new(String::from("Y24W12")),
                 crate::commons::Sprint::new(String::from("Y24W14")),
             ],
+            tags: vec![String::from("backend")],
+            aliases: Vec::new(),
             time_tracking: TimeTrackingObsidian {
                 original: None,
                 spent: None,
@@ -272,40 +815,57 @@ mod test {
         let file = TaskFile {
             path,
             properties,
-            content: String::from("Buenas noches gente"),
+            content: std::sync::OnceLock::from(String::from("Buenas noches gente")),
         };
-        file.save();
+        file.save(crate::context::Context::global());
     }
 
     #[test]
     fn read_file() {
         use super::TaskFile;
-        let file = TaskFile::read("read_file");
+        let file = TaskFile::read(crate::context::Context::global(), "read_file");
         println!("{:#?}", file);
     }
 
     #[test]
     fn get_all_project_tasks() {
         let mut task_filter = super::TaskFilter::new();
-        task_filter.set_path(crate::config::CONFIG.get_project_path());
+        let context = crate::context::Context::global();
+        task_filter.set_path(context.get_config().get_project_path());
 
-        for task in task_filter.get_tasks() {
+        for task in task_filter.get_tasks(context) {
             println!("{:#?}", task);
         }
     }
 
     #[test]
     fn get_sprint_tasks() {
+        let context = crate::context::Context::global();
         let mut task_filter = super::TaskFilter::new();
         task_filter
             .set_sprints(&[crate::commons::Sprint::new("Y24W10".to_owned())])
-            .set_path(crate::config::CONFIG.get_project_path());
+            .set_path(context.get_config().get_project_path());
 
-        for task in task_filter.get_tasks() {
+        for task in task_filter.get_tasks(context) {
             println!("{:#?}", task);
         }
     }
 
+    #[test]
+    fn keeps_the_order_on_several_threads() {
+        let items: Vec<u64> = (0..1000).collect();
+        let squares = |x: &u64| {
+            // Uneven work, so the threads finish out of order
+            std::thread::sleep(std::time::Duration::from_micros(x % 7 * 50));
+            x * x
+        };
+        assert_eq!(
+            super::parallel_map(&items, 8, squares),
+            super::parallel_map(&items, 1, squares)
+        );
+        assert!(super::parallel_map(&[] as &[u64], 8, squares).is_empty());
+    }
+
     fn fmt_time(time: time::Duration, with_symbol: bool) -> String {
         let abs_time: time::Duration;
         if time.is_negative() {
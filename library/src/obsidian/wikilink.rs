@@ -0,0 +1,328 @@
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::context::Context;
+
+use super::task_file::TaskFilter;
+
+/*
+    Links between notes as Obsidian writes them:
+
+        [[Projects/Public API#Endpoints|the API]]
+          ~~~~~~~~~~~~~~~~~~~ ~~~~~~~~~ ~~~~~~~
+          path                heading   alias
+
+    The path can be a name alone or end with a part of the folders, and the
+    heading can be a block instead, like `#^a1b2c3`. `![[...]]` embeds a note.
+*/
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Wikilink {
+    pub path: String,
+    pub heading: Option<String>,
+    pub block: Option<String>,
+    pub alias: Option<String>,
+    pub embed: bool,
+}
+
+impl Wikilink {
+    // From the text between the brackets
+    pub fn parse(inner: &str) -> Self {
+        let (target, alias) = match inner.split_once('|') {
+            Some((target, alias)) => (target, Some(alias.trim().to_owned())),
+            None => (inner, None),
+        };
+        let (path, subpath) = match target.split_once('#') {
+            Some((path, subpath)) => (path, Some(subpath.trim())),
+            None => (target, None),
+        };
+        let (heading, block) = match subpath {
+            Some(subpath) => match subpath.strip_prefix('^') {
+                Some(block) => (None, Some(block.to_owned())),
+                None => (Some(subpath.to_owned()), None),
+            },
+            None => (None, None),
+        };
+        Self {
+            path: path.trim().to_owned(),
+            heading,
+            block,
+            alias,
+            embed: false,
+        }
+    }
+
+    // Name of the note linked, without folders nor extension
+    pub fn get_note_name(&self) -> &str {
+        let name = self.path.rsplit('/').next().unwrap_or_default();
+        name.strip_suffix(".md").unwrap_or(name)
+    }
+
+    // Written back the way Obsidian writes it
+    pub fn to_text(&self) -> String {
+        let mut text = String::from(if self.embed { "![[" } else { "[[" });
+        text.push_str(&self.path);
+        if let Some(heading) = &self.heading {
+            text.push('#');
+            text.push_str(heading);
+        }
+        if let Some(block) = &self.block {
+            text.push_str("#^");
+            text.push_str(block);
+        }
+        if let Some(alias) = &self.alias {
+            text.push('|');
+            text.push_str(alias);
+        }
+        text.push_str("]]");
+        text
+    }
+
+    // What Obsidian shows in place of the link
+    pub fn get_display_text(&self) -> String {
+        match (&self.alias, &self.heading) {
+            (Some(alias), _) => alias.clone(),
+            (None, Some(heading)) => format!("{} > {}", self.get_note_name(), heading),
+            (None, None) => self.get_note_name().to_owned(),
+        }
+    }
+}
+
+// The links of a text, with where each one is written including the brackets
+pub fn find_wikilinks(text: &str) -> Vec<(Range<usize>, Wikilink)> {
+    let mut output = Vec::new();
+    let mut offset = 0;
+    while let Some(start) = text[offset..].find("[[").map(|x| x + offset) {
+        let Some(length) = text[start + 2..].find("]]") else {
+            break;
+        };
+        let end = start + 2 + length + 2;
+        let embed = text[..start].ends_with('!');
+        let mut link = Wikilink::parse(&text[start + 2..end - 2]);
+        link.embed = embed;
+        if !link.path.is_empty() {
+            output.push((if embed { start - 1 } else { start }..end, link));
+        }
+        offset = end;
+    }
+    output
+}
+
+// A note as a link to it refers to it, relative to the vault with `/` and without `.md`
+pub fn note_id(path: &Path) -> String {
+    let text = path.to_string_lossy().replace('\\', "/");
+    let text = text.trim_matches('/');
+    text.strip_suffix(".md").unwrap_or(text).to_owned()
+}
+
+/*
+    Finds the note a link points to like Obsidian does when several notes
+    share a name, in this order:
+    ~ the note in the folder of the note holding the link
+    ~ the note at that exact path from the root of the vault
+    ~ the note with the shortest path, then the first one alphabetically
+    Paths ending the same as the link match, so `[[Area/Task]]` finds
+    `Projects/Area/Task.md`. The case is ignored, like on most systems.
+*/
+#[derive(Debug, Default)]
+pub struct NoteResolver {
+    // Relative to the vault, with their extension
+    notes: Vec<PathBuf>,
+    // Lowercase names to the positions of the notes
+    by_name: HashMap<String, Vec<usize>>,
+}
+
+impl NoteResolver {
+    pub fn new<I: IntoIterator<Item = PathBuf>>(notes: I) -> Self {
+        let mut resolver = Self::default();
+        for note in notes {
+            let name = note_id(&note)
+                .rsplit('/')
+                .next()
+                .unwrap_or_default()
+                .to_lowercase();
+            resolver
+                .by_name
+                .entry(name)
+                .or_default()
+                .push(resolver.notes.len());
+            resolver.notes.push(note);
+        }
+        resolver
+    }
+
+    // Every note of the vault, except the ones skipped by `TaskFilter`
+    pub fn from_vault(context: &Context) -> Self {
+        Self::new(TaskFilter::new().set_recursive(true).find_notes(context))
+    }
+
+    // `source_folder` is the folder of the note with the link, relative to the vault
+    pub fn resolve(&self, link: &Wikilink, source_folder: &Path) -> Option<&Path> {
+        let wanted = note_id(Path::new(&link.path)).to_lowercase();
+        let name = wanted.rsplit('/').next().unwrap_or_default();
+        let source_folder = note_id(source_folder).to_lowercase();
+
+        let candidates: Vec<(String, &PathBuf)> = self
+            .by_name
+            .get(name)?
+            .iter()
+            .map(|x| (note_id(&self.notes[*x]).to_lowercase(), &self.notes[*x]))
+            .filter(|(id, _)| *id == wanted || id.ends_with(&format!("/{}", wanted)))
+            .collect();
+
+        let folder_of = |id: &str| id.rsplit_once('/').map_or("", |x| x.0).to_owned();
+        candidates
+            .iter()
+            .find(|(id, _)| folder_of(id) == source_folder)
+            .or_else(|| candidates.iter().find(|(id, _)| *id == wanted))
+            .or_else(|| {
+                candidates
+                    .iter()
+                    .min_by_key(|(id, path)| (id.matches('/').count(), *path))
+            })
+            .map(|(_, path)| path.as_path())
+    }
+
+    // Like `resolve`, but None when several notes match and none of them is in
+    // `source_folder` or at the exact path of the link
+    pub fn resolve_unique(&self, link: &Wikilink, source_folder: &Path) -> Option<&Path> {
+        let found = self.resolve(link, source_folder)?;
+        let wanted = note_id(Path::new(&link.path)).to_lowercase();
+        let id = note_id(found).to_lowercase();
+        let folder = id.rsplit_once('/').map_or("", |x| x.0);
+        let matching = self.by_name[id.rsplit('/').next().unwrap_or_default()]
+            .iter()
+            .map(|x| note_id(&self.notes[*x]).to_lowercase())
+            .filter(|x| *x == wanted || x.ends_with(&format!("/{}", wanted)))
+            .count();
+        let closest = id == wanted || folder == note_id(source_folder).to_lowercase();
+        (matching == 1 || closest).then_some(found)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_links() {
+        let links = find_wikilinks(
+            "[[Projects/Public API#Endpoints|the API]] then ![[Diagram.png]], [[Task#^a1b2]] and [[]]",
+        );
+        assert_eq!(links.len(), 3);
+        let (range, api) = &links[0];
+        assert_eq!(*range, 0..41);
+        assert_eq!(api.path, "Projects/Public API");
+        assert_eq!(api.heading.as_deref(), Some("Endpoints"));
+        assert_eq!(api.get_display_text(), "the API");
+        assert_eq!(api.get_note_name(), "Public API");
+        assert!(links[1].1.embed);
+        assert_eq!(links[1].0, 47..63);
+        assert_eq!(links[2].1.block.as_deref(), Some("a1b2"));
+        assert_eq!(links[2].1.get_note_name(), "Task");
+        assert_eq!(api.to_text(), "[[Projects/Public API#Endpoints|the API]]");
+        assert_eq!(links[1].1.to_text(), "![[Diagram.png]]");
+        assert_eq!(links[2].1.to_text(), "[[Task#^a1b2]]");
+    }
+
+    #[test]
+    fn resolves_like_obsidian() {
+        let resolver = NoteResolver::new(
+            [
+                "Projects/Area/Deep/Task.md",
+                "Projects/Task.md",
+                "Archive/Task.md",
+                "Daily/Task.md",
+                "Task.md",
+                "Other.md",
+            ]
+            .map(PathBuf::from),
+        );
+        let resolve = |link: &str, folder: &str| {
+            resolver
+                .resolve(&Wikilink::parse(link), Path::new(folder))
+                .map(|x| x.to_str().unwrap().to_owned())
+        };
+        assert_eq!(resolve("Task", "Daily").as_deref(), Some("Daily/Task.md"));
+        assert_eq!(resolve("task", "Journal").as_deref(), Some("Task.md"));
+        assert_eq!(
+            resolve("Deep/Task", "Daily").as_deref(),
+            Some("Projects/Area/Deep/Task.md")
+        );
+        assert_eq!(
+            resolve("Projects/Task.md|alias", "Daily").as_deref(),
+            Some("Projects/Task.md")
+        );
+        assert_eq!(resolve("Missing", "Daily"), None);
+        assert_eq!(resolve("Area/Other", "Daily"), None);
+
+        let unique = |link: &str, folder: &str| {
+            resolver
+                .resolve_unique(&Wikilink::parse(link), Path::new(folder))
+                .map(|x| x.to_str().unwrap().to_owned())
+        };
+        assert_eq!(unique("Task", "Daily").as_deref(), Some("Daily/Task.md"));
+        assert_eq!(unique("Task", "Journal").as_deref(), Some("Task.md"));
+        assert_eq!(
+            unique("Archive/Task", "Journal").as_deref(),
+            Some("Archive/Task.md")
+        );
+        assert_eq!(
+            unique("Deep/Task", "Journal").as_deref(),
+            Some("Projects/Area/Deep/Task.md")
+        );
+        assert_eq!(
+            unique("Area/Deep/Task", "Journal").as_deref(),
+            Some("Projects/Area/Deep/Task.md")
+        );
+
+        // Two notes by that name, neither closer to the link
+        let resolver =
+            NoteResolver::new(["Web/Release.md", "Mobile/Release.md"].map(PathBuf::from));
+        let link = Wikilink::parse("Release");
+        assert!(resolver.resolve(&link, Path::new("Daily")).is_some());
+        assert_eq!(resolver.resolve_unique(&link, Path::new("Daily")), None);
+        assert_eq!(
+            resolver.resolve_unique(&link, Path::new("Web")),
+            Some(Path::new("Web/Release.md"))
+        );
+    }
+
+    #[test]
+    fn credits_the_linked_note() {
+        use crate::test_vault::{context_for, temporary_vault};
+        let vault = temporary_vault("wikilinks");
+        std::fs::create_dir_all(vault.join("Projects/Web")).unwrap();
+        std::fs::create_dir_all(vault.join("Projects/Mobile")).unwrap();
+        let task = "---\npriority: 2\nstatus: To Do\nsprints:\n  - Y24W10\n---\n";
+        std::fs::write(vault.join("Projects/Web/Release.md"), task).unwrap();
+        std::fs::write(vault.join("Projects/Mobile/Release.md"), task).unwrap();
+        std::fs::write(
+            vault.join("Daily/2024-03-05.md"),
+            "- [x] 9:00 - 10:00 [[Mobile/Release|release]] on the stores\n- [ ] 10:00 - 10:30 [[Projects/Mobile/Release#Notes]]\n- [ ] 11:00 - 13:00 [[Web/Release]]\n",
+        )
+        .unwrap();
+
+        let context = context_for(&vault);
+        let balance = crate::obsidian::SprintTimeBalance::new(&context, 2024, 10);
+        let mut times: Vec<_> = balance
+            .tasks
+            .iter()
+            .map(|x| (x.name.as_str(), x.completed_time, x.uncompleted_time))
+            .collect();
+        times.sort();
+        assert_eq!(
+            times,
+            [
+                ("Release", time::Duration::ZERO, time::Duration::hours(2)),
+                (
+                    "Release",
+                    time::Duration::hours(1),
+                    time::Duration::minutes(30)
+                ),
+            ]
+        );
+    }
+}
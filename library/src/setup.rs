@@ -0,0 +1,197 @@
+use std::path::{Path, PathBuf};
+
+use crate::config::{ConfigInner, ConfigProblem};
+use crate::credentials::{Secret, TokenSource};
+use crate::jira::{request, Connection, JiraClient, JiraError, User};
+
+/*
+    The pieces of `init`, which writes a config file from a few answers instead
+    of asking people to write the YAML by hand. Asking the questions is left to
+    the frontends.
+*/
+
+// Accepts the site name as well as its URL, like https://mycompany.atlassian.net/
+pub fn site_name(input: &str) -> String {
+    let input = input.trim();
+    let input = input
+        .strip_prefix("https://")
+        .or_else(|| input.strip_prefix("http://"))
+        .unwrap_or(input);
+    let input = input.split('/').next().unwrap_or_default();
+    input
+        .strip_suffix(".atlassian.net")
+        .unwrap_or(input)
+        .to_owned()
+}
+
+// Returns the user the token belongs to, its account id is the `user_id` of the config
+pub fn verify_credentials(jira_url: &str, user_mail: &str, token: &str) -> Result<User, JiraError> {
+    let mut client = JiraClient::new(Connection::new(
+        &crate::jira::client::base_url(jira_url),
+        user_mail,
+        token,
+    ));
+    // Wrong credentials are not going to get better by waiting
+    client
+        .get_connection_mut()
+        .set_retry_policy(crate::jira::RetryPolicy {
+            max_retries: 0,
+            ..Default::default()
+        });
+    client.send(&request::myself())
+}
+
+// The vault containing the folder, the closest folder with an `.obsidian` folder
+pub fn find_vault(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .find(|x| x.join(".obsidian").is_dir())
+        .map(Path::to_owned)
+}
+
+// Folders of the vault, relative to it and sorted, without the hidden ones
+pub fn list_folders(vault_path: &Path) -> Vec<String> {
+    fn visit(vault_path: &Path, folder: &Path, depth: usize, output: &mut Vec<String>) {
+        let Ok(entries) = std::fs::read_dir(folder) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let hidden = entry.file_name().to_string_lossy().starts_with('.');
+            if hidden || !entry.file_type().is_ok_and(|x| x.is_dir()) {
+                continue;
+            }
+            if let Ok(relative) = path.strip_prefix(vault_path) {
+                output.push(relative.to_string_lossy().replace('\\', "/"));
+            }
+            if depth > 0 {
+                visit(vault_path, &path, depth - 1, output);
+            }
+        }
+    }
+
+    let mut output = Vec::new();
+    visit(vault_path, vault_path, 2, &mut output);
+    output.sort();
+    output
+}
+
+#[derive(Debug, Clone)]
+pub struct NewConfig {
+    pub jira_url: String,
+    pub user_mail: String,
+    pub user_id: String,
+    pub token: Secret,
+    // Where the config will get the token from
+    pub token_source: TokenSource,
+    pub vault_path: PathBuf,
+    pub daily_notes_path: String,
+    pub project_path: String,
+}
+
+impl NewConfig {
+    pub fn to_yaml(&self) -> String {
+        self.to_yaml_with(&self.token_source)
+    }
+
+    fn to_yaml_with(&self, token_source: &TokenSource) -> String {
+        let (token_key, token_value) = match token_source {
+            TokenSource::Plain(token) => ("jira_token", token.expose().to_owned()),
+            TokenSource::Env(variable) => ("token_env", variable.clone()),
+            TokenSource::Command(command) => ("token_command", command.clone()),
+            TokenSource::File(path) => ("token_file", path.to_string_lossy().into_owned()),
+        };
+
+        let mut mapping = serde_yaml::Mapping::new();
+        for (key, value) in [
+            ("jira_url", self.jira_url.clone()),
+            ("user_mail", self.user_mail.clone()),
+            ("user_id", self.user_id.clone()),
+            (token_key, token_value),
+            ("vault_path", self.vault_path.to_string_lossy().into_owned()),
+            ("daily_notes_path", self.daily_notes_path.clone()),
+            ("project_path", self.project_path.clone()),
+        ] {
+            mapping.insert(key.into(), value.into());
+        }
+        serde_yaml::to_string(&mapping).unwrap()
+    }
+
+    // Checks the config as it will be read, with the token given directly since
+    // its source may only be usable once the file is written
+    pub fn validate(&self) -> Result<(), Vec<ConfigProblem>> {
+        ConfigInner::parse(&self.to_yaml_with(&TokenSource::Plain(self.token.clone()))).map(|_| ())
+    }
+
+    // Refuses to write a config that would not load
+    pub fn write(&self, path: &Path) -> Result<(), Vec<ConfigProblem>> {
+        self.validate()?;
+        let io_problem = |error: std::io::Error| {
+            vec![ConfigProblem {
+                line: None,
+                message: format!("could not write `{}`: {}", path.display(), error),
+            }]
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(io_problem)?;
+        }
+        std::fs::write(path, self.to_yaml()).map_err(io_problem)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn normalizes_site_names() {
+        assert_eq!(site_name("mycompany"), "mycompany");
+        assert_eq!(site_name(" https://mycompany.atlassian.net/ "), "mycompany");
+        assert_eq!(
+            site_name("mycompany.atlassian.net/jira/software"),
+            "mycompany"
+        );
+    }
+
+    #[test]
+    fn writes_a_loadable_config() {
+        let vault = crate::test_vault::temporary_vault("setup");
+        std::fs::create_dir_all(vault.join(".obsidian")).unwrap();
+        std::fs::create_dir_all(vault.join("Journal/Daily")).unwrap();
+
+        assert_eq!(
+            find_vault(&vault.join("Journal/Daily")),
+            Some(vault.clone())
+        );
+        assert_eq!(
+            list_folders(&vault),
+            ["Daily", "Journal", "Journal/Daily", "Projects"]
+        );
+
+        let mut new_config = NewConfig {
+            jira_url: site_name("mycompany"),
+            user_mail: String::from("me@mycompany.com"),
+            user_id: String::from("1234"),
+            token: Secret::new(String::from("secret")),
+            token_source: TokenSource::Env(String::from("SOME_UNSET_VARIABLE")),
+            vault_path: vault.clone(),
+            daily_notes_path: String::from("Journal/Daily"),
+            project_path: String::from("Projects"),
+        };
+        let path = vault.join("config.yaml");
+        new_config.write(&path).unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert!(text.contains("token_env: SOME_UNSET_VARIABLE"));
+        assert!(!text.contains("secret"));
+
+        new_config.token_source = TokenSource::Plain(new_config.token.clone());
+        new_config.write(&path).unwrap();
+        let config =
+            crate::config::ConfigInner::parse(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(config.get_user_id(), "1234");
+        assert_eq!(config.get_daily_notes_path(), "Journal/Daily");
+
+        new_config.project_path = String::from("Missing");
+        assert!(new_config.write(&path).is_err());
+    }
+}
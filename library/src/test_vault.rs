@@ -0,0 +1,25 @@
+use std::path::{Path, PathBuf};
+
+use crate::config::ConfigInner;
+use crate::context::Context;
+
+// An empty vault with a `Daily` and a `Projects` folder, named after the test
+pub fn temporary_vault(name: &str) -> PathBuf {
+    let vault = std::env::temp_dir().join(format!("obsidian-jira-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&vault);
+    std::fs::create_dir_all(vault.join("Daily")).unwrap();
+    std::fs::create_dir_all(vault.join("Projects")).unwrap();
+    vault
+}
+
+// With the daily notes in `Daily` and the tasks in `Projects`, other keys can be added at the end
+pub fn config_text(vault: &Path) -> String {
+    format!(
+        "jira_url: mycompany\nuser_mail: me@mycompany.com\nuser_id: '1234'\njira_token: secret\nvault_path: {}\ndaily_notes_path: Daily\nproject_path: Projects\n",
+        vault.display()
+    )
+}
+
+pub fn context_for(vault: &Path) -> Context {
+    Context::new(ConfigInner::parse(&config_text(vault)).unwrap())
+}
@@ -0,0 +1,161 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use library::credentials::{self, Secret, TokenSource};
+use library::obsidian::settings::VaultSettings;
+use library::setup::{self, NewConfig};
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+/*
+    `tui init [--config path]` asks for the Jira credentials and the vault
+    folders, checks them and writes the config file.
+*/
+pub fn run(config_path: Option<PathBuf>) -> Result<()> {
+    let Some(path) = config_path.or_else(library::config::user_config_path) else {
+        return Err("No place to write the config, give one with --config".into());
+    };
+    if path.exists() && !confirm(&format!("{} exists, overwrite it?", path.display()))? {
+        return Ok(());
+    }
+
+    let (jira_url, user_mail, token, user_id) = ask_credentials()?;
+
+    let current_dir = std::env::current_dir()?;
+    let detected = setup::find_vault(&current_dir);
+    let vault_path = loop {
+        let answer = ask(
+            "Obsidian vault",
+            detected.as_deref().and_then(|x| x.to_str()),
+        )?;
+        let vault_path = PathBuf::from(answer);
+        if vault_path.join(".obsidian").is_dir()
+            || confirm("There is no .obsidian folder there, use it anyway?")?
+        {
+            break vault_path;
+        }
+    };
+
+    let folders = setup::list_folders(&vault_path);
+    for (index, folder) in folders.iter().enumerate() {
+        println!("{:>3}. {}", index + 1, folder);
+    }
+    let settings = VaultSettings::read(&vault_path);
+    let daily_notes_path = pick_folder(
+        "Daily notes folder",
+        &folders,
+        settings.get_daily_notes_folder().filter(|x| !x.is_empty()),
+    )?;
+    let project_path = pick_folder("Project folder", &folders, settings.get_new_note_folder())?;
+
+    let token_source = ask_token_source(&path, &token)?;
+
+    let new_config = NewConfig {
+        jira_url,
+        user_mail,
+        user_id,
+        token,
+        token_source,
+        vault_path,
+        daily_notes_path,
+        project_path,
+    };
+    if let Err(problems) = new_config.write(&path) {
+        for problem in problems {
+            eprintln!("{}", problem.message);
+        }
+        return Err("The config was not written".into());
+    }
+    println!("Wrote {}", path.display());
+    Ok(())
+}
+
+fn ask(question: &str, default: Option<&str>) -> Result<String> {
+    loop {
+        match default {
+            Some(default) => print!("{} [{}]: ", question, default),
+            None => print!("{}: ", question),
+        }
+        std::io::stdout().flush()?;
+
+        let mut answer = String::new();
+        if std::io::stdin().read_line(&mut answer)? == 0 {
+            return Err("No answer".into());
+        }
+        match (answer.trim(), default) {
+            ("", Some(default)) => return Ok(default.to_owned()),
+            ("", None) => continue,
+            (answer, _) => return Ok(answer.to_owned()),
+        }
+    }
+}
+
+fn confirm(question: &str) -> Result<bool> {
+    let answer = ask(&format!("{} (y/n)", question), Some("n"))?;
+    Ok(answer.eq_ignore_ascii_case("y") || answer.eq_ignore_ascii_case("yes"))
+}
+
+// Asks until Jira accepts them, returns the site, mail, token and account id
+fn ask_credentials() -> Result<(String, String, Secret, String)> {
+    loop {
+        let jira_url = setup::site_name(&ask(
+            "Jira site (mycompany for mycompany.atlassian.net)",
+            None,
+        )?);
+        let user_mail = ask("Email", None)?;
+        let token = credentials::prompt_secret("API token: ")?;
+
+        match setup::verify_credentials(&jira_url, &user_mail, token.expose()) {
+            Ok(user) => {
+                println!("Logged in as {}", user.get_display_name());
+                return Ok((jira_url, user_mail, token, user.get_account_id().to_owned()));
+            }
+            Err(error) => eprintln!("{}", error),
+        }
+    }
+}
+
+// By number in the list or by path, the default comes from the settings of Obsidian
+fn pick_folder(question: &str, folders: &[String], default: Option<&str>) -> Result<String> {
+    let answer = ask(question, default)?;
+    Ok(match answer.parse::<usize>() {
+        Ok(index) if (1..=folders.len()).contains(&index) => folders[index - 1].clone(),
+        _ => answer,
+    })
+}
+
+fn ask_token_source(config_path: &Path, token: &Secret) -> Result<TokenSource> {
+    println!("Where should the token be kept?");
+    println!("  1. In a file encrypted with a passphrase");
+    println!(
+        "  2. In the {} environment variable",
+        credentials::TOKEN_ENV_VAR
+    );
+    println!("  3. Given by a command, like `pass show jira`");
+    println!("  4. In the config file, in plain text");
+
+    loop {
+        match ask("Choice", Some("1"))?.as_str() {
+            "1" => {
+                let passphrase = credentials::prompt_secret("Passphrase: ")?;
+                if credentials::prompt_secret("Passphrase again: ")? != passphrase {
+                    eprintln!("The passphrases are different");
+                    continue;
+                }
+                let token_path = config_path.with_file_name("token.age");
+                let encrypted =
+                    credentials::encrypt(token.expose(), passphrase.expose().to_owned())?;
+                if let Some(parent) = token_path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::write(&token_path, encrypted)?;
+                println!("Wrote {}", token_path.display());
+                return Ok(TokenSource::File(token_path));
+            }
+            "2" => return Ok(TokenSource::default()),
+            "3" => return Ok(TokenSource::Command(ask("Command", None)?)),
+            "4" => return Ok(TokenSource::Plain(token.clone())),
+            _ => continue,
+        }
+    }
+}
@@ -1,4 +1,8 @@
-use library::{config, obsidian::TaskTimeData};
+mod init;
+mod plan;
+mod query;
+
+use library::{config::ConfigFile, context::Context, obsidian::TaskTimeData};
 use notify::{self, RecursiveMode, Watcher};
 use time;
 
@@ -8,40 +12,180 @@ use ratatui::{self, prelude::Constraint};
 type Terminal = ratatui::Terminal<ratatui::backend::CrosstermBackend<std::io::Stdout>>;
 
 struct App {
+    config_file: ConfigFile,
+    context: Context,
     data: library::obsidian::SprintTimeBalance,
+    // Shown to the user, like why a profile could not be loaded
+    message: String,
+    // Saved view of the config shown on the right, and its tasks
+    view: Option<String>,
+    view_lines: Vec<String>,
 }
 
 impl App {
-    pub fn new() -> Self {
-        let iso_week = config::CONFIG
-            .get_week()
-            .unwrap_or_else(|| time::OffsetDateTime::now_local().unwrap().date().iso_week());
-
+    pub fn new(config_file: ConfigFile, context: Context) -> Self {
+        let data = load_sprint_balance(&context);
         Self {
-            data: library::obsidian::SprintTimeBalance::new(2024, iso_week),
+            config_file,
+            context,
+            data,
+            message: String::new(),
+            view: None,
+            view_lines: Vec::new(),
         }
     }
     pub fn reload(&mut self) {
-        let iso_week = config::CONFIG
-            .get_week()
-            .unwrap_or_else(|| time::OffsetDateTime::now_local().unwrap().date().iso_week());
+        self.context.reload_notes();
+        self.data = load_sprint_balance(&self.context);
+        self.load_view();
+    }
+    // Moves to the next saved view, and back to none after the last one
+    pub fn next_view(&mut self) {
+        let views = self.context.get_config().get_views();
+        self.view = match &self.view {
+            None => views.keys().next().cloned(),
+            Some(current) => views.keys().find(|x| *x > current).cloned(),
+        };
+        if views.is_empty() {
+            self.message = String::from("The config has no views");
+        }
+        self.load_view();
+    }
+    fn load_view(&mut self) {
+        self.view_lines = match &self.view {
+            None => Vec::new(),
+            Some(name) => match query::find_view(&self.context, name) {
+                Ok(query) => query::format_groups(&query.run(&self.context)),
+                Err(error) => vec![error.to_string()],
+            },
+        };
+    }
+    // Moves to the next profile of the config file, the current one stays on errors
+    pub fn switch_profile(&mut self, terminal: &mut Terminal) {
+        let profiles = self.config_file.get_profile_names();
+        let Some(current) = self.context.get_config().get_profile() else {
+            self.message = String::from("The config file has no profiles");
+            return;
+        };
+        let index = profiles.iter().position(|x| x == current).unwrap_or(0);
+        let next = &profiles[(index + 1) % profiles.len()];
+
+        let loaded = self
+            .config_file
+            .load_profile(Some(next))
+            .map_err(|x| x.to_string());
+        match loaded.and_then(|config| resolve_token_outside(terminal, config)) {
+            Ok(config) => {
+                self.context = Context::new(config);
+                self.message = String::new();
+                self.view = None;
+                self.reload();
+            }
+            Err(error) => self.message = error,
+        }
+    }
+}
 
-        self.data = library::obsidian::SprintTimeBalance::new(2024, iso_week);
+// Asks for the passphrase of the token with the terminal back to normal, the
+// prompt would otherwise be drawn over the table and lose its keystrokes
+fn resolve_token_outside(
+    terminal: &mut Terminal,
+    config: library::config::ConfigInner,
+) -> Result<library::config::ConfigInner, String> {
+    if !config.jira_token_needs_prompt() {
+        return Ok(config);
     }
+    let suspend = || -> std::io::Result<()> {
+        crossterm::terminal::disable_raw_mode()?;
+        crossterm::execute!(std::io::stdout(), crossterm::terminal::LeaveAlternateScreen)
+    };
+    let resume = |terminal: &mut Terminal| -> std::io::Result<()> {
+        crossterm::execute!(std::io::stdout(), crossterm::terminal::EnterAlternateScreen)?;
+        crossterm::terminal::enable_raw_mode()?;
+        terminal.clear()
+    };
+    suspend().map_err(|x| x.to_string())?;
+    let resolved = config.resolve_jira_token();
+    resume(terminal).map_err(|x| x.to_string())?;
+    resolved.map(|_| config)
+}
+
+fn load_sprint_balance(context: &Context) -> library::obsidian::SprintTimeBalance {
+    let iso_week = context
+        .get_config()
+        .get_week()
+        .unwrap_or_else(|| time::OffsetDateTime::now_local().unwrap().date().iso_week());
+
+    library::obsidian::SprintTimeBalance::new(context, 2024, iso_week)
 }
 
 enum Event {
     Quit,
     Reload,
+    SwitchProfile,
+    NextView,
     Other,
 }
 
+// The value of `--name value` or `--name=value`
+fn get_argument(name: &str) -> Option<String> {
+    let flag = format!("--{}", name);
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == flag {
+            return args.next();
+        }
+        if let Some(value) = arg.strip_prefix(&flag).and_then(|x| x.strip_prefix('=')) {
+            return Some(value.to_owned());
+        }
+    }
+    None
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let config_path = get_argument("config").map(std::path::PathBuf::from);
+    if std::env::args().nth(1).as_deref() == Some("init") {
+        return init::run(config_path);
+    }
+    let loaded = ConfigFile::find(config_path.as_deref()).and_then(|config_file| {
+        let config = config_file.load_profile(get_argument("profile").as_deref())?;
+        Ok((config_file, config))
+    });
+    let (config_file, config) = match loaded {
+        Ok(loaded) => loaded,
+        Err(error) => {
+            eprint!("{}", error);
+            std::process::exit(1);
+        }
+    };
+    // Before the terminal is taken over, a passphrase may be asked for
+    if let Err(error) = config.resolve_jira_token() {
+        eprintln!("{}", error);
+        std::process::exit(1);
+    }
+    if std::env::args().nth(1).as_deref() == Some("query") {
+        let text = std::env::args().nth(2).filter(|x| !x.starts_with("--"));
+        // Printed like the config errors, not with Debug
+        if let Err(error) = query::run(&Context::new(config), text, get_argument("view")) {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    if std::env::args().nth(1).as_deref() == Some("plan") {
+        let write = std::env::args().any(|x| x == "--write");
+        if let Err(error) = plan::run(&Context::new(config), get_argument("date"), write) {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
     // Setup
     let mut terminal = setup_terminal()?;
     // Run the whole thing
 
-    let mut app = App::new();
+    let mut app = App::new(config_file, Context::new(config));
     run(&mut app, &mut terminal)?;
 
     // Bye!
@@ -82,6 +226,8 @@ fn run(
         match event {
             Event::Quit => break,
             Event::Reload => app.reload(),
+            Event::SwitchProfile => app.switch_profile(terminal),
+            Event::NextView => app.next_view(),
             Event::Other => {}
         };
     }
@@ -97,9 +243,16 @@ fn render_app(app: &App, frame: &mut ratatui::Frame) {
 
     let n_rows_in_sprint = app.data.tasks.iter().filter(|x| x.in_sprint).count();
 
-    let right_block = ratatui::widgets::Block::bordered().title("Greeting");
+    let right_block = match &app.view {
+        Some(view) => ratatui::widgets::Block::bordered().title(view.as_str()),
+        None => ratatui::widgets::Block::bordered().title("Greeting"),
+    };
+    let title = match app.context.get_config().get_profile() {
+        Some(profile) => format!("Table ({})", profile),
+        None => String::from("Table"),
+    };
     let left_block = ratatui::widgets::Block::bordered()
-        .title("Table")
+        .title(title)
         .padding(ratatui::widgets::Padding::symmetric(2, 1));
 
     let left_inner = left_block.inner(layout[0]);
@@ -204,7 +357,31 @@ fn render_app(app: &App, frame: &mut ratatui::Frame) {
         );
     }
 
-    let greeting = ratatui::widgets::Paragraph::new("Youu what the hell are you trying");
+    let greeting = match &app.view {
+        Some(_) => ratatui::widgets::Paragraph::new(format!(
+            "{}\n\n{}",
+            app.view_lines.join("\n"),
+            app.message
+        )),
+        None if !app.data.unmatched.is_empty() || !app.data.warnings.is_empty() => {
+            let mut text = String::new();
+            if !app.data.warnings.is_empty() {
+                text += &format!("Warnings:\n{}\n\n", app.data.warnings.join("\n"));
+            }
+            if !app.data.unmatched.is_empty() {
+                text += &format!(
+                    "Planned blocks matching no task:\n{}\n\n",
+                    app.data.unmatched.join("\n")
+                );
+            }
+            ratatui::widgets::Paragraph::new(text + &app.message)
+        }
+        None => ratatui::widgets::Paragraph::new(format!(
+            "Youu what the hell are you trying\n\n{}",
+            app.message
+        )),
+    }
+    .wrap(ratatui::widgets::Wrap { trim: false });
     frame.render_widget(left_block, layout[0]);
     frame.render_widget(right_block, layout[1]);
     frame.render_widget(greeting.clone(), right_inner);
@@ -391,6 +568,8 @@ fn get_event() -> Result<Event, Box<dyn std::error::Error>> {
     match key.code {
         crossterm::event::KeyCode::Char('q') => Ok(Event::Quit),
         crossterm::event::KeyCode::Char('r') => Ok(Event::Reload),
+        crossterm::event::KeyCode::Char('p') => Ok(Event::SwitchProfile),
+        crossterm::event::KeyCode::Char('v') => Ok(Event::NextView),
         _ => Ok(Event::Other),
     }
 }
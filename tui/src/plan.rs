@@ -0,0 +1,42 @@
+use library::commons::Date;
+use library::context::Context;
+use library::obsidian::planner::daily_note_path;
+use library::obsidian::scheduler::{propose_day_plan, write_day_plan};
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+/*
+    `tui plan [--date 2024-03-05] [--write]` prints the blocks proposed for the
+    remaining work of the sprint, for today by default. With `--write` they are
+    added to the daily note, after the blocks already there.
+*/
+pub fn run(context: &Context, date: Option<String>, write: bool) -> Result<()> {
+    let date: Date = match date {
+        Some(text) => time::Date::parse(
+            &text,
+            time::macros::format_description!("[year]-[month]-[day]"),
+        )
+        .map_err(|_| format!("Dates are written like 2024-03-05, found `{}`", text))?
+        .into(),
+        None => time::OffsetDateTime::now_local()?.date().into(),
+    };
+
+    let blocks = propose_day_plan(context, &date);
+    if blocks.is_empty() {
+        println!("Nothing to add to the plan of {}", date.get_date());
+        return Ok(());
+    }
+    let format = context.get_config().get_planner().get_format();
+    for block in &blocks {
+        println!("{}", block.to_line(format));
+    }
+    if write {
+        write_day_plan(context, &date, &blocks)?;
+        println!(
+            "Added {} blocks to {}",
+            blocks.len(),
+            daily_note_path(context, &date).display()
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,68 @@
+use library::context::Context;
+use library::obsidian::query::{Query, TaskGroup};
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+/*
+    `tui query "WHERE status != 'Done'"` or `tui query --view name` prints the
+    tasks found, the same way the TUI shows its views.
+*/
+pub fn run(context: &Context, query: Option<String>, view: Option<String>) -> Result<()> {
+    let query = match (query, view) {
+        (_, Some(view)) => find_view(context, &view)?,
+        (Some(query), None) => Query::parse(&query)?,
+        (None, None) => {
+            let views = context.get_config().get_views();
+            if views.is_empty() {
+                return Err("Give a query, like `tui query \"WHERE status != 'Done'\"`".into());
+            }
+            for (name, query) in views {
+                println!("{}: {}", name, query);
+            }
+            return Ok(());
+        }
+    };
+
+    for line in format_groups(&query.run(context)) {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+// A saved view of the config, by name
+pub fn find_view(context: &Context, name: &str) -> Result<Query> {
+    let Some(text) = context.get_config().get_views().get(name) else {
+        return Err(format!("There is no view `{}` in the config", name).into());
+    };
+    Ok(Query::parse(text)?)
+}
+
+pub fn format_groups(groups: &[TaskGroup]) -> Vec<String> {
+    let mut lines = Vec::new();
+    for group in groups {
+        let indentation = match &group.key {
+            Some(key) if key.is_empty() => {
+                lines.push(String::from("(none)"));
+                "  "
+            }
+            Some(key) => {
+                lines.push(key.clone());
+                "  "
+            }
+            None => "",
+        };
+        for task in &group.tasks {
+            lines.push(format!(
+                "{}- {} ({}, {})",
+                indentation,
+                task.get_name(),
+                task.get_status().get_name(),
+                task.get_priority().to_number()
+            ));
+        }
+    }
+    if lines.is_empty() {
+        lines.push(String::from("No tasks found"));
+    }
+    lines
+}